@@ -0,0 +1,108 @@
+//! Reporting of Aura equivocations.
+//!
+//! The Aura import queue notices when an authority authors two blocks in the same slot, but only
+//! logs it. This task watches imported blocks for such equivocations and reports them on chain
+//! through the `AuraEquivocationApi` runtime API. Reported authorities are disabled for the rest
+//! of the session.
+//!
+//! Blocks imported while the node is major syncing are not checked: their slots are long past,
+//! so an equivocation found there could not be reported anymore.
+
+use crate::service::FullClient;
+use futures::StreamExt;
+use pez_solochain_template_runtime::opaque::{Block, Header};
+use pezpallet_aura_equivocation::AuraEquivocationApi;
+use pezsc_client_api::BlockchainEvents;
+use pezsc_transaction_pool_api::OffchainTransactionPoolFactory;
+use pezsp_api::{ApiExt, ProvideRuntimeApi};
+use pezsp_blockchain::HeaderBackend;
+use pezsp_consensus::SyncOracle;
+use pezsp_consensus_aura::{
+	sr25519::AuthoritySignature as AuraSignature, AuraApi, Slot, SlotDuration,
+};
+use pezsp_runtime::traits::Header as _;
+use std::sync::Arc;
+
+const LOG_TARGET: &str = "aura-equivocation";
+
+/// Checks every imported block for an equivocation of its author and reports the ones found.
+pub async fn report_equivocations(
+	client: Arc<FullClient>,
+	offchain_tx_pool_factory: OffchainTransactionPoolFactory<Block>,
+	slot_duration: SlotDuration,
+	sync_oracle: impl SyncOracle,
+) {
+	let mut imported_blocks = client.import_notification_stream();
+
+	while let Some(notification) = imported_blocks.next().await {
+		if sync_oracle.is_major_syncing() {
+			continue;
+		}
+		if let Err(err) =
+			check_block(&client, &offchain_tx_pool_factory, &notification.header, slot_duration)
+		{
+			log::warn!(
+				target: LOG_TARGET,
+				"Failed to check block #{} for equivocations: {err}",
+				notification.header.number(),
+			);
+		}
+	}
+}
+
+fn check_block(
+	client: &FullClient,
+	offchain_tx_pool_factory: &OffchainTransactionPoolFactory<Block>,
+	header: &Header,
+	slot_duration: SlotDuration,
+) -> Result<(), String> {
+	let slot = pezsc_consensus_aura::find_pre_digest::<Block, AuraSignature>(header)
+		.map_err(|e| e.to_string())?;
+	let authorities =
+		client.runtime_api().authorities(*header.parent_hash()).map_err(|e| e.to_string())?;
+	if authorities.is_empty() {
+		return Ok(());
+	}
+	let author = &authorities[(*slot % authorities.len() as u64) as usize];
+
+	let slot_now = Slot::from_timestamp(pezsp_timestamp::Timestamp::current(), slot_duration);
+	let Some(equivocation_proof) =
+		pezsc_consensus_slots::check_equivocation(client, slot_now, slot, header, author)
+			.map_err(|e| e.to_string())?
+	else {
+		return Ok(());
+	};
+
+	log::warn!(
+		target: LOG_TARGET,
+		"⚠️ Authority {author:?} authored more than one block in slot {slot}, reporting it",
+	);
+
+	let best_hash = client.info().best_hash;
+	let mut runtime_api = client.runtime_api();
+	// The report is submitted as an unsigned transaction from within the runtime.
+	runtime_api.register_extension(offchain_tx_pool_factory.offchain_transaction_pool(best_hash));
+
+	// The ownership proof has to be for the session the offence happened in, which the best
+	// block may already have left.
+	let Some(key_owner_proof) = runtime_api
+		.generate_key_ownership_proof(header.hash(), equivocation_proof.offender.clone())
+		.map_err(|e| e.to_string())?
+	else {
+		log::debug!(
+			target: LOG_TARGET,
+			"Equivocation offender {author:?} is not part of the validator set at block #{}",
+			header.number(),
+		);
+		return Ok(());
+	};
+
+	runtime_api
+		.submit_report_equivocation_unsigned_extrinsic(
+			best_hash,
+			equivocation_proof,
+			key_owner_proof,
+		)
+		.map_err(|e| e.to_string())?
+		.ok_or_else(|| "the runtime failed to submit the report".to_string())
+}
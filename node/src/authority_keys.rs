@@ -0,0 +1,155 @@
+//! Provisioning of the authority keys from a file at startup.
+//!
+//! `--authority-keys <file>` reads the secret URIs of the block authoring key (`aura`, or `babe`
+//! with the `babe` feature) and of the GRANDPA key from a JSON or TOML file, e.g.
+//!
+//! ```toml
+//! aura = "//Alice"
+//! grandpa = "bottom drive obey lake curtain smoke basket hold race lonely fit walk//Alice"
+//! # Optional, the session keys registered on chain, as returned by `author_rotateKeys`.
+//! session_keys = "0xd435…b0ee"
+//! ```
+//!
+//! Every URI is either a seed, a mnemonic or a derivation path on top of one of them. The keys
+//! are checked against the `SessionKeys` of the runtime before they are inserted into the
+//! keystore, and the node refuses to start if they do not match.
+
+use crate::service::FullClient;
+use codec::Encode;
+use pez_solochain_template_runtime::{BlockAuthorityId, SessionKeys};
+use pezsp_api::ProvideRuntimeApi;
+use pezsp_blockchain::HeaderBackend;
+#[cfg(not(feature = "babe"))]
+use pezsp_consensus_aura::sr25519::AuthorityPair as BlockAuthorityPair;
+#[cfg(feature = "babe")]
+use pezsp_consensus_babe::AuthorityPair as BlockAuthorityPair;
+use pezsp_consensus_grandpa::{AuthorityId as GrandpaId, AuthorityPair as GrandpaPair};
+use pezsp_core::{
+	bytes::from_hex,
+	crypto::{key_types, ByteArray, KeyTypeId, Pair},
+	hexdisplay::HexDisplay,
+};
+use pezsp_keystore::KeystorePtr;
+use pezsp_session::SessionKeys as SessionKeysApi;
+use serde::Deserialize;
+use std::path::Path;
+
+/// Block authoring key type, `aura` by default or `babe` with the `babe` feature.
+#[cfg(not(feature = "babe"))]
+const BLOCK_AUTHORITY_KEY_TYPE: KeyTypeId = key_types::AURA;
+/// Block authoring key type, `aura` by default or `babe` with the `babe` feature.
+#[cfg(feature = "babe")]
+const BLOCK_AUTHORITY_KEY_TYPE: KeyTypeId = key_types::BABE;
+
+/// The content of an `--authority-keys` file.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct AuthorityKeysFile {
+	/// Secret URI of the block authoring key.
+	#[cfg(not(feature = "babe"))]
+	aura: String,
+	/// Secret URI of the block authoring key.
+	#[cfg(feature = "babe")]
+	babe: String,
+	/// Secret URI of the GRANDPA key.
+	grandpa: String,
+	/// The SCALE encoded session keys the URIs are expected to derive, as a hex string.
+	#[serde(default)]
+	session_keys: Option<String>,
+}
+
+impl AuthorityKeysFile {
+	fn read(path: &Path) -> Result<Self, String> {
+		let content = std::fs::read_to_string(path)
+			.map_err(|e| format!("failed to read {}: {e}", path.display()))?;
+		let parsed = match path.extension().and_then(|extension| extension.to_str()) {
+			Some("toml") => toml::from_str(&content).map_err(|e| e.to_string()),
+			_ => serde_json::from_str(&content).map_err(|e| e.to_string()),
+		};
+		parsed.map_err(|e| format!("failed to parse {}: {e}", path.display()))
+	}
+
+	#[cfg(not(feature = "babe"))]
+	fn block_authority(&self) -> &str {
+		&self.aura
+	}
+
+	#[cfg(feature = "babe")]
+	fn block_authority(&self) -> &str {
+		&self.babe
+	}
+}
+
+/// Inserts the keys of the file at `path` into `keystore`, once they are checked against the
+/// `SessionKeys` of the runtime at the best block.
+pub fn provision(path: &Path, client: &FullClient, keystore: &KeystorePtr) -> Result<(), String> {
+	let file = AuthorityKeysFile::read(path)?;
+	let block_authority = public::<BlockAuthorityPair>(file.block_authority(), "block authoring")?;
+	let grandpa = public::<GrandpaPair>(&file.grandpa, "GRANDPA")?;
+
+	let derived = [
+		(block_authority.to_raw_vec(), BLOCK_AUTHORITY_KEY_TYPE),
+		(grandpa.to_raw_vec(), key_types::GRANDPA),
+	];
+	let encoded = session_keys(block_authority, grandpa).encode();
+	if let Some(expected) = &file.session_keys {
+		let expected =
+			from_hex(expected).map_err(|e| format!("invalid `session_keys` {expected}: {e}"))?;
+		if expected != encoded {
+			return Err(format!(
+				"the keys in {} do not derive the session keys 0x{}",
+				path.display(),
+				HexDisplay::from(&expected),
+			));
+		}
+	}
+
+	// Decoding through the runtime catches files written for a different set of session keys.
+	let best_hash = client.info().best_hash;
+	let decoded = client
+		.runtime_api()
+		.decode_session_keys(best_hash, encoded)
+		.map_err(|e| format!("failed to decode the session keys: {e}"))?
+		.ok_or("the runtime does not accept the keys as session keys")?;
+	if decoded != derived {
+		let key_types =
+			decoded.iter().map(|(_, key_type)| key_type_name(*key_type)).collect::<Vec<_>>();
+		return Err(format!(
+			"the runtime expects session keys of the types {}, which {} does not provide",
+			key_types.join(", "),
+			path.display(),
+		));
+	}
+
+	for (suri, (public, key_type)) in
+		[file.block_authority(), &file.grandpa].into_iter().zip(derived)
+	{
+		keystore.insert(key_type, suri, &public).map_err(|()| {
+			format!("failed to insert the {} key into the keystore", key_type_name(key_type))
+		})?;
+	}
+
+	log::info!("🔑 Loaded the authority keys from {}", path.display());
+	Ok(())
+}
+
+/// Derives the public key of `suri`, naming the key with `name` in errors.
+fn public<P: Pair>(suri: &str, name: &str) -> Result<P::Public, String> {
+	P::from_string(suri, None)
+		.map(|pair| pair.public())
+		.map_err(|e| format!("invalid {name} key: {e:?}"))
+}
+
+#[cfg(not(feature = "babe"))]
+fn session_keys(aura: BlockAuthorityId, grandpa: GrandpaId) -> SessionKeys {
+	SessionKeys { aura, grandpa }
+}
+
+#[cfg(feature = "babe")]
+fn session_keys(babe: BlockAuthorityId, grandpa: GrandpaId) -> SessionKeys {
+	SessionKeys { babe, grandpa }
+}
+
+fn key_type_name(key_type: KeyTypeId) -> String {
+	String::from_utf8_lossy(&key_type.0).into_owned()
+}
@@ -0,0 +1,81 @@
+//! Aura authoring backoff while GRANDPA finality lags behind the chain head.
+
+use crate::cli::BackoffParams;
+use bizinikiwi_prometheus_endpoint::{register, Counter, PrometheusError, Registry, U64};
+use pez_solochain_template_runtime::BlockNumber;
+use pezsc_consensus_slots::{
+	BackoffAuthoringBlocksStrategy, BackoffAuthoringOnFinalizedHeadLagging,
+};
+use pezsp_consensus_aura::Slot;
+
+/// Backs off authoring like [`BackoffAuthoringOnFinalizedHeadLagging`], but also reports every
+/// skipped slot in the logs and through Prometheus.
+pub struct AuthoringBackoff {
+	inner: BackoffAuthoringOnFinalizedHeadLagging<BlockNumber>,
+	skipped_slots: Option<Counter<U64>>,
+}
+
+impl AuthoringBackoff {
+	/// Creates the backoff strategy configured by `params`, or `None` if backoff is disabled.
+	pub fn new(
+		params: &BackoffParams,
+		registry: Option<&Registry>,
+	) -> Result<Option<Self>, PrometheusError> {
+		if params.no_backoff_authoring {
+			return Ok(None);
+		}
+
+		let skipped_slots = registry
+			.map(|registry| {
+				register(
+					Counter::new(
+						"authoring_backoff_skipped_slots_total",
+						"Number of slots in which block authoring was skipped because finality lags",
+					)?,
+					registry,
+				)
+			})
+			.transpose()?;
+
+		Ok(Some(Self {
+			inner: BackoffAuthoringOnFinalizedHeadLagging {
+				max_interval: params.backoff_max_interval,
+				unfinalized_slack: params.backoff_unfinalized_slack,
+				authoring_bias: params.backoff_authoring_bias,
+			},
+			skipped_slots,
+		}))
+	}
+}
+
+impl BackoffAuthoringBlocksStrategy<BlockNumber> for AuthoringBackoff {
+	fn should_backoff(
+		&self,
+		chain_head_number: BlockNumber,
+		chain_head_slot: Slot,
+		finalized_number: BlockNumber,
+		slot_now: Slot,
+		logging_target: &str,
+	) -> bool {
+		let backoff = self.inner.should_backoff(
+			chain_head_number,
+			chain_head_slot,
+			finalized_number,
+			slot_now,
+			logging_target,
+		);
+
+		if backoff {
+			log::info!(
+				target: logging_target,
+				"⏸️  Skipping slot {slot_now}: best block #{chain_head_number} is {} blocks ahead of finalized #{finalized_number}",
+				chain_head_number.saturating_sub(finalized_number),
+			);
+			if let Some(skipped_slots) = &self.skipped_slots {
+				skipped_slots.inc();
+			}
+		}
+
+		backoff
+	}
+}
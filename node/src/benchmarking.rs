@@ -2,94 +2,359 @@
 //!
 //! Should only be used for benchmarking as it may break in other contexts.
 
-use crate::service::FullClient;
+use crate::{cli::BenchmarkCallParams, service::FullClient};
 
+use codec::{Decode, DecodeAll};
+use frame_metadata::{v15::RuntimeMetadataV15, RuntimeMetadata, RuntimeMetadataPrefixed};
 use pez_solochain_template_runtime as runtime;
+use pezframe_benchmarking_cli::ExtrinsicBuilder;
 use pezsc_cli::Result;
 use pezsc_client_api::BlockBackend;
-use pezsp_core::{Encode, Pair};
+use pezsp_api::{Metadata, ProvideRuntimeApi};
+use pezsp_blockchain::HeaderBackend;
+use pezsp_core::{bytes::from_hex, crypto::Ss58Codec, Encode, Pair};
 use pezsp_inherents::{InherentData, InherentDataProvider};
 use pezsp_keyring::Sr25519Keyring;
 use pezsp_runtime::{OpaqueExtrinsic, SaturatedConversion};
-use runtime::{AccountId, Balance, BalancesCall, SystemCall};
+use runtime::{AccountId, BalancesCall, SystemCall, EXISTENTIAL_DEPOSIT};
+use scale_info::TypeDef;
+use serde::Deserialize;
 
-use std::{sync::Arc, time::Duration};
+use std::{path::Path, sync::Arc, time::Duration};
 
-/// Generates extrinsics for the `benchmark overhead` command.
+type SudoCall = pezpallet_sudo::Call<runtime::Runtime>;
+type TemplateCall = pezpallet_template::Call<runtime::Runtime>;
+
+/// Development accounts signing benchmark extrinsics, in the order they join a [`SignerPool`].
+///
+/// The first four are endowed by the `dev` chain, all of them by the `local` chain.
+const SIGNERS: [Sr25519Keyring; 12] = [
+	Sr25519Keyring::Bob,
+	Sr25519Keyring::Alice,
+	Sr25519Keyring::BobStash,
+	Sr25519Keyring::AliceStash,
+	Sr25519Keyring::Charlie,
+	Sr25519Keyring::Dave,
+	Sr25519Keyring::Eve,
+	Sr25519Keyring::Ferdie,
+	Sr25519Keyring::CharlieStash,
+	Sr25519Keyring::DaveStash,
+	Sr25519Keyring::EveStash,
+	Sr25519Keyring::FerdieStash,
+];
+
+/// Accounts taking turns in signing benchmark extrinsics.
+///
+/// Spreading the extrinsics of a block over several senders keeps the nonce checks of a single
+/// sender from skewing the results.
+#[derive(Clone)]
+pub struct SignerPool {
+	signers: Vec<Sr25519Keyring>,
+}
+
+impl SignerPool {
+	/// The first `count` development accounts, starting with Bob.
+	pub fn new(count: usize) -> Self {
+		Self { signers: SIGNERS[..count.clamp(1, SIGNERS.len())].to_vec() }
+	}
+
+	/// The sudo key of the development chains.
+	pub fn sudo() -> Self {
+		Self { signers: vec![Sr25519Keyring::Alice] }
+	}
+
+	/// The signer of the `index`th extrinsic and its nonce.
+	fn signer(&self, index: u32) -> (Sr25519Keyring, u32) {
+		let count = self.signers.len() as u32;
+		(self.signers[(index % count) as usize], index / count)
+	}
+}
+
+/// Generates extrinsics of a [`runtime::RuntimeCall`] for the benchmarks.
 ///
 /// Note: Should only be used for benchmarking.
-pub struct RemarkBuilder {
+pub struct CallBuilder {
 	client: Arc<FullClient>,
+	pezpallet: String,
+	extrinsic: String,
+	call: runtime::RuntimeCall,
+	signers: SignerPool,
 }
 
-impl RemarkBuilder {
-	/// Creates a new [`Self`] from the given client.
-	pub fn new(client: Arc<FullClient>) -> Self {
-		Self { client }
+impl CallBuilder {
+	/// Creates a new [`Self`], benchmarked as `--pallet <pezpallet> --extrinsic <extrinsic>`.
+	pub fn new(
+		client: Arc<FullClient>,
+		pezpallet: impl Into<String>,
+		extrinsic: impl Into<String>,
+		call: runtime::RuntimeCall,
+		signers: SignerPool,
+	) -> Self {
+		Self { client, pezpallet: pezpallet.into(), extrinsic: extrinsic.into(), call, signers }
+	}
+
+	/// Generates `System::remark` extrinsics, as used by the `benchmark overhead` command.
+	pub fn remark(client: Arc<FullClient>, signers: SignerPool) -> Self {
+		Self::new(client, "system", "remark", SystemCall::remark { remark: vec![] }.into(), signers)
+	}
+
+	/// Wraps the call into `Sudo::sudo`, signed by the sudo key and benchmarked as
+	/// `--pallet sudo --extrinsic <pezpallet>_<extrinsic>`.
+	pub fn sudo(self) -> Self {
+		let extrinsic = format!("{}_{}", self.pezpallet, self.extrinsic);
+		let call = SudoCall::sudo { call: Box::new(self.call) }.into();
+		Self::new(self.client, "sudo", extrinsic, call, SignerPool::sudo())
 	}
 }
 
-impl pezframe_benchmarking_cli::ExtrinsicBuilder for RemarkBuilder {
+impl pezframe_benchmarking_cli::ExtrinsicBuilder for CallBuilder {
 	fn pezpallet(&self) -> &str {
-		"system"
+		&self.pezpallet
 	}
 
 	fn extrinsic(&self) -> &str {
-		"remark"
+		&self.extrinsic
 	}
 
 	fn build(&self, nonce: u32) -> std::result::Result<OpaqueExtrinsic, &'static str> {
-		let acc = Sr25519Keyring::Bob.pair();
-		let extrinsic: OpaqueExtrinsic = create_benchmark_extrinsic(
+		let (signer, nonce) = self.signers.signer(nonce);
+		let extrinsic = create_benchmark_extrinsic(
 			self.client.as_ref(),
-			acc,
-			SystemCall::remark { remark: vec![] }.into(),
+			signer.pair(),
+			self.call.clone(),
 			nonce,
-		)
-		.into();
+		)?;
 
-		Ok(extrinsic)
+		Ok(extrinsic.into())
 	}
 }
 
-/// Generates `Balances::TransferKeepAlive` extrinsics for the benchmarks.
+/// The builders registered for the `benchmark extrinsic` command.
 ///
-/// Note: Should only be used for benchmarking.
-pub struct TransferKeepAliveBuilder {
+/// Besides the built-in calls, these are the calls described in the `--benchmark-calls` file.
+pub fn extrinsic_builders(
 	client: Arc<FullClient>,
-	dest: AccountId,
-	value: Balance,
-}
+	params: &BenchmarkCallParams,
+) -> Result<Vec<Box<dyn ExtrinsicBuilder>>> {
+	let signers = SignerPool::new(params.benchmark_signers);
+	let builder = |pezpallet: &str, extrinsic: &str, call: runtime::RuntimeCall| {
+		CallBuilder::new(client.clone(), pezpallet, extrinsic, call, signers.clone())
+	};
 
-impl TransferKeepAliveBuilder {
-	/// Creates a new [`Self`] from the given client.
-	pub fn new(client: Arc<FullClient>, dest: AccountId, value: Balance) -> Self {
-		Self { client, dest, value }
+	let mut builders = vec![
+		CallBuilder::remark(client.clone(), signers.clone()),
+		builder(
+			"balances",
+			"transfer_keep_alive",
+			BalancesCall::transfer_keep_alive {
+				dest: Sr25519Keyring::Alice.to_account_id().into(),
+				value: EXISTENTIAL_DEPOSIT,
+			}
+			.into(),
+		),
+		builder("template", "do_something", TemplateCall::do_something { something: 42 }.into()),
+		builder("template", "cause_error", TemplateCall::cause_error {}.into()),
+		CallBuilder::remark(client.clone(), signers.clone()).sudo(),
+	];
+
+	if let Some(path) = &params.benchmark_calls {
+		let descriptions = read_call_descriptions(path)?;
+		let best_hash = client.info().best_hash;
+		let metadata = client
+			.runtime_api()
+			.metadata_at_version(best_hash, 15)
+			.map_err(|e| format!("failed to get the runtime metadata: {e}"))?
+			.ok_or("the runtime does not provide metadata V15")?;
+		let encoder = CallEncoder::new(&metadata)?;
+
+		for (index, description) in descriptions.iter().enumerate() {
+			let call = encoder.encode(description).map_err(|e| {
+				format!("call {} of --benchmark-calls {}: {e}", index + 1, path.display())
+			})?;
+			let extrinsic = description.name.as_ref().unwrap_or(&description.call);
+			let call_builder = builder(&description.pallet.to_lowercase(), extrinsic, call);
+			builders.push(if description.sudo { call_builder.sudo() } else { call_builder });
+		}
 	}
+
+	Ok(builders.into_iter().map(|builder| Box::new(builder) as Box<dyn ExtrinsicBuilder>).collect())
 }
 
-impl pezframe_benchmarking_cli::ExtrinsicBuilder for TransferKeepAliveBuilder {
-	fn pezpallet(&self) -> &str {
-		"balances"
-	}
+/// Reads the calls described in the `--benchmark-calls` file at `path`.
+fn read_call_descriptions(path: &Path) -> std::result::Result<Vec<CallDescription>, String> {
+	let content = std::fs::read_to_string(path)
+		.map_err(|e| format!("failed to read --benchmark-calls {}: {e}", path.display()))?;
+	serde_json::from_str(&content)
+		.map_err(|e| format!("failed to parse --benchmark-calls {}: {e}", path.display()))
+}
 
-	fn extrinsic(&self) -> &str {
-		"transfer_keep_alive"
+/// A call of a `--benchmark-calls` file.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CallDescription {
+	/// Name of the pallet in the runtime, e.g. `Balances`.
+	pub pallet: String,
+	/// Name of the call, e.g. `transfer_keep_alive`.
+	pub call: String,
+	/// The arguments, as a JSON object of the named arguments, a JSON array of all arguments or
+	/// a hex string of their SCALE encoding.
+	#[serde(default)]
+	pub args: serde_json::Value,
+	/// The name the call is benchmarked under, `call` if unspecified.
+	#[serde(default)]
+	pub name: Option<String>,
+	/// Whether to wrap the call into `Sudo::sudo`.
+	#[serde(default)]
+	pub sudo: bool,
+}
+
+/// Encodes calls described by name through the metadata of the runtime.
+struct CallEncoder {
+	metadata: RuntimeMetadataV15,
+}
+
+impl CallEncoder {
+	fn new(metadata: &[u8]) -> std::result::Result<Self, String> {
+		match RuntimeMetadataPrefixed::decode(&mut &metadata[..]) {
+			Ok(RuntimeMetadataPrefixed(_, RuntimeMetadata::V15(metadata))) => Ok(Self { metadata }),
+			Ok(_) => Err("the runtime metadata is not V15".into()),
+			Err(e) => Err(format!("invalid runtime metadata: {e}")),
+		}
 	}
 
-	fn build(&self, nonce: u32) -> std::result::Result<OpaqueExtrinsic, &'static str> {
-		let acc = Sr25519Keyring::Bob.pair();
-		let extrinsic: OpaqueExtrinsic = create_benchmark_extrinsic(
-			self.client.as_ref(),
-			acc,
-			BalancesCall::transfer_keep_alive { dest: self.dest.clone().into(), value: self.value }
-				.into(),
-			nonce,
-		)
-		.into();
+	fn encode(
+		&self,
+		description: &CallDescription,
+	) -> std::result::Result<runtime::RuntimeCall, String> {
+		let CallDescription { pallet, call, args, .. } = description;
+		let pallet_metadata = self
+			.metadata
+			.pallets
+			.iter()
+			.find(|metadata| metadata.name.eq_ignore_ascii_case(pallet))
+			.ok_or_else(|| format!("unknown pallet {pallet}"))?;
+		let calls = pallet_metadata
+			.calls
+			.as_ref()
+			.and_then(|calls| self.metadata.types.resolve(calls.ty.id))
+			.ok_or_else(|| format!("pallet {pallet} has no calls"))?;
+		let TypeDef::Variant(calls) = &calls.type_def else {
+			return Err(format!("the calls of pallet {pallet} are not an enum"));
+		};
+		let variant = calls
+			.variants
+			.iter()
+			.find(|variant| variant.name == *call)
+			.ok_or_else(|| format!("unknown call {pallet}::{call}"))?;
+
+		let mut encoded = vec![pallet_metadata.index, variant.index];
+		match args {
+			serde_json::Value::String(hex) => {
+				encoded.extend(from_hex(hex).map_err(|e| format!("invalid args of {call}: {e}"))?)
+			},
+			serde_json::Value::Null if variant.fields.is_empty() => {},
+			serde_json::Value::Array(values) if values.len() == variant.fields.len() => {
+				for (field, value) in variant.fields.iter().zip(values) {
+					self.encode_arg(field.ty.id, value, &mut encoded, call)?;
+				}
+			},
+			serde_json::Value::Object(values) if values.len() == variant.fields.len() => {
+				for field in &variant.fields {
+					let name = field.name.as_deref().unwrap_or_default();
+					let value = values
+						.get(name)
+						.ok_or_else(|| format!("missing argument {name} of {call}"))?;
+					self.encode_arg(field.ty.id, value, &mut encoded, call)?;
+				}
+			},
+			_ => {
+				let names = variant.fields.iter().filter_map(|field| field.name.as_deref());
+				return Err(format!(
+					"{call} expects the arguments {}",
+					names.collect::<Vec<_>>().join(", ")
+				));
+			},
+		}
 
-		Ok(extrinsic)
+		runtime::RuntimeCall::decode_all(&mut &encoded[..])
+			.map_err(|e| format!("invalid args of {pallet}::{call}: {e}"))
 	}
+
+	fn encode_arg(
+		&self,
+		type_id: u32,
+		value: &serde_json::Value,
+		encoded: &mut Vec<u8>,
+		call: &str,
+	) -> std::result::Result<(), String> {
+		let value = json_value(value).map_err(|e| format!("invalid args of {call}: {e}"))?;
+		scale_value::scale::encode_as_type(&value, type_id, &self.metadata.types, encoded)
+			.map_err(|e| format!("invalid args of {call}: {e}"))
+	}
+}
+
+/// Converts a JSON value into a SCALE value, to be encoded as the type of a call argument.
+///
+/// Hex strings and SS58 addresses become bytes, decimal strings numbers of up to 128 bits, and
+/// objects with a single capitalized key, like `{ "Id": "5Grw…" }`, enum variants.
+fn json_value(value: &serde_json::Value) -> std::result::Result<scale_value::Value, String> {
+	use scale_value::Value;
+	use serde_json::Value as Json;
+
+	Ok(match value {
+		Json::Null => Value::unnamed_composite([]),
+		Json::Bool(value) => Value::bool(*value),
+		Json::Number(number) => match (number.as_u64(), number.as_i64()) {
+			(Some(value), _) => Value::u128(value.into()),
+			(None, Some(value)) => Value::i128(value.into()),
+			(None, None) => return Err(format!("{number} is not an integer")),
+		},
+		Json::String(string) if string.starts_with("0x") => {
+			Value::from_bytes(from_hex(string).map_err(|e| format!("invalid hex {string}: {e}"))?)
+		},
+		Json::String(string)
+			if !string.is_empty() && string.bytes().all(|b| b.is_ascii_digit()) =>
+		{
+			Value::u128(string.parse().map_err(|e| format!("invalid number {string}: {e}"))?)
+		},
+		Json::String(string) => match AccountId::from_ss58check(string) {
+			Ok(account) => Value::from_bytes(AsRef::<[u8]>::as_ref(&account)),
+			Err(_) => Value::string(string.clone()),
+		},
+		Json::Array(values) => Value::unnamed_composite(
+			values.iter().map(json_value).collect::<std::result::Result<Vec<_>, _>>()?,
+		),
+		Json::Object(values) => match values.iter().next() {
+			Some((name, value))
+				if values.len() == 1 && name.starts_with(|c: char| c.is_ascii_uppercase()) =>
+			{
+				match value {
+					Json::Null => Value::unnamed_variant(name.clone(), []),
+					Json::Array(values) => Value::unnamed_variant(
+						name.clone(),
+						values
+							.iter()
+							.map(json_value)
+							.collect::<std::result::Result<Vec<_>, _>>()?,
+					),
+					Json::Object(fields) => Value::named_variant(
+						name.clone(),
+						fields
+							.iter()
+							.map(|(field, value)| Ok((field.clone(), json_value(value)?)))
+							.collect::<std::result::Result<Vec<_>, String>>()?,
+					),
+					value => Value::unnamed_variant(name.clone(), [json_value(value)?]),
+				}
+			},
+			_ => Value::named_composite(
+				values
+					.iter()
+					.map(|(field, value)| Ok((field.clone(), json_value(value)?)))
+					.collect::<std::result::Result<Vec<_>, String>>()?,
+			),
+		},
+	})
 }
 
 /// Create a transaction using the given `call`.
@@ -100,8 +365,9 @@ pub fn create_benchmark_extrinsic(
 	sender: pezsp_core::sr25519::Pair,
 	call: runtime::RuntimeCall,
 	nonce: u32,
-) -> runtime::UncheckedExtrinsic {
-	let genesis_hash = client.block_hash(0).ok().flatten().expect("Genesis block exists; qed");
+) -> std::result::Result<runtime::UncheckedExtrinsic, &'static str> {
+	let genesis_hash =
+		client.block_hash(0).ok().flatten().ok_or("the genesis hash is not available")?;
 	let best_hash = client.chain_info().best_hash;
 	let best_block = client.chain_info().best_number;
 
@@ -145,12 +411,12 @@ pub fn create_benchmark_extrinsic(
 	);
 	let signature = raw_payload.using_encoded(|e| sender.sign(e));
 
-	runtime::UncheckedExtrinsic::new_signed(
+	Ok(runtime::UncheckedExtrinsic::new_signed(
 		call,
 		pezsp_runtime::AccountId32::from(sender.public()).into(),
 		runtime::Signature::Sr25519(signature),
 		tx_ext,
-	)
+	))
 }
 
 /// Generates inherent data for the `benchmark overhead` command.
@@ -165,3 +431,89 @@ pub fn inherent_benchmark_data() -> Result<InherentData> {
 		.map_err(|e| format!("creating inherent data: {e:?}"))?;
 	Ok(inherent_data)
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn encode(description: serde_json::Value) -> std::result::Result<runtime::RuntimeCall, String> {
+		let metadata = runtime::Runtime::metadata_at_version(15).unwrap();
+		let description = serde_json::from_value(description).unwrap();
+		CallEncoder::new(&metadata)?.encode(&description)
+	}
+
+	#[test]
+	fn call_files_that_cant_be_loaded_are_reported() {
+		let dir = tempfile::tempdir().unwrap();
+		let path = dir.path().join("calls.json");
+
+		let missing = read_call_descriptions(&path).unwrap_err();
+		assert!(
+			missing.starts_with(&format!("failed to read --benchmark-calls {}", path.display()))
+		);
+
+		std::fs::write(&path, r#"[{ "pallet": "Template", "cal": "do_something" }]"#).unwrap();
+		let invalid = read_call_descriptions(&path).unwrap_err();
+		assert!(
+			invalid.starts_with(&format!("failed to parse --benchmark-calls {}", path.display()))
+		);
+		assert!(invalid.contains("unknown field `cal`"), "{invalid}");
+		assert!(invalid.contains("line 1"), "{invalid}");
+	}
+
+	#[test]
+	fn signers_take_turns() {
+		let pool = SignerPool::new(2);
+
+		assert_eq!(pool.signer(0), (Sr25519Keyring::Bob, 0));
+		assert_eq!(pool.signer(1), (Sr25519Keyring::Alice, 0));
+		assert_eq!(pool.signer(2), (Sr25519Keyring::Bob, 1));
+		assert_eq!(SignerPool::new(0).signer(3), (Sr25519Keyring::Bob, 3));
+		assert_eq!(SignerPool::new(100).signers.len(), SIGNERS.len());
+	}
+
+	#[test]
+	fn calls_are_encoded_from_named_and_listed_args() {
+		let expected: runtime::RuntimeCall = TemplateCall::do_something { something: 7 }.into();
+
+		let named = serde_json::json!({
+			"pallet": "Template", "call": "do_something", "args": { "something": 7 }
+		});
+		let listed =
+			serde_json::json!({ "pallet": "template", "call": "do_something", "args": [7] });
+		let hex = serde_json::json!({
+			"pallet": "Template", "call": "do_something", "args": "0x07000000"
+		});
+		assert_eq!(encode(named), Ok(expected.clone()));
+		assert_eq!(encode(listed), Ok(expected.clone()));
+		assert_eq!(encode(hex), Ok(expected));
+	}
+
+	#[test]
+	fn accounts_and_balances_are_encoded() {
+		let bob = Sr25519Keyring::Bob.to_account_id();
+		let description = serde_json::json!({
+			"pallet": "Balances",
+			"call": "transfer_keep_alive",
+			"args": { "dest": { "Id": bob.to_ss58check() }, "value": "1000000000000000000000" },
+		});
+
+		assert_eq!(
+			encode(description),
+			Ok(BalancesCall::transfer_keep_alive {
+				dest: bob.into(),
+				value: 1_000_000_000_000_000_000_000,
+			}
+			.into())
+		);
+	}
+
+	#[test]
+	fn unknown_calls_and_missing_args_are_rejected() {
+		let unknown = serde_json::json!({ "pallet": "Template", "call": "do_nothing" });
+		let missing = serde_json::json!({ "pallet": "Template", "call": "do_something" });
+
+		assert_eq!(encode(unknown), Err("unknown call Template::do_nothing".into()));
+		assert_eq!(encode(missing), Err("do_something expects the arguments something".into()));
+	}
+}
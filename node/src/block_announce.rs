@@ -0,0 +1,195 @@
+//! Validation of block announcements against the Aura authority set.
+//!
+//! Announcements are rejected when the announced header is not sealed by the Aura authority of
+//! its slot, when its slot is more than [`MAX_FUTURE_SLOTS`] ahead of the local clock, or when its
+//! author announces more blocks than allowed within the configured window.
+//! Sync lowers the reputation of the peer that relayed a rejected announcement, and disconnects
+//! it when the header was sealed by someone outside of the authority set.
+//!
+//! The sync protocol does not tell validators which peer an announcement came from, so the rate
+//! limit applies to the announced authors. Every distinct header is only counted once, no matter
+//! how many peers relay it.
+
+use crate::{cli::BlockAnnounceParams, service::FullClient};
+use bizinikiwi_prometheus_endpoint::{register, CounterVec, Opts, PrometheusError, Registry, U64};
+use futures::{future, FutureExt};
+use pez_solochain_template_runtime::opaque::{Block, Hash, Header};
+use pezsc_consensus_aura::standalone::{check_header_slot_and_seal, find_pre_digest, slot_author};
+use pezsp_api::ProvideRuntimeApi;
+use pezsp_blockchain::HeaderBackend;
+use pezsp_consensus::block_validation::{BlockAnnounceValidator, Validation};
+use pezsp_consensus_aura::{
+	sr25519::{AuthorityId, AuthorityPair, AuthoritySignature},
+	AuraApi, Slot, SlotDuration,
+};
+use pezsp_runtime::traits::Header as _;
+use std::{
+	collections::{HashMap, VecDeque},
+	error::Error,
+	future::Future,
+	pin::Pin,
+	sync::Arc,
+	time::{Duration, Instant},
+};
+
+const LOG_TARGET: &str = "block-announce";
+
+/// How many slots an announced header may be ahead of the local clock, to tolerate clock drift.
+const MAX_FUTURE_SLOTS: u64 = 2;
+
+/// Builds the block announce validator once the network is set up.
+pub type BlockAnnounceValidatorBuilder =
+	Box<dyn FnOnce(Arc<FullClient>) -> Box<dyn BlockAnnounceValidator<Block> + Send> + Send>;
+
+/// Why an announcement was rejected.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Rejection {
+	/// The header is not sealed by the Aura authority of its slot.
+	UnknownAuthor,
+	/// The slot of the header is too far ahead of the local clock.
+	FutureSlot,
+	/// The author of the header announced too many blocks.
+	RateLimited,
+}
+
+impl Rejection {
+	fn as_str(&self) -> &'static str {
+		match self {
+			Rejection::UnknownAuthor => "unknown_author",
+			Rejection::FutureSlot => "future_slot",
+			Rejection::RateLimited => "rate_limited",
+		}
+	}
+
+	fn validation(&self) -> Validation {
+		Validation::Failure { disconnect: *self == Rejection::UnknownAuthor }
+	}
+}
+
+/// Rejects announcements of headers not authored by the current Aura authorities, and of authors
+/// announcing too often.
+pub struct AuraAnnounceValidator {
+	client: Arc<FullClient>,
+	slot_duration: SlotDuration,
+	limit: usize,
+	window: Duration,
+	/// The headers announced within the window, per author.
+	recent: HashMap<AuthorityId, VecDeque<(Instant, Hash)>>,
+	rejected: Option<CounterVec<U64>>,
+}
+
+impl AuraAnnounceValidator {
+	/// Creates the validator configured by `params`.
+	pub fn new(
+		client: Arc<FullClient>,
+		slot_duration: SlotDuration,
+		params: &BlockAnnounceParams,
+		registry: Option<&Registry>,
+	) -> Result<Self, PrometheusError> {
+		let rejected = registry
+			.map(|registry| {
+				register(
+					CounterVec::new(
+						Opts::new(
+							"block_announces_rejected_total",
+							"Number of block announcements rejected, by reason",
+						),
+						&["reason"],
+					)?,
+					registry,
+				)
+			})
+			.transpose()?;
+
+		Ok(Self {
+			client,
+			slot_duration,
+			limit: params.block_announce_limit as usize,
+			window: Duration::from_secs(params.block_announce_window),
+			recent: HashMap::new(),
+			rejected,
+		})
+	}
+
+	/// Turns the validator into the builder expected by `build_network`.
+	pub fn into_builder(self) -> BlockAnnounceValidatorBuilder {
+		Box::new(move |_| Box::new(self))
+	}
+
+	fn check(&mut self, header: &Header) -> Result<(), Rejection> {
+		let Ok(slot) = find_pre_digest::<Block, AuthoritySignature>(header) else {
+			return Err(Rejection::UnknownAuthor);
+		};
+		// The authorities of a header are those of its parent. The parent of an announced header
+		// is not always imported yet, in which case the best block has to do.
+		let runtime_api = self.client.runtime_api();
+		let Ok(authorities) = runtime_api
+			.authorities(*header.parent_hash())
+			.or_else(|_| runtime_api.authorities(self.client.info().best_hash))
+		else {
+			return Ok(());
+		};
+		let Some(author) = slot_author::<AuthorityPair>(slot, &authorities) else {
+			return Ok(());
+		};
+
+		let slot_now =
+			Slot::from_timestamp(pezsp_timestamp::Timestamp::current(), self.slot_duration);
+		if slot > slot_now + MAX_FUTURE_SLOTS {
+			return Err(Rejection::FutureSlot);
+		}
+		// The seal of a header from the near future is checked as if its slot had come already,
+		// instead of being deferred.
+		if check_header_slot_and_seal::<Block, AuthorityPair>(
+			slot_now.max(slot),
+			header.clone(),
+			author,
+		)
+		.is_err()
+		{
+			return Err(Rejection::UnknownAuthor);
+		}
+
+		let now = Instant::now();
+		let hash = header.hash();
+		let announced = self.recent.entry(author.clone()).or_default();
+		while announced.front().is_some_and(|(at, _)| now.duration_since(*at) > self.window) {
+			announced.pop_front();
+		}
+		if announced.iter().any(|(_, announced)| *announced == hash) {
+			return Ok(());
+		}
+		if announced.len() >= self.limit {
+			return Err(Rejection::RateLimited);
+		}
+		announced.push_back((now, hash));
+		Ok(())
+	}
+}
+
+impl BlockAnnounceValidator<Block> for AuraAnnounceValidator {
+	fn validate(
+		&mut self,
+		header: &Header,
+		_data: &[u8],
+	) -> Pin<Box<dyn Future<Output = Result<Validation, Box<dyn Error + Send>>> + Send>> {
+		let validation = match self.check(header) {
+			Ok(()) => Validation::Success { is_new_best: false },
+			Err(rejection) => {
+				log::debug!(
+					target: LOG_TARGET,
+					"Rejected announcement of block #{} ({:?}): {}",
+					header.number(),
+					header.hash(),
+					rejection.as_str(),
+				);
+				if let Some(rejected) = &self.rejected {
+					rejected.with_label_values(&[rejection.as_str()]).inc();
+				}
+				rejection.validation()
+			},
+		};
+
+		future::ready(Ok(validation)).boxed()
+	}
+}
@@ -0,0 +1,533 @@
+//! Re-execution of blocks for debugging.
+//!
+//! With `--block-tracing` the node serves `state_traceBlock` and the `trace_block` RPC. The latter
+//! re-executes a block on top of its parent and reports, per extrinsic, the storage keys it read
+//! and wrote, the events it emitted and the weight it used. Storage accesses are attributed to
+//! extrinsics through the `apply_extrinsic` spans of the runtime, which only exist when the runtime
+//! is built with the `with-tracing` feature. Without it, all accesses end up in
+//! [`BlockTrace::outside_extrinsics`].
+//!
+//! `trace_block` is an unsafe RPC method, so it is only served with `--rpc-methods unsafe`.
+
+use crate::service::FullClient;
+use codec::Decode;
+use jsonrpsee::{
+	core::RpcResult,
+	proc_macros::rpc,
+	types::{error::ErrorObject, ErrorObjectOwned},
+	Extensions,
+};
+use pez_solochain_template_runtime::{opaque::Block, Hash, RuntimeEvent, System};
+use pezframe_support::{storage::storage_prefix, traits::PalletInfoAccess};
+use pezframe_system::{EventRecord, Phase};
+use pezsc_client_api::{BlockBackend, StorageProvider};
+use pezsc_rpc_api::check_if_safe;
+use pezsc_tracing::block::{BlockExecutor, TracingExecuteBlock};
+use pezsp_api::{Core, ProvideRuntimeApi};
+use pezsp_core::{bytes::from_hex, storage::StorageKey, Bytes};
+use pezsp_rpc::tracing::{BlockTrace as RawBlockTrace, Event, TraceBlockResponse};
+use pezsp_runtime::{
+	traits::{Block as BlockT, Header as _},
+	Weight,
+};
+use serde::Serialize;
+use std::{collections::HashMap, sync::Arc};
+
+/// Name of the runtime span wrapping the execution of a single extrinsic.
+const APPLY_EXTRINSIC_SPAN: &str = "apply_extrinsic";
+
+/// Re-executes blocks on behalf of `state_traceBlock` and `trace_block`.
+pub struct ExecuteBlock {
+	client: Arc<FullClient>,
+}
+
+impl ExecuteBlock {
+	/// Creates a block executor on top of `client`.
+	pub fn new(client: Arc<FullClient>) -> Self {
+		Self { client }
+	}
+}
+
+impl TracingExecuteBlock<Block> for ExecuteBlock {
+	fn execute_block(&self, _orig_hash: Hash, block: Block) -> pezsp_blockchain::Result<()> {
+		let parent_hash = *block.header().parent_hash();
+		self.client.runtime_api().execute_block(parent_hash, block).map_err(Into::into)
+	}
+}
+
+/// Storage keys accessed while executing part of a block.
+#[derive(Debug, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StorageAccesses {
+	/// Keys read, in the order of the reads.
+	pub reads: Vec<Bytes>,
+	/// Keys written, in the order of the writes.
+	pub writes: Vec<StorageWrite>,
+}
+
+/// A single storage write.
+#[derive(Debug, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase", tag = "kind")]
+pub enum StorageWrite {
+	/// The value of `key` was set, or removed if `value` is `None`.
+	Put { key: Bytes, value: Option<Bytes> },
+	/// `value` was appended to the value of `key`.
+	Append { key: Bytes, value: Bytes },
+	/// All keys starting with `prefix` were removed.
+	ClearPrefix { prefix: Bytes },
+}
+
+/// What a single extrinsic did when its block was re-executed.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExtrinsicTrace {
+	/// Index of the extrinsic in the block.
+	pub index: u32,
+	/// Storage accessed by the extrinsic.
+	pub storage: StorageAccesses,
+	/// Events emitted by the extrinsic.
+	pub events: Vec<String>,
+	/// Weight used by the extrinsic, as reported by `ExtrinsicSuccess` or `ExtrinsicFailed`.
+	pub weight: Option<Weight>,
+}
+
+/// The result of `trace_block`.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BlockTrace {
+	/// Hash of the traced block.
+	pub block_hash: Hash,
+	/// One entry per extrinsic of the block.
+	pub extrinsics: Vec<ExtrinsicTrace>,
+	/// Storage accessed while initializing and finalizing the block, or by extrinsics that could
+	/// not be told apart.
+	pub outside_extrinsics: StorageAccesses,
+}
+
+/// Block tracing RPC methods.
+#[rpc(server, namespace = "trace")]
+pub trait BlockTracingApi {
+	/// Re-executes the block with the given hash and reports what each extrinsic did.
+	#[method(name = "block", blocking, with_extensions)]
+	fn trace_block(&self, hash: Hash) -> RpcResult<BlockTrace>;
+}
+
+/// Implements [`BlockTracingApiServer`].
+#[derive(Clone)]
+pub struct BlockTracing {
+	client: Arc<FullClient>,
+	execute_block: Arc<dyn TracingExecuteBlock<Block>>,
+}
+
+impl BlockTracing {
+	/// Creates the RPC handler, re-executing blocks with `execute_block`.
+	pub fn new(
+		client: Arc<FullClient>,
+		execute_block: Arc<dyn TracingExecuteBlock<Block>>,
+	) -> Self {
+		Self { client, execute_block }
+	}
+
+	fn storage_accesses(&self, hash: Hash) -> Result<RawBlockTrace, String> {
+		let executor = BlockExecutor::new(
+			self.client.clone(),
+			hash,
+			Some("state".into()),
+			// An empty prefix keeps the accesses of all keys.
+			Some(String::new()),
+			None,
+			None,
+			self.execute_block.clone(),
+		);
+		match executor.trace_block().map_err(|e| e.to_string())? {
+			TraceBlockResponse::BlockTrace(trace) => Ok(trace),
+			TraceBlockResponse::TraceError(e) => Err(e.error),
+		}
+	}
+}
+
+impl BlockTracingApiServer for BlockTracing {
+	fn trace_block(&self, ext: &Extensions, hash: Hash) -> RpcResult<BlockTrace> {
+		check_if_safe(ext)?;
+		let extrinsic_count = self
+			.client
+			.block_body(hash)
+			.map_err(internal_error)?
+			.ok_or_else(|| internal_error(format!("block {hash} not found")))?
+			.len();
+		let mut extrinsics = (0..extrinsic_count as u32)
+			.map(|index| ExtrinsicTrace {
+				index,
+				storage: Default::default(),
+				events: Vec::new(),
+				weight: None,
+			})
+			.collect::<Vec<_>>();
+		let mut outside_extrinsics = StorageAccesses::default();
+
+		let trace = self.storage_accesses(hash).map_err(internal_error)?;
+		attribute_storage(&trace, &mut extrinsics, &mut outside_extrinsics);
+		attribute_events(
+			&mut extrinsics,
+			block_events(&self.client, hash).map_err(internal_error)?,
+		);
+
+		Ok(BlockTrace { block_hash: hash, extrinsics, outside_extrinsics })
+	}
+}
+
+/// The events deposited by the block with the given hash.
+pub(crate) fn block_events(
+	client: &FullClient,
+	hash: Hash,
+) -> Result<Vec<EventRecord<RuntimeEvent, Hash>>, String> {
+	let key = StorageKey(events_key().to_vec());
+	let Some(events) = client.storage(hash, &key).map_err(|e| e.to_string())? else {
+		return Ok(Vec::new());
+	};
+	Decode::decode(&mut &events.0[..]).map_err(|e| e.to_string())
+}
+
+/// The storage key of `System::Events`.
+fn events_key() -> [u8; 32] {
+	storage_prefix(System::name().as_bytes(), b"Events")
+}
+
+/// Adds the storage accesses of `trace` to the extrinsics that made them, or to
+/// `outside_extrinsics` when no extrinsic can be found.
+fn attribute_storage(
+	trace: &RawBlockTrace,
+	extrinsics: &mut [ExtrinsicTrace],
+	outside_extrinsics: &mut StorageAccesses,
+) {
+	let extrinsic_spans = extrinsic_spans(trace);
+	let span_parents =
+		trace.spans.iter().map(|span| (span.id, span.parent_id)).collect::<HashMap<_, _>>();
+	for event in &trace.events {
+		let storage = match extrinsic_index(event, &span_parents, &extrinsic_spans) {
+			Some(index) if index < extrinsics.len() => &mut extrinsics[index].storage,
+			_ => &mut *outside_extrinsics,
+		};
+		record_access(storage, event);
+	}
+}
+
+/// Adds the events deposited while applying an extrinsic, and the weight it used, to its trace.
+fn attribute_events(
+	extrinsics: &mut [ExtrinsicTrace],
+	records: Vec<EventRecord<RuntimeEvent, Hash>>,
+) {
+	for record in records {
+		let Phase::ApplyExtrinsic(index) = record.phase else { continue };
+		let Some(extrinsic) = extrinsics.get_mut(index as usize) else { continue };
+		if let RuntimeEvent::System(
+			pezframe_system::Event::ExtrinsicSuccess { dispatch_info }
+			| pezframe_system::Event::ExtrinsicFailed { dispatch_info, .. },
+		) = &record.event
+		{
+			extrinsic.weight = Some(dispatch_info.weight);
+		}
+		extrinsic.events.push(format!("{:?}", record.event));
+	}
+}
+
+/// Maps the ids of the `apply_extrinsic` spans to the index of their extrinsic.
+///
+/// Span ids are handed out in execution order, so the n-th span belongs to the n-th extrinsic.
+fn extrinsic_spans(trace: &RawBlockTrace) -> HashMap<u64, usize> {
+	let mut ids = trace
+		.spans
+		.iter()
+		.filter(|span| span.name == APPLY_EXTRINSIC_SPAN)
+		.map(|span| span.id)
+		.collect::<Vec<_>>();
+	ids.sort_unstable();
+	ids.into_iter().enumerate().map(|(index, id)| (id, index)).collect()
+}
+
+/// Finds the extrinsic whose execution emitted `event`, by walking up its spans.
+fn extrinsic_index(
+	event: &Event,
+	span_parents: &HashMap<u64, Option<u64>>,
+	extrinsic_spans: &HashMap<u64, usize>,
+) -> Option<usize> {
+	let mut span = event.parent_id;
+	while let Some(id) = span {
+		if let Some(index) = extrinsic_spans.get(&id) {
+			return Some(*index);
+		}
+		span = span_parents.get(&id).copied().flatten();
+	}
+	None
+}
+
+/// Adds the storage access described by a `state` tracing event to `storage`.
+fn record_access(storage: &mut StorageAccesses, event: &Event) {
+	let values = &event.data.string_values;
+	let bytes = |name: &str| values.get(name).and_then(|value| from_hex(value).ok());
+	let write = match values.get("method").map(String::as_str) {
+		Some("Get" | "Exists" | "Hash") => {
+			if let Some(key) = bytes("key") {
+				storage.reads.push(key.into());
+			}
+			return;
+		},
+		Some("Put") => bytes("key").map(|key| StorageWrite::Put {
+			key: key.into(),
+			// An `Option` of the raw value, which unlike a `Vec` isn't length prefixed.
+			value: bytes("value_encoded").and_then(|value| match value.split_first() {
+				Some((1, value)) => Some(value.to_vec().into()),
+				_ => None,
+			}),
+		}),
+		Some("Append") => bytes("key")
+			.zip(bytes("value"))
+			.map(|(key, value)| StorageWrite::Append { key: key.into(), value: value.into() }),
+		Some("ClearPrefix") => {
+			bytes("prefix").map(|prefix| StorageWrite::ClearPrefix { prefix: prefix.into() })
+		},
+		_ => None,
+	};
+	storage.writes.extend(write);
+}
+
+fn internal_error(e: impl ToString) -> ErrorObjectOwned {
+	ErrorObject::owned(1, e.to_string(), None::<()>)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use pezframe_support::dispatch::{DispatchClass, Pays};
+	use pezframe_system::DispatchEventInfo;
+	use pezsp_core::{hexdisplay::HexDisplay, twox_128};
+	use pezsp_rpc::tracing::{Data, Span};
+	use pezsp_runtime::DispatchError;
+
+	fn span(id: u64, parent_id: Option<u64>, name: &str) -> Span {
+		Span { id, parent_id, name: name.into(), target: "frame_executive".into(), wasm: true }
+	}
+
+	fn access(parent_id: Option<u64>, method: &str, key: &[u8], value: Option<&[u8]>) -> Event {
+		let hex = |bytes: &[u8]| HexDisplay::from(&bytes).to_string();
+		let mut string_values = vec![("method".to_string(), method.to_string())];
+		match method {
+			"ClearPrefix" => string_values.push(("prefix".into(), hex(key))),
+			_ => string_values.push(("key".into(), hex(key))),
+		}
+		match method {
+			// Encoded like `Option<EncodeOpaqueValue>`: a tag followed by the raw value.
+			"Put" => {
+				let encoded = match value {
+					Some(value) => [&[1][..], value].concat(),
+					None => vec![0],
+				};
+				string_values.push(("value_encoded".into(), hex(&encoded)));
+			},
+			"Append" => string_values.push(("value".into(), hex(value.unwrap_or_default()))),
+			_ => {},
+		}
+		let data = Data { string_values: string_values.into_iter().collect() };
+		Event { target: "state".into(), data, parent_id }
+	}
+
+	fn trace(spans: Vec<Span>, events: Vec<Event>) -> RawBlockTrace {
+		RawBlockTrace {
+			block_hash: Default::default(),
+			parent_hash: Default::default(),
+			tracing_targets: "state".into(),
+			storage_keys: Default::default(),
+			methods: Default::default(),
+			spans,
+			events,
+		}
+	}
+
+	fn extrinsic_traces(count: u32) -> Vec<ExtrinsicTrace> {
+		(0..count)
+			.map(|index| ExtrinsicTrace {
+				index,
+				storage: Default::default(),
+				events: Vec::new(),
+				weight: None,
+			})
+			.collect()
+	}
+
+	fn dispatch_info(ref_time: u64) -> DispatchEventInfo {
+		DispatchEventInfo {
+			weight: Weight::from_parts(ref_time, 0),
+			class: DispatchClass::Normal,
+			pays_fee: Pays::Yes,
+		}
+	}
+
+	fn record(
+		phase: Phase,
+		event: pezframe_system::Event<pez_solochain_template_runtime::Runtime>,
+	) -> EventRecord<RuntimeEvent, Hash> {
+		EventRecord { phase, event: RuntimeEvent::System(event), topics: Vec::new() }
+	}
+
+	#[test]
+	fn records_reads_and_writes() {
+		let mut storage = StorageAccesses::default();
+
+		record_access(&mut storage, &access(None, "Get", b"read", None));
+		record_access(&mut storage, &access(None, "Exists", b"checked", None));
+		record_access(&mut storage, &access(None, "Put", b"written", Some(b"value")));
+		record_access(&mut storage, &access(None, "Put", b"removed", None));
+		record_access(&mut storage, &access(None, "Append", b"appended", Some(b"item")));
+		record_access(&mut storage, &access(None, "ClearPrefix", b"prefix", None));
+		record_access(&mut storage, &access(None, "NextKey", b"next", None));
+
+		assert_eq!(storage.reads, vec![Bytes::from(b"read".to_vec()), b"checked".to_vec().into()]);
+		assert_eq!(
+			storage.writes,
+			vec![
+				StorageWrite::Put {
+					key: b"written".to_vec().into(),
+					value: Some(b"value".to_vec().into())
+				},
+				StorageWrite::Put { key: b"removed".to_vec().into(), value: None },
+				StorageWrite::Append {
+					key: b"appended".to_vec().into(),
+					value: b"item".to_vec().into()
+				},
+				StorageWrite::ClearPrefix { prefix: b"prefix".to_vec().into() },
+			]
+		);
+	}
+
+	#[test]
+	fn put_values_are_not_length_prefixed() {
+		let mut storage = StorageAccesses::default();
+		// A value starting with what would be a valid compact length prefix.
+		let value = [4, 1, 2, 3, 4, 5];
+
+		record_access(&mut storage, &access(None, "Put", b"key", Some(&value)));
+
+		assert_eq!(
+			storage.writes,
+			vec![StorageWrite::Put {
+				key: b"key".to_vec().into(),
+				value: Some(value.to_vec().into())
+			}]
+		);
+	}
+
+	#[test]
+	fn finds_the_extrinsic_of_nested_spans() {
+		let trace = trace(
+			vec![
+				span(1, None, "initialize_block"),
+				span(2, None, APPLY_EXTRINSIC_SPAN),
+				span(3, Some(2), "dispatch"),
+				span(4, Some(3), "on_unbalanced"),
+				span(5, None, APPLY_EXTRINSIC_SPAN),
+			],
+			Vec::new(),
+		);
+		let extrinsic_spans = extrinsic_spans(&trace);
+		let span_parents =
+			trace.spans.iter().map(|span| (span.id, span.parent_id)).collect::<HashMap<_, _>>();
+		let index = |parent_id| {
+			extrinsic_index(
+				&access(parent_id, "Get", b"key", None),
+				&span_parents,
+				&extrinsic_spans,
+			)
+		};
+
+		assert_eq!(index(Some(4)), Some(0));
+		assert_eq!(index(Some(5)), Some(1));
+		assert_eq!(index(Some(1)), None);
+		assert_eq!(index(None), None);
+	}
+
+	#[test]
+	fn attributes_storage_accesses_to_extrinsics() {
+		// Spans are listed as they close, but their ids are handed out as they open.
+		let trace = trace(
+			vec![span(7, None, APPLY_EXTRINSIC_SPAN), span(3, None, APPLY_EXTRINSIC_SPAN)],
+			vec![
+				access(None, "Get", b"initialize", None),
+				access(Some(3), "Get", b"first", None),
+				access(Some(7), "Put", b"second", Some(b"value")),
+				access(None, "Put", b"finalize", Some(b"value")),
+			],
+		);
+		let mut extrinsics = extrinsic_traces(2);
+		let mut outside_extrinsics = StorageAccesses::default();
+
+		attribute_storage(&trace, &mut extrinsics, &mut outside_extrinsics);
+
+		assert_eq!(extrinsics[0].storage.reads, vec![Bytes::from(b"first".to_vec())]);
+		assert!(extrinsics[0].storage.writes.is_empty());
+		assert!(extrinsics[1].storage.reads.is_empty());
+		assert_eq!(
+			extrinsics[1].storage.writes,
+			vec![StorageWrite::Put {
+				key: b"second".to_vec().into(),
+				value: Some(b"value".to_vec().into())
+			}]
+		);
+		assert_eq!(outside_extrinsics.reads, vec![Bytes::from(b"initialize".to_vec())]);
+		assert_eq!(outside_extrinsics.writes.len(), 1);
+	}
+
+	#[test]
+	fn accesses_of_unknown_extrinsics_are_kept_outside() {
+		let trace = trace(
+			vec![span(1, None, APPLY_EXTRINSIC_SPAN), span(2, None, APPLY_EXTRINSIC_SPAN)],
+			vec![access(Some(2), "Get", b"key", None)],
+		);
+		let mut extrinsics = extrinsic_traces(1);
+		let mut outside_extrinsics = StorageAccesses::default();
+
+		attribute_storage(&trace, &mut extrinsics, &mut outside_extrinsics);
+
+		assert!(extrinsics[0].storage.reads.is_empty());
+		assert_eq!(outside_extrinsics.reads, vec![Bytes::from(b"key".to_vec())]);
+	}
+
+	#[test]
+	fn attributes_events_and_weights_to_extrinsics() {
+		let mut extrinsics = extrinsic_traces(2);
+
+		attribute_events(
+			&mut extrinsics,
+			vec![
+				record(Phase::Initialization, pezframe_system::Event::CodeUpdated),
+				record(
+					Phase::ApplyExtrinsic(0),
+					pezframe_system::Event::ExtrinsicSuccess { dispatch_info: dispatch_info(10) },
+				),
+				record(
+					Phase::ApplyExtrinsic(1),
+					pezframe_system::Event::ExtrinsicFailed {
+						dispatch_error: DispatchError::BadOrigin,
+						dispatch_info: dispatch_info(20),
+					},
+				),
+				record(
+					Phase::ApplyExtrinsic(2),
+					pezframe_system::Event::ExtrinsicSuccess { dispatch_info: dispatch_info(30) },
+				),
+				record(Phase::Finalization, pezframe_system::Event::CodeUpdated),
+			],
+		);
+
+		assert_eq!(extrinsics[0].weight, Some(Weight::from_parts(10, 0)));
+		assert_eq!(extrinsics[0].events.len(), 1);
+		assert!(extrinsics[0].events[0].contains("ExtrinsicSuccess"));
+		assert_eq!(extrinsics[1].weight, Some(Weight::from_parts(20, 0)));
+		assert_eq!(extrinsics[1].events.len(), 1);
+		assert!(extrinsics[1].events[0].contains("ExtrinsicFailed"));
+	}
+
+	#[test]
+	fn events_are_read_from_system_events() {
+		assert_eq!(events_key().to_vec(), [twox_128(b"System"), twox_128(b"Events")].concat());
+	}
+}
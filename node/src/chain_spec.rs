@@ -1,13 +1,108 @@
-use pez_solochain_template_runtime::WASM_BINARY;
+use crate::voting_rules::VotingRuleConfig;
+use pez_solochain_template_runtime::{opaque::Block, BlockNumber, Hash, WASM_BINARY};
+use pezsc_chain_spec::{ChainSpecExtension, GenesisConfigBuilderRuntimeCaller};
 use pezsc_service::ChainType;
+use pezsp_consensus_grandpa::{AuthorityId as GrandpaId, AuthorityWeight, SetId};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+/// The minimum period of blocks on which justifications will be
+/// imported and generated.
+const GRANDPA_JUSTIFICATION_PERIOD: u32 = 512;
+
+/// How long GRANDPA waits before gossiping its messages again.
+const GRANDPA_GOSSIP_DURATION_MILLIS: u64 = 333;
+
+/// Node-side GRANDPA parameters, tunable per network through the chain spec.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct GrandpaParams {
+	/// Gossip duration in milliseconds.
+	pub gossip_duration_millis: u64,
+	/// The minimum period of blocks on which justifications will be imported and generated.
+	pub justification_period: u32,
+	/// Voting rules applied by every voter on top of the default ones.
+	pub voting_rules: Vec<VotingRuleConfig>,
+	/// Authority set changes forced onto the chain, e.g. to recover a stalled network.
+	pub authority_set_hard_forks: Vec<AuthoritySetHardFork>,
+}
+
+impl Default for GrandpaParams {
+	fn default() -> Self {
+		Self {
+			gossip_duration_millis: GRANDPA_GOSSIP_DURATION_MILLIS,
+			justification_period: GRANDPA_JUSTIFICATION_PERIOD,
+			voting_rules: Vec::new(),
+			authority_set_hard_forks: Vec::new(),
+		}
+	}
+}
+
+/// A GRANDPA authority set that replaces the one on chain at a given block.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AuthoritySetHardFork {
+	/// Number of the block at which the new authority set is enacted.
+	pub block_number: BlockNumber,
+	/// Hash of the block at which the new authority set is enacted.
+	pub block_hash: Hash,
+	/// Id of the new authority set.
+	pub set_id: SetId,
+	/// The new authorities and their voting weights.
+	pub authorities: Vec<(GrandpaId, AuthorityWeight)>,
+	/// The last block finalized before the fork. When given, the change is enacted without
+	/// waiting for `block_number` to be finalized, and voting resumes from this block.
+	#[serde(default)]
+	pub last_finalized: Option<BlockNumber>,
+}
+
+impl From<&AuthoritySetHardFork> for pezsc_consensus_grandpa::AuthoritySetHardFork<Block> {
+	fn from(fork: &AuthoritySetHardFork) -> Self {
+		Self {
+			set_id: fork.set_id,
+			block: (fork.block_hash, fork.block_number),
+			authorities: fork.authorities.clone(),
+			last_finalized: fork.last_finalized,
+		}
+	}
+}
+
+impl GrandpaParams {
+	/// The gossip duration as a [`Duration`].
+	pub fn gossip_duration(&self) -> Duration {
+		Duration::from_millis(self.gossip_duration_millis)
+	}
+
+	/// The authority set hard forks in the form expected by GRANDPA.
+	pub fn authority_set_hard_forks(
+		&self,
+	) -> Vec<pezsc_consensus_grandpa::AuthoritySetHardFork<Block>> {
+		self.authority_set_hard_forks.iter().map(Into::into).collect()
+	}
+}
+
+/// Node-specific chain spec extensions.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize, ChainSpecExtension)]
+#[serde(rename_all = "camelCase", default)]
+pub struct Extensions {
+	/// GRANDPA parameters of this network.
+	pub grandpa: GrandpaParams,
+}
+
+impl Extensions {
+	/// Try to get the extension from the given `ChainSpec`.
+	pub fn try_get(chain_spec: &dyn pezsc_service::ChainSpec) -> Option<&Self> {
+		pezsc_chain_spec::get_extension(chain_spec.extensions())
+	}
+}
 
 /// Specialized `ChainSpec`. This is a specialization of the general Bizinikiwi ChainSpec type.
-pub type ChainSpec = pezsc_service::GenericChainSpec;
+pub type ChainSpec = pezsc_service::GenericChainSpec<Extensions>;
 
 pub fn development_chain_spec() -> Result<ChainSpec, String> {
 	Ok(ChainSpec::builder(
 		WASM_BINARY.ok_or_else(|| "Development wasm not available".to_string())?,
-		None,
+		Extensions::default(),
 	)
 	.with_name("Development")
 	.with_id("dev")
@@ -19,7 +114,7 @@ pub fn development_chain_spec() -> Result<ChainSpec, String> {
 pub fn local_chain_spec() -> Result<ChainSpec, String> {
 	Ok(ChainSpec::builder(
 		WASM_BINARY.ok_or_else(|| "Development wasm not available".to_string())?,
-		None,
+		Extensions::default(),
 	)
 	.with_name("Local Testnet")
 	.with_id("local_testnet")
@@ -27,3 +122,39 @@ pub fn local_chain_spec() -> Result<ChainSpec, String> {
 	.with_genesis_config_preset_name(pezsp_genesis_builder::LOCAL_TESTNET_RUNTIME_PRESET)
 	.build())
 }
+
+/// Names of the genesis config presets of the embedded runtime.
+pub fn preset_names() -> Result<Vec<String>, String> {
+	let wasm = WASM_BINARY.ok_or_else(|| "Development wasm not available".to_string())?;
+	let caller: GenesisConfigBuilderRuntimeCaller = GenesisConfigBuilderRuntimeCaller::new(wasm);
+	let presets = caller
+		.preset_names()
+		.map_err(|e| format!("failed to query the genesis presets of the runtime: {e}"))?;
+	Ok(presets.iter().map(|preset| AsRef::<str>::as_ref(preset).to_string()).collect())
+}
+
+/// A chain spec starting from the genesis config preset `name` of the embedded runtime.
+pub fn preset_chain_spec(name: &str) -> Result<ChainSpec, String> {
+	let presets = preset_names()?;
+	if !presets.iter().any(|preset| preset == name) {
+		return Err(format!(
+			"unknown genesis preset `{name}`, the runtime provides: {}",
+			presets.join(", ")
+		));
+	}
+	let chain_type = if name == pezsp_genesis_builder::DEV_RUNTIME_PRESET {
+		ChainType::Development
+	} else {
+		ChainType::Local
+	};
+
+	Ok(ChainSpec::builder(
+		WASM_BINARY.ok_or_else(|| "Development wasm not available".to_string())?,
+		Extensions::default(),
+	)
+	.with_name(&format!("Preset {name}"))
+	.with_id(name)
+	.with_chain_type(chain_type)
+	.with_genesis_config_preset_name(name)
+	.build())
+}
@@ -0,0 +1,412 @@
+//! Generating chain specs for new networks.
+//!
+//! `generate-chain-spec` builds a chain spec with the given authorities, endowed accounts and sudo
+//! key, taken from flags or from a TOML file such as
+//!
+//! ```toml
+//! name = "Staging"
+//! id = "staging"
+//! chain_type = "live"
+//! sudo = "5GrwvaEF5zXb26Fz9rcQpDWS57CtERHpNehXCPcNoHGKutQY"
+//! bootnodes = ["/dns/boot.example.com/tcp/30333/p2p/12D3KooWEyoppNCUx8Yx66oV9fJnriXwCcXwDDUA2kj6vnc6iDEp"]
+//!
+//! [[authorities]]
+//! aura = "5GrwvaEF5zXb26Fz9rcQpDWS57CtERHpNehXCPcNoHGKutQY"
+//! grandpa = "5FA9nQDVg267DEd8m1ZypXLBnvN7SFxYwV7ndqSYGiN9TTpu"
+//!
+//! [[endowed]]
+//! account = "5GrwvaEF5zXb26Fz9rcQpDWS57CtERHpNehXCPcNoHGKutQY"
+//! amount = "1000000000000000"
+//! ```
+//!
+//! Flags extend the lists of the file and override its other values. Accounts and keys are SS58
+//! addresses of the chain's `SS58Prefix` or hex encoded public keys.
+
+use crate::chain_spec::{ChainSpec, Extensions};
+use pez_solochain_template_runtime::{
+	configs::SS58Prefix, genesis_config_presets, AccountId, Balance, BlockAuthorityId, WASM_BINARY,
+};
+use pezsc_network::config::MultiaddrWithPeerId;
+use pezsc_service::ChainType;
+use pezsp_consensus_grandpa::AuthorityId as GrandpaId;
+use pezsp_core::{
+	bytes::from_hex,
+	crypto::{ByteArray, Ss58Codec},
+	ed25519, sr25519, Get,
+};
+use serde::Deserialize;
+use std::{
+	collections::HashSet,
+	fs,
+	io::{self, Write},
+	path::PathBuf,
+	str::FromStr,
+};
+
+/// Generate a chain spec with custom authorities, balances and sudo key.
+#[derive(Debug, Clone, clap::Parser)]
+pub struct GenerateChainSpecCmd {
+	/// TOML file with the parameters of the chain spec.
+	#[arg(long, value_name = "FILE")]
+	pub config: Option<PathBuf>,
+
+	/// Name of the chain.
+	#[arg(long)]
+	pub name: Option<String>,
+
+	/// Id of the chain.
+	#[arg(long)]
+	pub id: Option<String>,
+
+	/// Type of the chain.
+	#[arg(long, value_enum)]
+	pub chain_type: Option<GeneratedChainType>,
+
+	/// An authority, as `<aura>,<grandpa>[,<account>]`.
+	///
+	/// The account defaults to the account of the Aura (sr25519) key. The GRANDPA key is an
+	/// ed25519 key. Can be given multiple times.
+	#[arg(long = "authority", value_name = "KEYS")]
+	pub authorities: Vec<AuthorityArg>,
+
+	/// An endowed account, as `<account>=<amount>`. Can be given multiple times.
+	#[arg(long = "endow", value_name = "ACCOUNT=AMOUNT")]
+	pub endowed: Vec<EndowedArg>,
+
+	/// The sudo key.
+	#[arg(long, value_name = "ACCOUNT")]
+	pub sudo: Option<String>,
+
+	/// A boot node of the chain. Can be given multiple times.
+	#[arg(long = "bootnode", value_name = "ADDR")]
+	pub bootnodes: Vec<MultiaddrWithPeerId>,
+
+	/// Write the chain spec with the raw genesis storage.
+	#[arg(long)]
+	pub raw: bool,
+
+	/// Output file name, or stdout if unspecified.
+	#[arg(long, short, value_name = "FILE")]
+	pub output: Option<PathBuf>,
+}
+
+/// The type of a generated chain.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum GeneratedChainType {
+	/// A development chain that runs mainly on one node.
+	Development,
+	/// A local chain that runs locally on multiple nodes for testing purposes.
+	Local,
+	/// A live chain.
+	Live,
+}
+
+impl From<GeneratedChainType> for ChainType {
+	fn from(chain_type: GeneratedChainType) -> Self {
+		match chain_type {
+			GeneratedChainType::Development => ChainType::Development,
+			GeneratedChainType::Local => ChainType::Local,
+			GeneratedChainType::Live => ChainType::Live,
+		}
+	}
+}
+
+/// An authority given by `--authority` or in the `authorities` of the file.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AuthorityArg {
+	/// The Aura (sr25519) key.
+	pub aura: String,
+	/// The GRANDPA (ed25519) key.
+	pub grandpa: String,
+	/// The account registering the session keys, the account of `aura` if unspecified.
+	#[serde(default)]
+	pub account: Option<String>,
+}
+
+impl FromStr for AuthorityArg {
+	type Err = String;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		match s.split(',').collect::<Vec<_>>()[..] {
+			[aura, grandpa] => {
+				Ok(Self { aura: aura.into(), grandpa: grandpa.into(), account: None })
+			},
+			[aura, grandpa, account] => Ok(Self {
+				aura: aura.into(),
+				grandpa: grandpa.into(),
+				account: Some(account.into()),
+			}),
+			_ => Err(format!("invalid authority `{s}`, expected `<aura>,<grandpa>[,<account>]`")),
+		}
+	}
+}
+
+/// An endowed account given by `--endow` or in the `endowed` of the file.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct EndowedArg {
+	/// The account.
+	pub account: String,
+	/// The free balance of the account, as a string since TOML integers are limited to 64 bits.
+	pub amount: String,
+}
+
+impl FromStr for EndowedArg {
+	type Err = String;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		let (account, amount) = s
+			.split_once('=')
+			.ok_or_else(|| format!("invalid endowment `{s}`, expected `<account>=<amount>`"))?;
+		Ok(Self { account: account.into(), amount: amount.into() })
+	}
+}
+
+/// The content of a `--config` file.
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct GeneratorConfig {
+	name: Option<String>,
+	id: Option<String>,
+	chain_type: Option<GeneratedChainType>,
+	#[serde(default)]
+	authorities: Vec<AuthorityArg>,
+	#[serde(default)]
+	endowed: Vec<EndowedArg>,
+	sudo: Option<String>,
+	#[serde(default)]
+	bootnodes: Vec<MultiaddrWithPeerId>,
+}
+
+/// The validated genesis of a generated chain spec.
+#[derive(Debug, PartialEq)]
+struct Genesis {
+	authorities: Vec<(AccountId, BlockAuthorityId, GrandpaId)>,
+	endowed: Vec<(AccountId, Balance)>,
+	sudo: Option<AccountId>,
+}
+
+impl GenerateChainSpecCmd {
+	/// Writes the chain spec to the output.
+	pub fn run(&self) -> pezsc_cli::Result<()> {
+		let config = match &self.config {
+			Some(path) => {
+				let content = fs::read_to_string(path)?;
+				toml::from_str(&content)
+					.map_err(|e| format!("failed to parse {}: {e}", path.display()))?
+			},
+			None => GeneratorConfig::default(),
+		};
+
+		let authorities =
+			config.authorities.iter().chain(&self.authorities).cloned().collect::<Vec<_>>();
+		let endowed = config.endowed.iter().chain(&self.endowed).cloned().collect::<Vec<_>>();
+		let sudo = self.sudo.as_ref().or(config.sudo.as_ref());
+		let genesis = genesis(&authorities, &endowed, sudo.map(String::as_str))?;
+
+		let name = self.name.clone().or(config.name).unwrap_or_else(|| "Custom".into());
+		let id = self.id.clone().or(config.id).unwrap_or_else(|| "custom".into());
+		let chain_type = self.chain_type.or(config.chain_type).unwrap_or(GeneratedChainType::Live);
+		let bootnodes = config.bootnodes.into_iter().chain(self.bootnodes.clone()).collect();
+
+		let chain_spec = ChainSpec::builder(
+			WASM_BINARY.ok_or_else(|| "Development wasm not available".to_string())?,
+			Extensions::default(),
+		)
+		.with_name(&name)
+		.with_id(&id)
+		.with_chain_type(chain_type.into())
+		.with_boot_nodes(bootnodes)
+		.with_genesis_config_patch(genesis_config_presets::genesis(
+			genesis.authorities,
+			genesis.endowed,
+			genesis.sudo,
+		))
+		.build();
+
+		let json = chain_spec.as_json(self.raw)?;
+		match &self.output {
+			Some(output) => fs::write(output, json)?,
+			None => io::stdout().write_all(json.as_bytes())?,
+		}
+		Ok(())
+	}
+}
+
+/// Validates the authorities, endowments and sudo key of a chain spec.
+fn genesis(
+	authorities: &[AuthorityArg],
+	endowed: &[EndowedArg],
+	sudo: Option<&str>,
+) -> Result<Genesis, String> {
+	if authorities.is_empty() {
+		return Err("at least one authority is required".into());
+	}
+
+	let mut accounts = HashSet::new();
+	let mut aura_keys = HashSet::new();
+	let mut grandpa_keys = HashSet::new();
+	let authorities = authorities
+		.iter()
+		.map(|authority| {
+			let aura = public::<sr25519::Public>(&authority.aura, "Aura key")?;
+			let grandpa = public::<ed25519::Public>(&authority.grandpa, "GRANDPA key")?;
+			let account = match &authority.account {
+				Some(account) => public::<sr25519::Public>(account, "account")?,
+				None => aura,
+			};
+			if !aura_keys.insert(aura) {
+				return Err(format!("duplicate Aura key {}", authority.aura));
+			}
+			if !grandpa_keys.insert(grandpa) {
+				return Err(format!("duplicate GRANDPA key {}", authority.grandpa));
+			}
+			let account = AccountId::from(account);
+			if !accounts.insert(account.clone()) {
+				return Err(format!("duplicate authority account {}", to_ss58(&account)));
+			}
+			Ok((account, BlockAuthorityId::from(aura), GrandpaId::from(grandpa)))
+		})
+		.collect::<Result<Vec<_>, String>>()?;
+
+	let mut endowed_accounts = HashSet::new();
+	let endowed = endowed
+		.iter()
+		.map(|endowed| {
+			let account = AccountId::from(public::<sr25519::Public>(&endowed.account, "account")?);
+			let amount = endowed.amount.parse::<Balance>().map_err(|e| {
+				format!("invalid amount {} of {}: {e}", endowed.amount, endowed.account)
+			})?;
+			if !endowed_accounts.insert(account.clone()) {
+				return Err(format!("duplicate endowed account {}", endowed.account));
+			}
+			Ok((account, amount))
+		})
+		.collect::<Result<Vec<_>, String>>()?;
+
+	let sudo = sudo
+		.map(|sudo| public::<sr25519::Public>(sudo, "sudo key").map(AccountId::from))
+		.transpose()?;
+
+	Ok(Genesis { authorities, endowed, sudo })
+}
+
+/// Parses `key`, an SS58 address of the chain's prefix or a hex encoded public key, naming it
+/// `name` in errors.
+fn public<P: Ss58Codec + ByteArray>(key: &str, name: &str) -> Result<P, String> {
+	if key.starts_with("0x") {
+		let bytes = from_hex(key).map_err(|e| format!("invalid {name} {key}: {e}"))?;
+		return P::from_slice(&bytes).map_err(|()| format!("invalid {name} {key}: wrong length"));
+	}
+
+	let (public, format) =
+		P::from_ss58check_with_version(key).map_err(|e| format!("invalid {name} {key}: {e}"))?;
+	let prefix = u16::from(format);
+	if prefix != u16::from(SS58Prefix::get()) {
+		return Err(format!(
+			"{name} {key} has the SS58 prefix {prefix}, expected {}",
+			SS58Prefix::get()
+		));
+	}
+	Ok(public)
+}
+
+fn to_ss58(account: &AccountId) -> String {
+	account.to_ss58check_with_version(u16::from(SS58Prefix::get()).into())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use pezsp_core::{crypto::Ss58AddressFormat, hexdisplay::HexDisplay};
+	use pezsp_keyring::{Ed25519Keyring, Sr25519Keyring};
+
+	fn authority(keyring: Sr25519Keyring, grandpa: Ed25519Keyring) -> AuthorityArg {
+		AuthorityArg {
+			aura: keyring.public().to_ss58check(),
+			grandpa: grandpa.public().to_ss58check(),
+			account: None,
+		}
+	}
+
+	#[test]
+	fn parses_flags() {
+		let alice = Sr25519Keyring::Alice.to_account_id().to_ss58check();
+
+		assert_eq!(
+			format!("{alice},0x01,{alice}").parse::<AuthorityArg>().unwrap(),
+			AuthorityArg {
+				aura: alice.clone(),
+				grandpa: "0x01".into(),
+				account: Some(alice.clone())
+			},
+		);
+		assert!(alice.parse::<AuthorityArg>().is_err());
+		assert_eq!(
+			format!("{alice}=1000").parse::<EndowedArg>().unwrap(),
+			EndowedArg { account: alice, amount: "1000".into() },
+		);
+	}
+
+	#[test]
+	fn builds_genesis() {
+		let alice = Sr25519Keyring::Alice.to_account_id();
+		let endowed = [EndowedArg { account: to_ss58(&alice), amount: "1000".into() }];
+
+		let genesis = genesis(
+			&[authority(Sr25519Keyring::Alice, Ed25519Keyring::Alice)],
+			&endowed,
+			Some(&to_ss58(&alice)),
+		)
+		.unwrap();
+
+		assert_eq!(
+			genesis.authorities,
+			[(
+				alice.clone(),
+				Sr25519Keyring::Alice.public().into(),
+				Ed25519Keyring::Alice.public().into()
+			)],
+		);
+		assert_eq!(genesis.endowed, [(alice.clone(), 1000)]);
+		assert_eq!(genesis.sudo, Some(alice));
+	}
+
+	#[test]
+	fn accepts_hex_keys() {
+		let grandpa = format!("0x{}", HexDisplay::from(&Ed25519Keyring::Bob.public().to_raw_vec()));
+		let authority =
+			AuthorityArg { grandpa, ..authority(Sr25519Keyring::Bob, Ed25519Keyring::Bob) };
+
+		assert!(genesis(&[authority], &[], None).is_ok());
+	}
+
+	#[test]
+	fn rejects_duplicate_authorities() {
+		let alice = authority(Sr25519Keyring::Alice, Ed25519Keyring::Alice);
+		let same_grandpa = authority(Sr25519Keyring::Bob, Ed25519Keyring::Alice);
+
+		assert!(genesis(&[alice.clone(), alice.clone()], &[], None)
+			.unwrap_err()
+			.contains("duplicate Aura key"));
+		assert!(genesis(&[alice, same_grandpa], &[], None)
+			.unwrap_err()
+			.contains("duplicate GRANDPA key"));
+	}
+
+	#[test]
+	fn rejects_addresses_of_other_chains() {
+		let polkadot = Sr25519Keyring::Alice
+			.to_account_id()
+			.to_ss58check_with_version(Ss58AddressFormat::custom(0));
+		let endowed = [EndowedArg { account: polkadot, amount: "1".into() }];
+
+		let error =
+			genesis(&[authority(Sr25519Keyring::Alice, Ed25519Keyring::Alice)], &endowed, None)
+				.unwrap_err();
+
+		assert!(error.contains("has the SS58 prefix 0, expected 42"), "{error}");
+	}
+}
@@ -1,3 +1,299 @@
+use crate::{transaction_pool::CallIndex, voting_rules::VotingRuleConfig};
+use std::path::PathBuf;
+
+/// Block production engine used in place of Aura/GRANDPA for development chains.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Sealing {
+	/// Seal a block as soon as a new transaction becomes ready in the pool.
+	Instant,
+	/// Seal blocks only on request through the `engine_createBlock` RPC.
+	Manual,
+	/// Seal a block every given number of milliseconds.
+	Interval(u64),
+}
+
+impl std::str::FromStr for Sealing {
+	type Err = String;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		Ok(match s {
+			"instant" => Sealing::Instant,
+			"manual" => Sealing::Manual,
+			s => {
+				let millis = s.strip_prefix("interval=").ok_or_else(|| {
+					format!("unknown sealing mode `{s}`, expected `instant`, `manual` or `interval=<ms>`")
+				})?;
+				match millis.parse::<u64>() {
+					Ok(millis) if millis > 0 => Sealing::Interval(millis),
+					_ => return Err(format!("invalid sealing interval `{millis}`")),
+				}
+			},
+		})
+	}
+}
+
+/// Node-specific parameters that are not covered by [`pezsc_cli::RunCmd`].
+#[derive(Debug, Clone, clap::Parser)]
+pub struct NodeParams {
+	/// Replace Aura and GRANDPA with a seal-on-demand engine.
+	///
+	/// `instant` seals a block for every new ready transaction and finalizes it right away,
+	/// `manual` seals through the `engine_createBlock` / `engine_finalizeBlock` RPCs and
+	/// `interval=<ms>` seals and finalizes a block at a fixed interval. Not supported by nodes
+	/// built with the `babe` feature.
+	#[arg(long, value_name = "MODE")]
+	pub sealing: Option<Sealing>,
+
+	/// Run the GRANDPA observer instead of the full voter.
+	///
+	/// The observer imports justifications and tracks finality, but never takes part in voting
+	/// rounds. Only allowed on non-authority nodes.
+	#[arg(long)]
+	pub grandpa_observer: bool,
+
+	/// Add a GRANDPA voting rule on top of the default ones and those from the chain spec.
+	///
+	/// Can be given multiple times, the most conservative rule wins. One of
+	/// `before-best=<blocks>`, `three-quarters` or `min-age=<secs>`.
+	#[arg(long = "grandpa-voting-rule", value_name = "RULE")]
+	pub grandpa_voting_rules: Vec<VotingRuleConfig>,
+
+	/// Serve `state_traceBlock` and `trace_block`, which re-execute blocks to debug them.
+	///
+	/// Re-executing blocks is expensive, so both methods are unsafe and need `--rpc-methods unsafe`
+	/// on a public RPC endpoint. Build the runtime with the `with-tracing` feature to attribute
+	/// storage accesses to the extrinsics that made them.
+	#[arg(long)]
+	pub block_tracing: bool,
+
+	/// Load the authority keys from a JSON or TOML file into the keystore at startup.
+	///
+	/// The file holds the secret URIs of the block authoring and GRANDPA keys, under `aura` (or
+	/// `babe`) and `grandpa`, and optionally the expected `session_keys`. The node refuses to
+	/// start if the keys do not match the session keys of the runtime.
+	#[arg(long, value_name = "FILE")]
+	pub authority_keys: Option<PathBuf>,
+
+	#[clap(flatten)]
+	pub backoff: BackoffParams,
+
+	#[clap(flatten)]
+	pub proposal: ProposalParams,
+
+	#[clap(flatten)]
+	pub offchain: OffchainParams,
+
+	#[clap(flatten)]
+	pub block_announce: BlockAnnounceParams,
+
+	#[clap(flatten)]
+	pub health: HealthParams,
+
+	#[clap(flatten)]
+	pub telemetry_file: TelemetryFileParams,
+
+	#[clap(flatten)]
+	pub pool_policy: PoolPolicyParams,
+}
+
+/// Parameters of the Aura authoring backoff while GRANDPA finality lags.
+#[derive(Debug, Clone, clap::Args)]
+pub struct BackoffParams {
+	/// Keep authoring a block in every slot, no matter how far finality lags behind.
+	///
+	/// Backoff is always disabled when GRANDPA is disabled.
+	#[arg(long)]
+	pub no_backoff_authoring: bool,
+
+	/// Number of unfinalized blocks tolerated before authoring starts to back off.
+	#[arg(long, value_name = "BLOCKS", default_value_t = 50)]
+	pub backoff_unfinalized_slack: u32,
+
+	/// Scales the backoff rate. A higher value backs off slower as the unfinalized part of the
+	/// chain grows.
+	#[arg(long, value_name = "BIAS", default_value_t = 2)]
+	pub backoff_authoring_bias: u32,
+
+	/// Maximum number of slots between two authored blocks, regardless of the finality lag.
+	#[arg(long, value_name = "SLOTS", default_value_t = 100)]
+	pub backoff_max_interval: u32,
+}
+
+/// Parameters of the block proposer, i.e. how long and how much it may spend on a block.
+#[derive(Debug, Clone, clap::Args)]
+pub struct ProposalParams {
+	/// Portion of the slot the proposer may spend on building a block, between 0 and 1.
+	///
+	/// Defaults to two thirds of the slot.
+	#[arg(long, value_name = "PORTION", value_parser = parse_slot_portion)]
+	pub block_proposal_slot_portion: Option<f32>,
+
+	/// Largest portion of the slot the proposer may spend on a block after missed slots,
+	/// between 0 and 1.
+	///
+	/// The proposal time grows with every slot missed since the last block, up to this portion.
+	/// Without it the proposal time never grows beyond `--block-proposal-slot-portion`.
+	#[arg(long, value_name = "PORTION", value_parser = parse_slot_portion)]
+	pub max_block_proposal_slot_portion: Option<f32>,
+
+	/// Size limit in bytes of the blocks built by the proposer.
+	///
+	/// Defaults to the limit configured in the runtime.
+	#[arg(long, value_name = "BYTES")]
+	pub proposer_block_size_limit: Option<usize>,
+
+	/// Percentage of the proposal time after which the proposer stops collecting transactions
+	/// once a transaction did not fit into the block.
+	#[arg(
+		long,
+		value_name = "PERCENT",
+		default_value_t = 50,
+		value_parser = clap::value_parser!(u8).range(1..=100),
+	)]
+	pub proposer_soft_deadline: u8,
+}
+
+/// Restrictions on what offchain workers may do.
+#[derive(Debug, Clone, clap::Args)]
+pub struct OffchainParams {
+	/// Allow offchain workers to send HTTP requests to this host.
+	///
+	/// Either `host` for any port or `host:port`. Can be given multiple times. Once given, requests
+	/// to any other host are rejected and logged. Without it, all hosts can be reached.
+	#[arg(long, value_name = "HOST")]
+	pub offchain_http_allow: Vec<String>,
+
+	/// Do not allow offchain workers to send HTTP requests at all.
+	#[arg(long, conflicts_with = "offchain_http_allow")]
+	pub no_offchain_http: bool,
+}
+
+/// Limits on the block announcements accepted from the network.
+#[derive(Debug, Clone, clap::Args)]
+pub struct BlockAnnounceParams {
+	/// Number of blocks a single authority may announce within `--block-announce-window`.
+	///
+	/// Further announcements are rejected, which lowers the reputation of the relaying peers.
+	#[arg(long, value_name = "BLOCKS", default_value_t = 30)]
+	pub block_announce_limit: u32,
+
+	/// Length in seconds of the window `--block-announce-limit` applies to.
+	#[arg(long, value_name = "SECS", default_value_t = 60)]
+	pub block_announce_window: u64,
+}
+
+/// The health endpoint and the thresholds it reports on.
+#[derive(Debug, Clone, clap::Args)]
+pub struct HealthParams {
+	/// Serve `/health` and `/ready` on this port.
+	///
+	/// Both report the state of the node as JSON, and answer with `503 Service Unavailable` once a
+	/// threshold is exceeded. `/ready` also does while the node is major syncing.
+	#[arg(long, value_name = "PORT")]
+	pub health_port: Option<u16>,
+
+	/// Listen to all interfaces for the health endpoint instead of only the local one.
+	#[arg(long, requires = "health_port")]
+	pub health_external: bool,
+
+	/// Smallest number of connected peers considered healthy.
+	#[arg(long, value_name = "PEERS", default_value_t = 1)]
+	pub health_min_peers: usize,
+
+	/// Largest number of blocks the finalized block may be behind the best block.
+	#[arg(long, value_name = "BLOCKS", default_value_t = 50)]
+	pub health_max_finality_lag: u32,
+
+	/// Longest time in seconds an authority may go without authoring a block.
+	///
+	/// Only checked while the key of this node is in the authority set. Not checked by default.
+	#[arg(long, value_name = "SECS")]
+	pub health_max_authoring_gap: Option<u64>,
+}
+
+/// Local recording of telemetry.
+#[derive(Debug, Clone, clap::Args)]
+pub struct TelemetryFileParams {
+	/// Record block imports, finality and interval stats to this JSONL file.
+	///
+	/// Works without any telemetry endpoint. `telemetry-summary` reads the file back.
+	#[arg(long, value_name = "PATH")]
+	pub telemetry_file: Option<PathBuf>,
+
+	/// Size in MiB at which the telemetry file is rotated.
+	#[arg(long, value_name = "MIB", default_value_t = 64, requires = "telemetry_file")]
+	pub telemetry_file_max_size: u64,
+
+	/// Number of rotated telemetry files to keep next to the current one.
+	#[arg(long, value_name = "FILES", default_value_t = 4, requires = "telemetry_file")]
+	pub telemetry_file_keep: usize,
+}
+
+/// Node-local admission policy for transactions from the RPC and the network.
+#[derive(Debug, Clone, clap::Args)]
+pub struct PoolPolicyParams {
+	/// Largest number of ready transactions a single sender may have in the pool.
+	#[arg(long, value_name = "COUNT")]
+	pub pool_max_ready_per_sender: Option<usize>,
+
+	/// Largest number of future transactions a single sender may have in the pool.
+	#[arg(long, value_name = "COUNT")]
+	pub pool_max_future_per_sender: Option<usize>,
+
+	/// Only admit calls of this pallet, or of this single call.
+	///
+	/// Either `<pallet index>` or `<pallet index>:<call index>`. Can be given multiple times. Calls
+	/// wrapped by `Sudo` have to be allowed as well as `Sudo` itself.
+	#[arg(long, value_name = "CALL")]
+	pub pool_allow_call: Vec<CallIndex>,
+
+	/// Reject calls of this pallet, or this single call.
+	///
+	/// Either `<pallet index>` or `<pallet index>:<call index>`. Can be given multiple times. Calls
+	/// wrapped by `Sudo` are rejected as well.
+	#[arg(long, value_name = "CALL", conflicts_with = "pool_allow_call")]
+	pub pool_deny_call: Vec<CallIndex>,
+
+	/// Smallest tip of the signed transactions admitted to the pool.
+	#[arg(long, value_name = "BALANCE", default_value_t = 0)]
+	pub pool_min_tip: u128,
+}
+
+/// The `benchmark` subcommands, with the options of the extrinsics generated by `overhead` and
+/// `extrinsic`.
+#[derive(Debug, clap::Args)]
+pub struct NodeBenchmarkCmd {
+	#[command(subcommand)]
+	pub cmd: pezframe_benchmarking_cli::BenchmarkCmd,
+
+	#[clap(flatten)]
+	pub calls: BenchmarkCallParams,
+}
+
+/// Extrinsics generated by the `benchmark extrinsic` and `benchmark overhead` commands.
+#[derive(Debug, Clone, clap::Args)]
+pub struct BenchmarkCallParams {
+	/// Number of development accounts taking turns in signing the benchmark extrinsics.
+	#[arg(long, value_name = "COUNT", default_value_t = 1, global = true)]
+	pub benchmark_signers: usize,
+
+	/// Register the calls of a JSON file with `benchmark extrinsic`.
+	///
+	/// The file holds a list of `{ "pallet": "Balances", "call": "transfer_allow_death", "args":
+	/// { "dest": { "Id": "5Fhe…" }, "value": "1000" } }`, with the arguments given by name, as a
+	/// list or as a hex string of their SCALE encoding. An optional `name` sets the extrinsic name
+	/// and `sudo` wraps the call into `Sudo::sudo`.
+	#[arg(long, value_name = "FILE", global = true)]
+	pub benchmark_calls: Option<PathBuf>,
+}
+
+fn parse_slot_portion(s: &str) -> Result<f32, String> {
+	match s.parse::<f32>() {
+		Ok(portion) if portion > 0.0 && portion <= 1.0 => Ok(portion),
+		_ => Err(format!("invalid slot portion `{s}`, expected a number in (0, 1]")),
+	}
+}
+
 #[derive(Debug, clap::Parser)]
 pub struct Cli {
 	#[command(subcommand)]
@@ -5,6 +301,9 @@ pub struct Cli {
 
 	#[clap(flatten)]
 	pub run: pezsc_cli::RunCmd,
+
+	#[clap(flatten)]
+	pub node: NodeParams,
 }
 
 #[derive(Debug, clap::Subcommand)]
@@ -34,6 +333,12 @@ pub enum Subcommand {
 	/// Export the state of a given block into a chain spec.
 	ExportState(pezsc_cli::ExportStateCmd),
 
+	/// Export the genesis header of the chain.
+	ExportGenesisHead(crate::genesis::ExportGenesisHeadCmd),
+
+	/// Export the genesis runtime code of the chain.
+	ExportGenesisWasm(crate::genesis::ExportGenesisWasmCmd),
+
 	/// Import blocks.
 	ImportBlocks(pezsc_cli::ImportBlocksCmd),
 
@@ -44,9 +349,21 @@ pub enum Subcommand {
 	Revert(pezsc_cli::RevertCmd),
 
 	/// Sub-commands concerned with benchmarking.
-	#[command(subcommand)]
-	Benchmark(pezframe_benchmarking_cli::BenchmarkCmd),
+	Benchmark(NodeBenchmarkCmd),
 
 	/// Db meta columns information.
 	ChainInfo(pezsc_cli::ChainInfoCmd),
+
+	/// Generate a chain spec with custom authorities, balances and sudo key.
+	GenerateChainSpec(crate::chain_spec_generator::GenerateChainSpecCmd),
+
+	/// List the genesis config presets of the runtime, usable as `--chain preset:<name>`.
+	ListPresets,
+
+	/// Summarise telemetry files written with `--telemetry-file`.
+	TelemetrySummary(crate::telemetry_recorder::TelemetrySummaryCmd),
+
+	/// Try runtime upgrades and blocks against a local state snapshot.
+	#[cfg(feature = "try-runtime")]
+	TryRuntime(crate::try_runtime::TryRuntimeCmd),
 }
@@ -1,14 +1,16 @@
 use crate::{
-	benchmarking::{inherent_benchmark_data, RemarkBuilder, TransferKeepAliveBuilder},
+	benchmarking::{self, inherent_benchmark_data, CallBuilder, SignerPool},
 	chain_spec,
-	cli::{Cli, Subcommand},
+	cli::{Cli, NodeBenchmarkCmd, Subcommand},
 	service,
 };
-use pez_solochain_template_runtime::{Block, EXISTENTIAL_DEPOSIT};
+use pez_solochain_template_runtime::Block;
 use pezframe_benchmarking_cli::{BenchmarkCmd, ExtrinsicFactory, BIZINIKIWI_REFERENCE_HARDWARE};
 use pezsc_cli::BizinikiwiCli;
 use pezsc_service::PartialComponents;
-use pezsp_keyring::Sr25519Keyring;
+
+/// Prefix of the `--chain` ids naming a genesis config preset of the runtime.
+const PRESET_PREFIX: &str = "preset:";
 
 impl BizinikiwiCli for Cli {
 	fn impl_name() -> String {
@@ -36,12 +38,27 @@ impl BizinikiwiCli for Cli {
 	}
 
 	fn load_spec(&self, id: &str) -> Result<Box<dyn pezsc_service::ChainSpec>, String> {
+		if let Some(name) = id.strip_prefix(PRESET_PREFIX) {
+			return Ok(Box::new(chain_spec::preset_chain_spec(name)?));
+		}
+
 		Ok(match id {
 			"dev" => Box::new(chain_spec::development_chain_spec()?),
 			"" | "local" => Box::new(chain_spec::local_chain_spec()?),
-			path => {
+			path if std::path::Path::new(path).exists() => {
 				Box::new(chain_spec::ChainSpec::from_json_file(std::path::PathBuf::from(path))?)
 			},
+			id => {
+				let presets = chain_spec::preset_names()?
+					.iter()
+					.map(|name| format!("`{PRESET_PREFIX}{name}`"))
+					.collect::<Vec<_>>();
+				return Err(format!(
+					"unknown chain `{id}`, expected `dev`, `local`, the path of a chain spec file \
+					or one of the presets {}",
+					presets.join(", ")
+				));
+			},
 		})
 	}
 }
@@ -61,7 +78,7 @@ pub fn run() -> pezsc_cli::Result<()> {
 			let runner = cli.create_runner(cmd)?;
 			runner.async_run(|config| {
 				let PartialComponents { client, task_manager, import_queue, .. } =
-					service::new_partial(&config)?;
+					service::new_partial(&config, &cli.node)?;
 				Ok((cmd.run(client, import_queue), task_manager))
 			})
 		},
@@ -72,22 +89,35 @@ pub fn run() -> pezsc_cli::Result<()> {
 		Some(Subcommand::ExportBlocks(cmd)) => {
 			let runner = cli.create_runner(cmd)?;
 			runner.async_run(|config| {
-				let PartialComponents { client, task_manager, .. } = service::new_partial(&config)?;
+				let PartialComponents { client, task_manager, .. } =
+					service::new_partial(&config, &cli.node)?;
 				Ok((cmd.run(client, config.database), task_manager))
 			})
 		},
 		Some(Subcommand::ExportState(cmd)) => {
 			let runner = cli.create_runner(cmd)?;
 			runner.async_run(|config| {
-				let PartialComponents { client, task_manager, .. } = service::new_partial(&config)?;
+				let PartialComponents { client, task_manager, .. } =
+					service::new_partial(&config, &cli.node)?;
 				Ok((cmd.run(client, config.chain_spec), task_manager))
 			})
 		},
+		Some(Subcommand::ExportGenesisHead(cmd)) => {
+			let runner = cli.create_runner(cmd)?;
+			runner.sync_run(|config| {
+				let PartialComponents { client, .. } = service::new_partial(&config, &cli.node)?;
+				cmd.run(&*client)
+			})
+		},
+		Some(Subcommand::ExportGenesisWasm(cmd)) => {
+			let runner = cli.create_runner(cmd)?;
+			runner.sync_run(|config| cmd.run(&*config.chain_spec))
+		},
 		Some(Subcommand::ImportBlocks(cmd)) => {
 			let runner = cli.create_runner(cmd)?;
 			runner.async_run(|config| {
 				let PartialComponents { client, task_manager, import_queue, .. } =
-					service::new_partial(&config)?;
+					service::new_partial(&config, &cli.node)?;
 				Ok((cmd.run(client, import_queue), task_manager))
 			})
 		},
@@ -99,7 +129,7 @@ pub fn run() -> pezsc_cli::Result<()> {
 			let runner = cli.create_runner(cmd)?;
 			runner.async_run(|config| {
 				let PartialComponents { client, task_manager, backend, .. } =
-					service::new_partial(&config)?;
+					service::new_partial(&config, &cli.node)?;
 				let aux_revert = Box::new(|client, _, blocks| {
 					pezsc_consensus_grandpa::revert(client, blocks)?;
 					Ok(())
@@ -107,7 +137,7 @@ pub fn run() -> pezsc_cli::Result<()> {
 				Ok((cmd.run(client, backend, Some(aux_revert)), task_manager))
 			})
 		},
-		Some(Subcommand::Benchmark(cmd)) => {
+		Some(Subcommand::Benchmark(NodeBenchmarkCmd { cmd, calls })) => {
 			let runner = cli.create_runner(cmd)?;
 
 			runner.sync_run(|config| {
@@ -128,14 +158,15 @@ pub fn run() -> pezsc_cli::Result<()> {
 						))
 					},
 					BenchmarkCmd::Block(cmd) => {
-						let PartialComponents { client, .. } = service::new_partial(&config)?;
+						let PartialComponents { client, .. } =
+							service::new_partial(&config, &cli.node)?;
 						cmd.run(client)
 					},
 
 					#[cfg(feature = "runtime-benchmarks")]
 					BenchmarkCmd::Storage(cmd) => {
 						let PartialComponents { client, backend, .. } =
-							service::new_partial(&config)?;
+							service::new_partial(&config, &cli.node)?;
 						let db = backend.expose_db();
 						let storage = backend.expose_storage();
 						let shared_cache = backend.expose_shared_trie_cache();
@@ -143,8 +174,12 @@ pub fn run() -> pezsc_cli::Result<()> {
 						cmd.run(config, client, db, storage, shared_cache)
 					},
 					BenchmarkCmd::Overhead(cmd) => {
-						let PartialComponents { client, .. } = service::new_partial(&config)?;
-						let ext_builder = RemarkBuilder::new(client.clone());
+						let PartialComponents { client, .. } =
+							service::new_partial(&config, &cli.node)?;
+						let ext_builder = CallBuilder::remark(
+							client.clone(),
+							SignerPool::new(calls.benchmark_signers),
+						);
 
 						cmd.run(
 							config.chain_spec.name().into(),
@@ -156,16 +191,13 @@ pub fn run() -> pezsc_cli::Result<()> {
 						)
 					},
 					BenchmarkCmd::Extrinsic(cmd) => {
-						let PartialComponents { client, .. } = service::new_partial(&config)?;
-						// Register the *Remark* and *TKA* builders.
-						let ext_factory = ExtrinsicFactory(vec![
-							Box::new(RemarkBuilder::new(client.clone())),
-							Box::new(TransferKeepAliveBuilder::new(
-								client.clone(),
-								Sr25519Keyring::Alice.to_account_id(),
-								EXISTENTIAL_DEPOSIT,
-							)),
-						]);
+						let PartialComponents { client, .. } =
+							service::new_partial(&config, &cli.node)?;
+						// Register the built-in builders and those of `--benchmark-calls`.
+						let ext_factory = ExtrinsicFactory(benchmarking::extrinsic_builders(
+							client.clone(),
+							calls,
+						)?);
 
 						cmd.run(client, inherent_benchmark_data()?, Vec::new(), &ext_factory)
 					},
@@ -179,6 +211,19 @@ pub fn run() -> pezsc_cli::Result<()> {
 			let runner = cli.create_runner(cmd)?;
 			runner.sync_run(|config| cmd.run::<Block>(&config))
 		},
+		Some(Subcommand::GenerateChainSpec(cmd)) => cmd.run(),
+		Some(Subcommand::ListPresets) => {
+			for name in chain_spec::preset_names()? {
+				println!("{name}");
+			}
+			Ok(())
+		},
+		Some(Subcommand::TelemetrySummary(cmd)) => cmd.run(),
+		#[cfg(feature = "try-runtime")]
+		Some(Subcommand::TryRuntime(cmd)) => {
+			let runner = cli.create_runner(cmd)?;
+			runner.sync_run(|_| cmd.run())
+		},
 		None => {
 			let runner = cli.create_runner(&cli.run)?;
 			runner.run_node_until_exit(|config| async move {
@@ -188,10 +233,12 @@ pub fn run() -> pezsc_cli::Result<()> {
 							pez_solochain_template_runtime::opaque::Block,
 							<pez_solochain_template_runtime::opaque::Block as pezsp_runtime::traits::Block>::Hash,
 						>,
-					>(config)
+					>(config, cli.node)
+					.map(|node| node.task_manager)
 					.map_err(pezsc_cli::Error::Service),
 					pezsc_network::config::NetworkBackendType::Litep2p =>
-						service::new_full::<pezsc_network::Litep2pNetworkBackend>(config)
+						service::new_full::<pezsc_network::Litep2pNetworkBackend>(config, cli.node)
+							.map(|node| node.task_manager)
 							.map_err(pezsc_cli::Error::Service),
 				}
 			})
@@ -0,0 +1,93 @@
+//! Exporting the genesis of a chain for registration and bridge tooling.
+//!
+//! `export-genesis-head` prints the SCALE encoded genesis header of `--chain` and
+//! `export-genesis-wasm` the runtime code it starts with, both hex encoded unless `--raw` is given.
+
+use codec::Encode;
+use pez_solochain_template_runtime::opaque::Block;
+use pezsc_cli::{CliConfiguration, SharedParams};
+use pezsc_service::ChainSpec;
+use pezsp_blockchain::HeaderBackend;
+use pezsp_core::{hexdisplay::HexDisplay, storage::well_known_keys};
+use pezsp_runtime::{traits::Zero, BuildStorage};
+use std::{
+	fs,
+	io::{self, Write},
+	path::PathBuf,
+};
+
+/// Export the genesis header of the chain.
+#[derive(Debug, Clone, clap::Parser)]
+pub struct ExportGenesisHeadCmd {
+	/// Output file name, or stdout if unspecified.
+	#[arg(value_name = "FILE")]
+	pub output: Option<PathBuf>,
+
+	/// Write the header as binary instead of hex.
+	#[arg(long)]
+	pub raw: bool,
+
+	#[allow(missing_docs)]
+	#[clap(flatten)]
+	pub shared_params: SharedParams,
+}
+
+impl ExportGenesisHeadCmd {
+	/// Writes the genesis header known to `client`.
+	pub fn run(&self, client: &impl HeaderBackend<Block>) -> pezsc_cli::Result<()> {
+		let genesis_hash = client.hash(Zero::zero())?.ok_or("genesis hash not found")?;
+		let genesis_header = client.header(genesis_hash)?.ok_or("genesis header not found")?;
+		write_output(self.output.as_ref(), &genesis_header.encode(), self.raw)
+	}
+}
+
+impl CliConfiguration for ExportGenesisHeadCmd {
+	fn shared_params(&self) -> &SharedParams {
+		&self.shared_params
+	}
+}
+
+/// Export the genesis runtime code of the chain.
+#[derive(Debug, Clone, clap::Parser)]
+pub struct ExportGenesisWasmCmd {
+	/// Output file name, or stdout if unspecified.
+	#[arg(value_name = "FILE")]
+	pub output: Option<PathBuf>,
+
+	/// Write the runtime code as binary instead of hex.
+	#[arg(long)]
+	pub raw: bool,
+
+	#[allow(missing_docs)]
+	#[clap(flatten)]
+	pub shared_params: SharedParams,
+}
+
+impl ExportGenesisWasmCmd {
+	/// Writes the `:code` of the genesis storage of `chain_spec`.
+	pub fn run(&self, chain_spec: &dyn ChainSpec) -> pezsc_cli::Result<()> {
+		let mut storage = chain_spec.build_storage()?;
+		let code = storage
+			.top
+			.remove(well_known_keys::CODE)
+			.ok_or("the genesis storage has no `:code`")?;
+		write_output(self.output.as_ref(), &code, self.raw)
+	}
+}
+
+impl CliConfiguration for ExportGenesisWasmCmd {
+	fn shared_params(&self) -> &SharedParams {
+		&self.shared_params
+	}
+}
+
+/// Writes `data` to `output` or stdout, hex encoded unless `raw`.
+fn write_output(output: Option<&PathBuf>, data: &[u8], raw: bool) -> pezsc_cli::Result<()> {
+	let buffer =
+		if raw { data.to_vec() } else { format!("0x{:?}", HexDisplay::from(&data)).into_bytes() };
+	match output {
+		Some(output) => fs::write(output, buffer)?,
+		None => io::stdout().write_all(&buffer)?,
+	}
+	Ok(())
+}
@@ -0,0 +1,318 @@
+//! Health and readiness endpoint for orchestration.
+//!
+//! `/health` and `/ready` both answer with a JSON [`HealthReport`] of the node. `/health` answers
+//! with `503 Service Unavailable` once one of the thresholds of [`HealthParams`] is exceeded,
+//! `/ready` additionally while the node is major syncing, so it can be used to hold back traffic
+//! until the node caught up with the chain.
+
+use crate::{cli::HealthParams, service::FullClient};
+use futures::{future, StreamExt};
+use http_body_util::Full;
+use hyper::{
+	body::{Bytes, Incoming},
+	header::{HeaderValue, CONTENT_TYPE},
+	server::conn::http1,
+	service::service_fn,
+	Request, Response, StatusCode,
+};
+use hyper_util::rt::TokioIo;
+use pezsc_client_api::BlockchainEvents;
+use pezsc_network::{service::traits::NetworkService, NetworkStatusProvider};
+use pezsp_api::ProvideRuntimeApi;
+use pezsp_blockchain::HeaderBackend;
+use pezsp_consensus::{BlockOrigin, SyncOracle};
+#[cfg(not(feature = "babe"))]
+use pezsp_consensus_aura::AuraApi;
+#[cfg(feature = "babe")]
+use pezsp_consensus_babe::BabeApi;
+use pezsp_core::crypto::{key_types, ByteArray};
+use pezsp_keystore::KeystorePtr;
+use serde::Serialize;
+use std::{
+	convert::Infallible,
+	net::{Ipv4Addr, SocketAddr},
+	sync::{Arc, Mutex},
+	time::Instant,
+};
+use tokio::net::TcpListener;
+
+const LOG_TARGET: &str = "health";
+
+/// The state of the node, as reported by the health endpoint.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HealthReport {
+	/// Whether the node is major syncing.
+	pub is_syncing: bool,
+	/// Number of connected peers.
+	pub peers: usize,
+	/// Number of the best block.
+	pub best_number: u32,
+	/// Number of the finalized block.
+	pub finalized_number: u32,
+	/// Number of blocks the finalized block is behind the best block.
+	pub finality_lag: u32,
+	/// Whether the keystore holds a key of the current authority set.
+	pub in_authority_set: bool,
+	/// Seconds since this node last authored a block, if it did since it started.
+	pub secs_since_last_authored: Option<u64>,
+	/// The exceeded thresholds, empty if the node is healthy.
+	pub problems: Vec<String>,
+}
+
+/// Serves the health endpoint.
+pub struct Health {
+	client: Arc<FullClient>,
+	network: Arc<dyn NetworkService>,
+	sync_oracle: Arc<dyn SyncOracle + Send + Sync>,
+	keystore: KeystorePtr,
+	is_authority: bool,
+	params: HealthParams,
+	started: Instant,
+	/// When the last block authored by this node was imported.
+	last_authored: Mutex<Option<Instant>>,
+}
+
+impl Health {
+	/// Creates the endpoint, to be served by [`Health::run`].
+	pub fn new(
+		client: Arc<FullClient>,
+		network: Arc<dyn NetworkService>,
+		sync_oracle: Arc<dyn SyncOracle + Send + Sync>,
+		keystore: KeystorePtr,
+		is_authority: bool,
+		params: HealthParams,
+	) -> Self {
+		Self {
+			client,
+			network,
+			sync_oracle,
+			keystore,
+			is_authority,
+			params,
+			started: Instant::now(),
+			last_authored: Mutex::new(None),
+		}
+	}
+
+	/// Binds the port configured by `--health-port`, if any.
+	///
+	/// Binding right away makes the node refuse to start when the port is taken.
+	pub fn bind(params: &HealthParams) -> std::io::Result<Option<TcpListener>> {
+		let Some(port) = params.health_port else { return Ok(None) };
+		let ip = if params.health_external { Ipv4Addr::UNSPECIFIED } else { Ipv4Addr::LOCALHOST };
+		let listener = std::net::TcpListener::bind(SocketAddr::from((ip, port)))?;
+		listener.set_nonblocking(true)?;
+		log::info!(target: LOG_TARGET, "〽️ Health endpoint listening on {}", listener.local_addr()?);
+		TcpListener::from_std(listener).map(Some)
+	}
+
+	/// Serves requests on `listener`, while keeping track of the blocks authored by this node.
+	pub async fn run(self, listener: TcpListener) {
+		let health = Arc::new(self);
+		future::join(health.clone().track_authored_blocks(), health.serve(listener)).await;
+	}
+
+	async fn track_authored_blocks(self: Arc<Self>) {
+		let mut imports = self.client.import_notification_stream();
+		while let Some(notification) = imports.next().await {
+			if notification.origin == BlockOrigin::Own {
+				*self.last_authored.lock().expect("lock is never poisoned; qed") =
+					Some(Instant::now());
+			}
+		}
+	}
+
+	async fn serve(self: Arc<Self>, listener: TcpListener) {
+		loop {
+			let stream = match listener.accept().await {
+				Ok((stream, _)) => stream,
+				Err(e) => {
+					log::debug!(target: LOG_TARGET, "Failed to accept a connection: {e}");
+					continue;
+				},
+			};
+
+			let health = self.clone();
+			tokio::spawn(async move {
+				let service = service_fn(move |request: Request<Incoming>| {
+					let health = health.clone();
+					async move { Ok::<_, Infallible>(health.respond(request.uri().path()).await) }
+				});
+				if let Err(e) =
+					http1::Builder::new().serve_connection(TokioIo::new(stream), service).await
+				{
+					log::debug!(target: LOG_TARGET, "Failed to serve a connection: {e}");
+				}
+			});
+		}
+	}
+
+	async fn respond(&self, path: &str) -> Response<Full<Bytes>> {
+		let report = match path {
+			"/health" => self.report(false).await,
+			"/ready" => self.report(true).await,
+			_ => {
+				let mut response = Response::new(Full::from("Not found"));
+				*response.status_mut() = StatusCode::NOT_FOUND;
+				return response;
+			},
+		};
+
+		let status = if report.problems.is_empty() {
+			StatusCode::OK
+		} else {
+			StatusCode::SERVICE_UNAVAILABLE
+		};
+		let body = serde_json::to_vec(&report).expect("the report can be serialized; qed");
+		let mut response = Response::new(Full::from(body));
+		*response.status_mut() = status;
+		response.headers_mut().insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
+		response
+	}
+
+	/// The current state of the node, checked for readiness rather than health if `ready`.
+	async fn report(&self, ready: bool) -> HealthReport {
+		let info = self.client.info();
+		let peers =
+			self.network.status().await.map(|status| status.num_connected_peers).unwrap_or(0);
+		let last_authored = *self.last_authored.lock().expect("lock is never poisoned; qed");
+
+		let mut report = HealthReport {
+			is_syncing: self.sync_oracle.is_major_syncing(),
+			peers,
+			best_number: info.best_number,
+			finalized_number: info.finalized_number,
+			finality_lag: info.best_number.saturating_sub(info.finalized_number),
+			in_authority_set: self.in_authority_set(),
+			secs_since_last_authored: last_authored.map(|at| at.elapsed().as_secs()),
+			problems: Vec::new(),
+		};
+		let authoring_gap = last_authored.unwrap_or(self.started).elapsed().as_secs();
+		report.problems = problems(&report, &self.params, self.is_authority, authoring_gap, ready);
+		report
+	}
+
+	/// Whether the keystore holds a key of the authority set at the best block.
+	#[cfg(not(feature = "babe"))]
+	fn in_authority_set(&self) -> bool {
+		let best_hash = self.client.info().best_hash;
+		let Ok(authorities) = self.client.runtime_api().authorities(best_hash) else {
+			return false;
+		};
+		authorities
+			.iter()
+			.any(|authority| self.keystore.has_keys(&[(authority.to_raw_vec(), key_types::AURA)]))
+	}
+
+	/// Whether the keystore holds a key of the authority set at the best block.
+	#[cfg(feature = "babe")]
+	fn in_authority_set(&self) -> bool {
+		let best_hash = self.client.info().best_hash;
+		let Ok(epoch) = self.client.runtime_api().current_epoch(best_hash) else {
+			return false;
+		};
+		epoch.authorities.iter().any(|(authority, _)| {
+			self.keystore.has_keys(&[(authority.to_raw_vec(), key_types::BABE)])
+		})
+	}
+}
+
+/// The thresholds of `params` exceeded by `report`.
+///
+/// `authoring_gap` is the number of seconds since the node last authored a block, or since it
+/// started if it did not yet.
+fn problems(
+	report: &HealthReport,
+	params: &HealthParams,
+	is_authority: bool,
+	authoring_gap: u64,
+	ready: bool,
+) -> Vec<String> {
+	let mut problems = Vec::new();
+	if ready && report.is_syncing {
+		problems.push("the node is major syncing".into());
+	}
+	if report.peers < params.health_min_peers {
+		problems.push(format!(
+			"{} peers connected, expected at least {}",
+			report.peers, params.health_min_peers
+		));
+	}
+	if report.finality_lag > params.health_max_finality_lag {
+		problems.push(format!(
+			"finality lags {} blocks behind, expected at most {}",
+			report.finality_lag, params.health_max_finality_lag
+		));
+	}
+	if is_authority && !report.in_authority_set {
+		problems.push("no key of the current authority set in the keystore".into());
+	}
+	if let Some(max_gap) = params.health_max_authoring_gap {
+		if report.in_authority_set && authoring_gap > max_gap {
+			problems.push(format!(
+				"no block authored for {authoring_gap} seconds, expected at most {max_gap}"
+			));
+		}
+	}
+	problems
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn params() -> HealthParams {
+		HealthParams {
+			health_port: Some(9955),
+			health_external: false,
+			health_min_peers: 1,
+			health_max_finality_lag: 10,
+			health_max_authoring_gap: Some(60),
+		}
+	}
+
+	fn report() -> HealthReport {
+		HealthReport {
+			is_syncing: false,
+			peers: 3,
+			best_number: 100,
+			finalized_number: 98,
+			finality_lag: 2,
+			in_authority_set: true,
+			secs_since_last_authored: Some(6),
+			problems: Vec::new(),
+		}
+	}
+
+	#[test]
+	fn healthy_within_thresholds() {
+		assert!(problems(&report(), &params(), true, 6, true).is_empty());
+	}
+
+	#[test]
+	fn syncing_nodes_are_healthy_but_not_ready() {
+		let report = HealthReport { is_syncing: true, ..report() };
+
+		assert!(problems(&report, &params(), true, 6, false).is_empty());
+		assert_eq!(problems(&report, &params(), true, 6, true).len(), 1);
+	}
+
+	#[test]
+	fn reports_exceeded_thresholds() {
+		let report = HealthReport { peers: 0, finality_lag: 11, ..report() };
+
+		assert_eq!(problems(&report, &params(), true, 61, false).len(), 3);
+	}
+
+	#[test]
+	fn authoring_gap_only_applies_to_authorities_in_the_set() {
+		let report = HealthReport { in_authority_set: false, ..report() };
+
+		assert!(problems(&report, &params(), false, 600, false).is_empty());
+		assert_eq!(
+			problems(&report, &params(), true, 600, false),
+			vec!["no key of the current authority set in the keystore".to_string()],
+		);
+	}
+}
@@ -1,12 +1,32 @@
 //! Bizinikiwi Node Template CLI library.
 #![warn(missing_docs)]
 
+#[cfg(not(feature = "babe"))]
+mod aura_equivocation;
+mod authority_keys;
+mod backoff;
 mod benchmarking;
+#[cfg(not(feature = "babe"))]
+mod block_announce;
+mod block_tracing;
 mod chain_spec;
+mod chain_spec_generator;
 mod cli;
 mod command;
+mod genesis;
+mod health;
+mod maintenance;
+mod metrics;
+mod offchain;
 mod rpc;
 mod service;
+mod telemetry_recorder;
+#[cfg(test)]
+mod test_network;
+mod transaction_pool;
+#[cfg(feature = "try-runtime")]
+mod try_runtime;
+mod voting_rules;
 
 fn main() -> pezsc_cli::Result<()> {
 	command::run()
@@ -0,0 +1,190 @@
+//! Pausing block authoring and GRANDPA voting on a running node, e.g. to drain a validator
+//! before an upgrade.
+//!
+//! The `maintenance_*` RPC methods flip the switches of [`Maintenance`]. A paused author backs
+//! off in every slot, like [`AuthoringBackoff`] does while finality lags. A paused voter only
+//! prevotes for the last finalized block, as voting rules only restrict the prevote target. It
+//! keeps taking part in rounds and still precommits on what the other voters prevoted, so once
+//! enough of them prevote for new blocks, its precommits help to finalize them. Finality only
+//! stalls while more than a third of the voting weight is paused. The node stays connected,
+//! imports blocks and gossips transactions and votes throughout. The methods are unsafe, so they
+//! are only served with `--rpc-methods unsafe`.
+
+use crate::backoff::AuthoringBackoff;
+use jsonrpsee::{core::RpcResult, proc_macros::rpc, Extensions};
+use pez_solochain_template_runtime::{opaque::Block, BlockNumber};
+use pezsc_consensus_grandpa::{VotingRule, VotingRuleResult};
+use pezsc_consensus_slots::BackoffAuthoringBlocksStrategy;
+use pezsc_rpc_api::check_if_safe;
+use pezsp_blockchain::HeaderBackend;
+use pezsp_consensus_aura::Slot;
+use pezsp_runtime::traits::{Block as BlockT, Header as _};
+use serde::Serialize;
+use std::sync::{
+	atomic::{AtomicBool, Ordering},
+	Arc,
+};
+
+const LOG_TARGET: &str = "maintenance";
+
+/// Switches pausing block authoring and GRANDPA voting, shared with the RPC.
+#[derive(Clone, Default)]
+pub struct Maintenance {
+	authoring_paused: Arc<AtomicBool>,
+	voting_paused: Arc<AtomicBool>,
+}
+
+impl Maintenance {
+	/// The authoring backoff strategy, which also backs off while authoring is paused.
+	pub fn authoring(&self, backoff: Option<AuthoringBackoff>) -> PausableAuthoring {
+		PausableAuthoring { backoff, paused: self.authoring_paused.clone() }
+	}
+
+	/// The voting rule restricting prevotes to the last finalized block while voting is paused.
+	pub fn voting_rule(&self) -> PausableVoting {
+		PausableVoting { paused: self.voting_paused.clone() }
+	}
+
+	fn state(&self) -> MaintenanceState {
+		MaintenanceState {
+			authoring_paused: self.authoring_paused.load(Ordering::Relaxed),
+			voting_paused: self.voting_paused.load(Ordering::Relaxed),
+		}
+	}
+}
+
+/// Backs off authoring while it is paused, and otherwise as the [`AuthoringBackoff`] does.
+pub struct PausableAuthoring {
+	backoff: Option<AuthoringBackoff>,
+	paused: Arc<AtomicBool>,
+}
+
+impl BackoffAuthoringBlocksStrategy<BlockNumber> for PausableAuthoring {
+	fn should_backoff(
+		&self,
+		chain_head_number: BlockNumber,
+		chain_head_slot: Slot,
+		finalized_number: BlockNumber,
+		slot_now: Slot,
+		logging_target: &str,
+	) -> bool {
+		if self.paused.load(Ordering::Relaxed) {
+			log::debug!(target: logging_target, "Skipping slot {slot_now}: authoring is paused");
+			return true;
+		}
+
+		self.backoff.as_ref().is_some_and(|backoff| {
+			backoff.should_backoff(
+				chain_head_number,
+				chain_head_slot,
+				finalized_number,
+				slot_now,
+				logging_target,
+			)
+		})
+	}
+}
+
+/// Restricts the GRANDPA prevote to the last finalized block while voting is paused.
+///
+/// Precommits aren't restricted by voting rules, so they still follow the prevotes of the other
+/// voters.
+#[derive(Clone)]
+pub struct PausableVoting {
+	paused: Arc<AtomicBool>,
+}
+
+impl<B> VotingRule<Block, B> for PausableVoting
+where
+	B: HeaderBackend<Block> + 'static,
+{
+	fn restrict_vote(
+		&self,
+		_backend: Arc<B>,
+		base: &<Block as BlockT>::Header,
+		_best_target: &<Block as BlockT>::Header,
+		current_target: &<Block as BlockT>::Header,
+	) -> VotingRuleResult<Block> {
+		let restricted = (self.paused.load(Ordering::Relaxed)
+			&& current_target.hash() != base.hash())
+		.then(|| (base.hash(), *base.number()));
+		Box::pin(async move { restricted })
+	}
+}
+
+/// Whether authoring and voting are paused.
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MaintenanceState {
+	/// Whether block authoring is paused.
+	pub authoring_paused: bool,
+	/// Whether GRANDPA voting is paused.
+	pub voting_paused: bool,
+}
+
+/// Maintenance RPC methods.
+#[rpc(server, namespace = "maintenance")]
+pub trait MaintenanceApi {
+	/// Stops authoring blocks until `maintenance_resumeAuthoring` is called.
+	#[method(name = "pauseAuthoring", with_extensions)]
+	fn pause_authoring(&self) -> RpcResult<MaintenanceState>;
+
+	/// Authors blocks again after `maintenance_pauseAuthoring`.
+	#[method(name = "resumeAuthoring", with_extensions)]
+	fn resume_authoring(&self) -> RpcResult<MaintenanceState>;
+
+	/// Stops prevoting for new blocks in GRANDPA until `maintenance_resumeVoting` is called.
+	#[method(name = "pauseVoting", with_extensions)]
+	fn pause_voting(&self) -> RpcResult<MaintenanceState>;
+
+	/// Prevotes for new blocks again after `maintenance_pauseVoting`.
+	#[method(name = "resumeVoting", with_extensions)]
+	fn resume_voting(&self) -> RpcResult<MaintenanceState>;
+
+	/// Whether authoring and voting are paused.
+	#[method(name = "state", with_extensions)]
+	fn state(&self) -> RpcResult<MaintenanceState>;
+}
+
+impl Maintenance {
+	fn switch(
+		&self,
+		ext: &Extensions,
+		switch: &AtomicBool,
+		paused: bool,
+		what: &str,
+	) -> RpcResult<MaintenanceState> {
+		check_if_safe(ext)?;
+		if switch.swap(paused, Ordering::Relaxed) != paused {
+			log::info!(
+				target: LOG_TARGET,
+				"{} {what}",
+				if paused { "⏸️  Paused" } else { "▶️  Resumed" },
+			);
+		}
+		Ok(self.state())
+	}
+}
+
+impl MaintenanceApiServer for Maintenance {
+	fn pause_authoring(&self, ext: &Extensions) -> RpcResult<MaintenanceState> {
+		self.switch(ext, &self.authoring_paused, true, "block authoring")
+	}
+
+	fn resume_authoring(&self, ext: &Extensions) -> RpcResult<MaintenanceState> {
+		self.switch(ext, &self.authoring_paused, false, "block authoring")
+	}
+
+	fn pause_voting(&self, ext: &Extensions) -> RpcResult<MaintenanceState> {
+		self.switch(ext, &self.voting_paused, true, "GRANDPA voting")
+	}
+
+	fn resume_voting(&self, ext: &Extensions) -> RpcResult<MaintenanceState> {
+		self.switch(ext, &self.voting_paused, false, "GRANDPA voting")
+	}
+
+	fn state(&self, ext: &Extensions) -> RpcResult<MaintenanceState> {
+		check_if_safe(ext)?;
+		Ok(Maintenance::state(self))
+	}
+}
@@ -0,0 +1,256 @@
+//! Prometheus metrics specific to this chain.
+//!
+//! Authorship and runtime events are counted on finalized blocks only, so blocks on abandoned
+//! forks are never counted. The finality lag is updated on every import and finality
+//! notification, and transaction pool rejections are reported by
+//! [`NodeTransactionPool`](crate::transaction_pool::NodeTransactionPool).
+//!
+//! Missed slots are only counted under Aura. A BABE slot may legitimately stay empty, so there is
+//! no authority to blame for it and `missed_slots_total` is not registered.
+
+use crate::{
+	block_tracing::block_events, service::FullClient, transaction_pool::Rejection,
+	voting_rules::block_slot,
+};
+use bizinikiwi_prometheus_endpoint::{
+	register, CounterVec, Gauge, Opts, PrometheusError, Registry, F64, U64,
+};
+use futures::{stream, StreamExt};
+use pez_solochain_template_runtime::{opaque::Block, Hash, RuntimeEvent};
+use pezsc_client_api::BlockchainEvents;
+use pezsc_transaction_pool_api::error::Error as PoolError;
+use pezsp_api::ProvideRuntimeApi;
+use pezsp_blockchain::HeaderBackend;
+use pezsp_consensus_aura::SlotDuration;
+#[cfg(not(feature = "babe"))]
+use pezsp_consensus_aura::{AuraApi, Slot};
+#[cfg(feature = "babe")]
+use pezsp_consensus_babe::BabeApi;
+use pezsp_core::{crypto::Ss58Codec, sr25519};
+use pezsp_runtime::{
+	traits::{Block as BlockT, Header as _},
+	transaction_validity::{InvalidTransaction, UnknownTransaction},
+};
+use std::sync::Arc;
+
+const LOG_TARGET: &str = "metrics";
+
+/// The chain specific metrics.
+pub struct Metrics {
+	authored_slots: CounterVec<U64>,
+	#[cfg(not(feature = "babe"))]
+	missed_slots: CounterVec<U64>,
+	finality_lag_blocks: Gauge<U64>,
+	finality_lag_seconds: Gauge<F64>,
+	runtime_events: CounterVec<U64>,
+	pool_rejections: CounterVec<U64>,
+}
+
+impl Metrics {
+	/// Registers the metrics with `registry`.
+	pub fn register(registry: &Registry) -> Result<Self, PrometheusError> {
+		Ok(Self {
+			authored_slots: register(
+				CounterVec::new(
+					Opts::new("authored_slots_total", "Number of finalized blocks, by author"),
+					&["authority"],
+				)?,
+				registry,
+			)?,
+			#[cfg(not(feature = "babe"))]
+			missed_slots: register(
+				CounterVec::new(
+					Opts::new(
+						"missed_slots_total",
+						"Number of slots without a finalized block, by the authority of the slot",
+					),
+					&["authority"],
+				)?,
+				registry,
+			)?,
+			finality_lag_blocks: register(
+				Gauge::new(
+					"finality_lag_blocks",
+					"Number of blocks the best block is ahead of the finalized one",
+				)?,
+				registry,
+			)?,
+			finality_lag_seconds: register(
+				Gauge::new(
+					"finality_lag_seconds",
+					"Time between the slots of the finalized block and of the best block",
+				)?,
+				registry,
+			)?,
+			runtime_events: register(
+				CounterVec::new(
+					Opts::new(
+						"runtime_events_total",
+						"Number of selected events in finalized blocks",
+					),
+					&["event"],
+				)?,
+				registry,
+			)?,
+			pool_rejections: register(
+				CounterVec::new(
+					Opts::new(
+						"transaction_pool_rejections_total",
+						"Number of transactions rejected by the transaction pool, by reason",
+					),
+					&["reason"],
+				)?,
+				registry,
+			)?,
+		})
+	}
+
+	/// Counts a transaction the pool refused to import.
+	pub fn report_rejection(&self, error: &PoolError) {
+		self.pool_rejections.with_label_values(&[rejection_reason(error)]).inc();
+	}
+
+	fn on_finalized(&self, client: &FullClient, hash: Hash) {
+		let Ok(Some(header)) = client.header(hash) else { return };
+
+		if let Some((authorities, author)) = authorship(client, &header) {
+			self.authored_slots.with_label_values(&[&authorities[author].to_ss58check()]).inc();
+
+			#[cfg(not(feature = "babe"))]
+			if let (Some(slot), Ok(Some(parent))) =
+				(block_slot(&header), client.header(*header.parent_hash()))
+			{
+				if let Some(parent_slot) = block_slot(&parent) {
+					self.report_missed_slots(&authorities, parent_slot, slot);
+				}
+			}
+		}
+
+		match block_events(client, hash) {
+			Ok(events) => {
+				for label in events.iter().filter_map(|record| event_label(&record.event)) {
+					self.runtime_events.with_label_values(&[label]).inc();
+				}
+			},
+			Err(e) => log::debug!(target: LOG_TARGET, "Failed to read the events of {hash}: {e}"),
+		}
+	}
+
+	/// Counts the slots between `parent_slot` and `slot` as missed by their Aura authorities.
+	#[cfg(not(feature = "babe"))]
+	fn report_missed_slots(&self, authorities: &[sr25519::Public], parent_slot: Slot, slot: Slot) {
+		let first_missed = *parent_slot + 1;
+		let missed = (*slot).saturating_sub(first_missed);
+		let count = authorities.len() as u64;
+		// Every authority misses one slot in `count` of the gap, starting with the first missed.
+		for offset in 0..missed.min(count) {
+			let authority = &authorities[((first_missed + offset) % count) as usize];
+			self.missed_slots
+				.with_label_values(&[&authority.to_ss58check()])
+				.inc_by((missed - offset).div_ceil(count));
+		}
+	}
+
+	fn update_finality_lag(&self, client: &FullClient, slot_duration: SlotDuration) {
+		let info = client.info();
+		self.finality_lag_blocks.set(info.best_number.saturating_sub(info.finalized_number).into());
+
+		let slot_of = |hash| client.header(hash).ok().flatten().as_ref().and_then(block_slot);
+		if let (Some(best), Some(finalized)) =
+			(slot_of(info.best_hash), slot_of(info.finalized_hash))
+		{
+			let lag = (*best).saturating_sub(*finalized) * slot_duration.as_millis();
+			self.finality_lag_seconds.set(lag as f64 / 1000.0);
+		}
+	}
+}
+
+/// Keeps the metrics up to date with the chain.
+pub async fn run(client: Arc<FullClient>, metrics: Arc<Metrics>, slot_duration: SlotDuration) {
+	let imports = client.import_notification_stream().map(|_| None);
+	let finality = client.finality_notification_stream().map(Some);
+	let mut notifications = stream::select(imports, finality);
+
+	while let Some(finalized) = notifications.next().await {
+		if let Some(finalized) = finalized {
+			for hash in finalized.tree_route.iter().chain([&finalized.hash]) {
+				metrics.on_finalized(&client, *hash);
+			}
+		}
+		metrics.update_finality_lag(&client, slot_duration);
+	}
+}
+
+/// The authorities the block with `header` was authored by, and the index of its author.
+#[cfg(not(feature = "babe"))]
+fn authorship(
+	client: &FullClient,
+	header: &<Block as BlockT>::Header,
+) -> Option<(Vec<sr25519::Public>, usize)> {
+	let slot = block_slot(header)?;
+	let authorities = client.runtime_api().authorities(*header.parent_hash()).ok()?;
+	if authorities.is_empty() {
+		return None;
+	}
+	let author = (*slot % authorities.len() as u64) as usize;
+	Some((authorities.into_iter().map(Into::into).collect(), author))
+}
+
+/// The authorities the block with `header` was authored by, and the index of its author.
+#[cfg(feature = "babe")]
+fn authorship(
+	client: &FullClient,
+	header: &<Block as BlockT>::Header,
+) -> Option<(Vec<sr25519::Public>, usize)> {
+	let pre_digest = pezsc_consensus_babe::find_pre_digest::<Block>(header).ok()?;
+	let epoch = client.runtime_api().current_epoch(*header.parent_hash()).ok()?;
+	let authorities = epoch.authorities.into_iter().map(|(id, _)| id.into()).collect::<Vec<_>>();
+	let author = pre_digest.authority_index() as usize;
+	(author < authorities.len()).then_some((authorities, author))
+}
+
+/// The label of the runtime events counted by `runtime_events_total`.
+fn event_label(event: &RuntimeEvent) -> Option<&'static str> {
+	Some(match event {
+		RuntimeEvent::Template(pezpallet_template::Event::SomethingStored { .. }) => {
+			"template_something_stored"
+		},
+		RuntimeEvent::Balances(pezpallet_balances::Event::Transfer { .. }) => "balances_transfer",
+		RuntimeEvent::System(pezframe_system::Event::ExtrinsicFailed { .. }) => {
+			"system_extrinsic_failed"
+		},
+		_ => return None,
+	})
+}
+
+/// The label of a pool rejection in `transaction_pool_rejections_total`.
+fn rejection_reason(error: &PoolError) -> &'static str {
+	match error {
+		PoolError::InvalidTransaction(invalid) => match invalid {
+			InvalidTransaction::Call => "invalid_call",
+			InvalidTransaction::Payment => "invalid_payment",
+			InvalidTransaction::Future => "invalid_future",
+			InvalidTransaction::Stale => "invalid_stale",
+			InvalidTransaction::BadProof => "invalid_bad_proof",
+			InvalidTransaction::AncientBirthBlock => "invalid_ancient_birth_block",
+			InvalidTransaction::ExhaustsResources => "invalid_exhausts_resources",
+			InvalidTransaction::Custom(code) => {
+				Rejection::from_code(*code).map_or("invalid_custom", |rejection| rejection.as_str())
+			},
+			InvalidTransaction::BadMandatory => "invalid_bad_mandatory",
+			InvalidTransaction::MandatoryValidation => "invalid_mandatory_validation",
+			InvalidTransaction::BadSigner => "invalid_bad_signer",
+			_ => "invalid_other",
+		},
+		PoolError::UnknownTransaction(unknown) => match unknown {
+			UnknownTransaction::CannotLookup => "unknown_cannot_lookup",
+			UnknownTransaction::NoUnsignedValidator => "unknown_no_unsigned_validator",
+			UnknownTransaction::Custom(_) => "unknown_custom",
+		},
+		PoolError::AlreadyImported(_) => "already_imported",
+		PoolError::TemporarilyBanned => "temporarily_banned",
+		PoolError::TooLowPriority { .. } => "too_low_priority",
+		PoolError::ImmediatelyDropped => "immediately_dropped",
+		_ => "other",
+	}
+}
@@ -7,25 +7,117 @@
 
 use std::sync::Arc;
 
+use crate::{
+	block_tracing::{BlockTracing, BlockTracingApiServer},
+	maintenance::{Maintenance, MaintenanceApiServer},
+};
+use futures::channel::mpsc;
 use jsonrpsee::RpcModule;
-use pez_solochain_template_runtime::{opaque::Block, AccountId, Balance, Nonce};
+use pez_solochain_template_runtime::{opaque::Block, AccountId, Balance, Hash, Nonce};
+use pezsc_consensus_manual_seal::EngineCommand;
 use pezsc_transaction_pool_api::TransactionPool;
 use pezsp_api::ProvideRuntimeApi;
 use pezsp_block_builder::BlockBuilder;
 use pezsp_blockchain::{Error as BlockChainError, HeaderBackend, HeaderMetadata};
 
+/// Dependencies of the BABE RPC.
+#[cfg(feature = "babe")]
+#[derive(Clone)]
+pub struct BabeDeps<SC> {
+	/// Handle to the BABE worker, answering epoch queries.
+	pub worker_handle: pezsc_consensus_babe::BabeWorkerHandle<Block>,
+	/// The keystore holding the local authority keys.
+	pub keystore: pezsp_keystore::KeystorePtr,
+	/// The chain selection strategy.
+	pub select_chain: SC,
+}
+
 /// Full client dependencies.
+#[cfg(not(feature = "babe"))]
 pub struct FullDeps<C, P> {
 	/// The client instance to use.
 	pub client: Arc<C>,
 	/// Transaction pool instance.
 	pub pool: Arc<P>,
+	/// Channel to the manual seal engine, if the node runs with `--sealing manual`.
+	pub command_sink: Option<mpsc::Sender<EngineCommand<Hash>>>,
+	/// The `trace_block` handler, if the node runs with `--block-tracing`.
+	pub block_tracing: Option<BlockTracing>,
+	/// The switches pausing block authoring and GRANDPA voting.
+	pub maintenance: Maintenance,
+}
+
+/// Full client dependencies.
+#[cfg(feature = "babe")]
+pub struct FullDeps<C, P, SC> {
+	/// The client instance to use.
+	pub client: Arc<C>,
+	/// Transaction pool instance.
+	pub pool: Arc<P>,
+	/// Channel to the manual seal engine, if the node runs with `--sealing manual`.
+	pub command_sink: Option<mpsc::Sender<EngineCommand<Hash>>>,
+	/// The `trace_block` handler, if the node runs with `--block-tracing`.
+	pub block_tracing: Option<BlockTracing>,
+	/// The switches pausing block authoring and GRANDPA voting.
+	pub maintenance: Maintenance,
+	/// BABE specific dependencies.
+	pub babe: BabeDeps<SC>,
 }
 
 /// Instantiate all full RPC extensions.
+#[cfg(not(feature = "babe"))]
 pub fn create_full<C, P>(
 	deps: FullDeps<C, P>,
 ) -> Result<RpcModule<()>, Box<dyn std::error::Error + Send + Sync>>
+where
+	C: ProvideRuntimeApi<Block>,
+	C: HeaderBackend<Block> + HeaderMetadata<Block, Error = BlockChainError> + 'static,
+	C: Send + Sync + 'static,
+	C::Api: bizinikiwi_frame_rpc_system::AccountNonceApi<Block, AccountId, Nonce>,
+	C::Api: pezpallet_transaction_payment_rpc::TransactionPaymentRuntimeApi<Block, Balance>,
+	C::Api: BlockBuilder<Block>,
+	P: TransactionPool + 'static,
+{
+	let FullDeps { client, pool, command_sink, block_tracing, maintenance } = deps;
+
+	create_common(client, pool, command_sink, block_tracing, maintenance)
+}
+
+/// Instantiate all full RPC extensions, including `babe_epochAuthorship`.
+#[cfg(feature = "babe")]
+pub fn create_full<C, P, SC>(
+	deps: FullDeps<C, P, SC>,
+) -> Result<RpcModule<()>, Box<dyn std::error::Error + Send + Sync>>
+where
+	C: ProvideRuntimeApi<Block>,
+	C: HeaderBackend<Block> + HeaderMetadata<Block, Error = BlockChainError> + 'static,
+	C: Send + Sync + 'static,
+	C::Api: bizinikiwi_frame_rpc_system::AccountNonceApi<Block, AccountId, Nonce>,
+	C::Api: pezpallet_transaction_payment_rpc::TransactionPaymentRuntimeApi<Block, Balance>,
+	C::Api: BlockBuilder<Block>,
+	C::Api: pezsp_consensus_babe::BabeApi<Block>,
+	P: TransactionPool + 'static,
+	SC: pezsp_consensus::SelectChain<Block> + 'static,
+{
+	use pezsc_consensus_babe_rpc::{Babe, BabeApiServer};
+
+	let FullDeps { client, pool, command_sink, block_tracing, maintenance, babe } = deps;
+	let BabeDeps { worker_handle, keystore, select_chain } = babe;
+
+	let mut module = create_common(client.clone(), pool, command_sink, block_tracing, maintenance)?;
+	module.merge(Babe::new(client, worker_handle, keystore, select_chain).into_rpc())?;
+
+	Ok(module)
+}
+
+/// The RPC extensions shared by Aura and BABE nodes.
+fn create_common<C, P>(
+	client: Arc<C>,
+	pool: Arc<P>,
+	command_sink: Option<mpsc::Sender<EngineCommand<Hash>>>,
+	block_tracing: Option<BlockTracing>,
+	maintenance: Maintenance,
+) -> Result<RpcModule<()>, Box<dyn std::error::Error + Send + Sync>>
 where
 	C: ProvideRuntimeApi<Block>,
 	C: HeaderBackend<Block> + HeaderMetadata<Block, Error = BlockChainError> + 'static,
@@ -37,13 +129,26 @@ where
 {
 	use bizinikiwi_frame_rpc_system::{System, SystemApiServer};
 	use pezpallet_transaction_payment_rpc::{TransactionPayment, TransactionPaymentApiServer};
+	use pezsc_consensus_manual_seal::rpc::{ManualSeal, ManualSealApiServer};
 
 	let mut module = RpcModule::new(());
-	let FullDeps { client, pool } = deps;
 
 	module.merge(System::new(client.clone(), pool).into_rpc())?;
 	module.merge(TransactionPayment::new(client).into_rpc())?;
 
+	if let Some(command_sink) = command_sink {
+		// `engine_createBlock` and `engine_finalizeBlock`
+		module.merge(ManualSeal::new(command_sink).into_rpc())?;
+	}
+
+	if let Some(block_tracing) = block_tracing {
+		// `trace_block`
+		module.merge(block_tracing.into_rpc())?;
+	}
+
+	// `maintenance_*`, refused unless unsafe RPC methods are allowed
+	module.merge(maintenance.into_rpc())?;
+
 	// Extend this RPC with a custom API by using the following syntax.
 	// `YourRpcStruct` should have a reference to a client, which is needed
 	// to call into the runtime.
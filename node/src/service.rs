@@ -1,27 +1,84 @@
 //! Service and ServiceFactory implementation. Specialized wrapper over bizinikiwi service.
 
-use futures::FutureExt;
+#[cfg(not(feature = "babe"))]
+use crate::block_announce::AuraAnnounceValidator;
+use crate::{
+	backoff::AuthoringBackoff,
+	block_tracing::{BlockTracing, ExecuteBlock},
+	chain_spec::{Extensions, GrandpaParams},
+	cli::{NodeParams, ProposalParams, Sealing},
+	health::Health,
+	maintenance::Maintenance,
+	metrics::Metrics,
+	offchain::OffchainExtensions,
+	telemetry_recorder::RotatingFile,
+	transaction_pool::{AdmissionPolicy, NodeTransactionPool},
+};
+use futures::{FutureExt, StreamExt};
 use pez_solochain_template_runtime::{self, apis::RuntimeApi, opaque::Block};
+use pezsc_basic_authorship::ProposerFactory;
 use pezsc_client_api::{Backend, BlockBackend};
-use pezsc_consensus_aura::{ImportQueueParams, SlotProportion, StartAuraParams};
+#[cfg(not(feature = "babe"))]
+use pezsc_consensus_aura::{ImportQueueParams, StartAuraParams};
 use pezsc_consensus_grandpa::SharedVoterState;
+#[cfg(not(feature = "babe"))]
+use pezsc_consensus_manual_seal::consensus::aura::AuraConsensusDataProvider;
+use pezsc_consensus_manual_seal::{
+	consensus::ConsensusDataProvider, EngineCommand, InstantSealParams, ManualSealParams,
+};
+use pezsc_consensus_slots::SlotProportion;
 use pezsc_service::{error::Error as ServiceError, Configuration, TaskManager, WarpSyncConfig};
 use pezsc_telemetry::{Telemetry, TelemetryWorker};
+use pezsc_tracing::block::TracingExecuteBlock;
 use pezsc_transaction_pool_api::OffchainTransactionPoolFactory;
+use pezsp_blockchain::HeaderBackend;
+#[cfg(not(feature = "babe"))]
 use pezsp_consensus_aura::sr25519::AuthorityPair as AuraPair;
+use pezsp_consensus_aura::{sr25519::AuthoritySignature as AuraSignature, SlotDuration};
+use pezsp_runtime::{traits::Block as BlockT, Percent};
+use pezsp_wasm_interface::ExtendedHostFunctions;
 use std::{sync::Arc, time::Duration};
 
-pub(crate) type FullClient = pezsc_service::TFullClient<
-	Block,
-	RuntimeApi,
-	pezsc_executor::WasmExecutor<pezsp_io::BizinikiwiHostFunctions>,
->;
+/// The `sp-io` host functions, with the offchain ones enforcing the node's offchain policies.
+///
+/// `ExtendedHostFunctions` replaces every `sp-io` host function that `crate::offchain` declares
+/// again, for all runtime calls of the node and not only for offchain workers. The replacements
+/// behave like the originals unless an extension such as `HttpAllowlistExt` is registered, which
+/// only happens for offchain worker runs.
+pub(crate) type HostFunctions =
+	ExtendedHostFunctions<pezsp_io::BizinikiwiHostFunctions, crate::offchain::HostFunctions>;
+
+pub(crate) type FullClient =
+	pezsc_service::TFullClient<Block, RuntimeApi, pezsc_executor::WasmExecutor<HostFunctions>>;
 type FullBackend = pezsc_service::TFullBackend<Block>;
 type FullSelectChain = pezsc_consensus::LongestChain<FullBackend, Block>;
+type FullGrandpaBlockImport =
+	pezsc_consensus_grandpa::GrandpaBlockImport<FullBackend, Block, FullClient, FullSelectChain>;
+
+/// The block import used for authoring, which is the GRANDPA one with Aura.
+#[cfg(not(feature = "babe"))]
+type FullBlockImport = FullGrandpaBlockImport;
+/// The block import used for authoring, which wraps the GRANDPA one with BABE.
+#[cfg(feature = "babe")]
+type FullBlockImport = pezsc_consensus_babe::BabeBlockImport<
+	Block,
+	FullClient,
+	FullGrandpaBlockImport,
+	pezsc_consensus_babe::BabeCreateInherentDataProviders<Block>,
+	FullSelectChain,
+>;
 
-/// The minimum period of blocks on which justifications will be
-/// imported and generated.
-const GRANDPA_JUSTIFICATION_PERIOD: u32 = 512;
+/// Consensus specific parts of the partial components, needed to start block authoring.
+pub struct ConsensusLink {
+	/// The slot duration of the chain.
+	pub slot_duration: SlotDuration,
+	/// Shared epoch data, handed from the import queue to the BABE worker.
+	#[cfg(feature = "babe")]
+	pub babe_link: pezsc_consensus_babe::BabeLink<Block>,
+	/// Handle to the BABE worker, used by the `babe_epochAuthorship` RPC.
+	#[cfg(feature = "babe")]
+	pub babe_worker_handle: pezsc_consensus_babe::BabeWorkerHandle<Block>,
+}
 
 pub type Service = pezsc_service::PartialComponents<
 	FullClient,
@@ -30,18 +87,24 @@ pub type Service = pezsc_service::PartialComponents<
 	pezsc_consensus::DefaultImportQueue<Block>,
 	pezsc_transaction_pool::TransactionPoolHandle<Block, FullClient>,
 	(
-		pezsc_consensus_grandpa::GrandpaBlockImport<
-			FullBackend,
-			Block,
-			FullClient,
-			FullSelectChain,
-		>,
+		FullBlockImport,
 		pezsc_consensus_grandpa::LinkHalf<Block, FullClient, FullSelectChain>,
+		ConsensusLink,
 		Option<Telemetry>,
 	),
 >;
 
-pub fn new_partial(config: &Configuration) -> Result<Service, ServiceError> {
+/// A full node started by [`new_full`].
+pub struct FullNode {
+	/// Runs the tasks of the node, which are stopped when it is dropped.
+	pub task_manager: TaskManager,
+	/// The client of the node.
+	pub client: Arc<FullClient>,
+	/// The transaction pool of the node.
+	pub transaction_pool: Arc<pezsc_transaction_pool::TransactionPoolHandle<Block, FullClient>>,
+}
+
+pub fn new_partial(config: &Configuration, params: &NodeParams) -> Result<Service, ServiceError> {
 	let telemetry = config
 		.telemetry_endpoints
 		.clone()
@@ -53,8 +116,7 @@ pub fn new_partial(config: &Configuration) -> Result<Service, ServiceError> {
 		})
 		.transpose()?;
 
-	let executor =
-		pezsc_service::new_wasm_executor::<pezsp_io::BizinikiwiHostFunctions>(&config.executor);
+	let executor = pezsc_service::new_wasm_executor::<HostFunctions>(&config.executor);
 	let (client, backend, keystore_container, task_manager) =
 		pezsc_service::new_full_parts::<Block, RuntimeApi, _>(
 			config,
@@ -81,44 +143,121 @@ pub fn new_partial(config: &Configuration) -> Result<Service, ServiceError> {
 		.build(),
 	);
 
-	let (grandpa_block_import, grandpa_link) = pezsc_consensus_grandpa::block_import(
-		client.clone(),
-		GRANDPA_JUSTIFICATION_PERIOD,
-		&client,
-		select_chain.clone(),
-		telemetry.as_ref().map(|x| x.handle()),
-	)?;
-
-	let cidp_client = client.clone();
-	let import_queue =
-		pezsc_consensus_aura::import_queue::<AuraPair, _, _, _, _, _>(ImportQueueParams {
-			block_import: grandpa_block_import.clone(),
-			justification_import: Some(Box::new(grandpa_block_import.clone())),
-			client: client.clone(),
-			create_inherent_data_providers: move |parent_hash, _| {
-				let cidp_client = cidp_client.clone();
-				async move {
-					let slot_duration = pezsc_consensus_aura::standalone::slot_duration_at(
-						&*cidp_client,
-						parent_hash,
-					)?;
-					let timestamp = pezsp_timestamp::InherentDataProvider::from_system_time();
+	let grandpa_params = grandpa_params(config);
+	let (grandpa_block_import, grandpa_link) =
+		pezsc_consensus_grandpa::block_import_with_authority_set_hard_forks(
+			client.clone(),
+			grandpa_params.justification_period,
+			&client,
+			select_chain.clone(),
+			grandpa_params.authority_set_hard_forks(),
+			telemetry.as_ref().map(|x| x.handle()),
+		)?;
 
-					let slot =
+	// Sealed blocks carry no Aura seal, so they can't go through the Aura verifier.
+	#[cfg(not(feature = "babe"))]
+	if params.sealing.is_some() {
+		let import_queue = pezsc_consensus_manual_seal::import_queue(
+			Box::new(client.clone()),
+			&task_manager.spawn_essential_handle(),
+			config.prometheus_registry(),
+		);
+		let consensus_link =
+			ConsensusLink { slot_duration: pezsc_consensus_aura::slot_duration(&*client)? };
+
+		return Ok(pezsc_service::PartialComponents {
+			client,
+			backend,
+			task_manager,
+			import_queue,
+			keystore_container,
+			select_chain,
+			transaction_pool,
+			other: (grandpa_block_import, grandpa_link, consensus_link, telemetry),
+		});
+	}
+
+	// The sealing engines only know how to fill in Aura digests.
+	#[cfg(feature = "babe")]
+	if params.sealing.is_some() {
+		return Err(ServiceError::Other("`--sealing` is not supported by BABE nodes".into()));
+	}
+
+	#[cfg(not(feature = "babe"))]
+	let (import_queue, block_import, consensus_link) = {
+		let slot_duration = pezsc_consensus_aura::slot_duration(&*client)?;
+		let cidp_client = client.clone();
+		let import_queue =
+			pezsc_consensus_aura::import_queue::<AuraPair, _, _, _, _, _>(ImportQueueParams {
+				block_import: grandpa_block_import.clone(),
+				justification_import: Some(Box::new(grandpa_block_import.clone())),
+				client: client.clone(),
+				create_inherent_data_providers: move |parent_hash, _| {
+					let cidp_client = cidp_client.clone();
+					async move {
+						let slot_duration = pezsc_consensus_aura::standalone::slot_duration_at(
+							&*cidp_client,
+							parent_hash,
+						)?;
+						let timestamp = pezsp_timestamp::InherentDataProvider::from_system_time();
+
+						let slot =
 						pezsp_consensus_aura::inherents::InherentDataProvider::from_timestamp_and_slot_duration(
 							*timestamp,
 							slot_duration,
 						);
 
-					Ok((slot, timestamp))
-				}
-			},
-			spawner: &task_manager.spawn_essential_handle(),
-			registry: config.prometheus_registry(),
-			check_for_equivocation: Default::default(),
-			telemetry: telemetry.as_ref().map(|x| x.handle()),
-			compatibility_mode: Default::default(),
-		})?;
+						Ok((slot, timestamp))
+					}
+				},
+				spawner: &task_manager.spawn_essential_handle(),
+				registry: config.prometheus_registry(),
+				// Detected equivocations are only logged here, see `aura_equivocation` for reporting.
+				check_for_equivocation: Default::default(),
+				telemetry: telemetry.as_ref().map(|x| x.handle()),
+				compatibility_mode: Default::default(),
+			})?;
+
+		(import_queue, grandpa_block_import, ConsensusLink { slot_duration })
+	};
+
+	#[cfg(feature = "babe")]
+	let (import_queue, block_import, consensus_link) = {
+		let babe_config = pezsc_consensus_babe::configuration(&*client)?;
+		let slot_duration = babe_config.slot_duration();
+		let (block_import, babe_link) = pezsc_consensus_babe::block_import(
+			babe_config,
+			grandpa_block_import.clone(),
+			client.clone(),
+			Arc::new(move |_, _| async move {
+				let timestamp = pezsp_timestamp::InherentDataProvider::from_system_time();
+
+				let slot =
+					pezsp_consensus_babe::inherents::InherentDataProvider::from_timestamp_and_slot_duration(
+						*timestamp,
+						slot_duration,
+					);
+
+				Ok((slot, timestamp))
+			}) as pezsc_consensus_babe::BabeCreateInherentDataProviders<Block>,
+			select_chain.clone(),
+			OffchainTransactionPoolFactory::new(transaction_pool.clone()),
+		)?;
+
+		let (import_queue, babe_worker_handle) =
+			pezsc_consensus_babe::import_queue(pezsc_consensus_babe::ImportQueueParams {
+				link: babe_link.clone(),
+				block_import: block_import.clone(),
+				justification_import: Some(Box::new(grandpa_block_import)),
+				client: client.clone(),
+				slot_duration,
+				spawner: &task_manager.spawn_essential_handle(),
+				registry: config.prometheus_registry(),
+				telemetry: telemetry.as_ref().map(|x| x.handle()),
+			})?;
+
+		(import_queue, block_import, ConsensusLink { slot_duration, babe_link, babe_worker_handle })
+	};
 
 	Ok(pezsc_service::PartialComponents {
 		client,
@@ -128,7 +267,7 @@ pub fn new_partial(config: &Configuration) -> Result<Service, ServiceError> {
 		keystore_container,
 		select_chain,
 		transaction_pool,
-		other: (grandpa_block_import, grandpa_link, telemetry),
+		other: (block_import, grandpa_link, consensus_link, telemetry),
 	})
 }
 
@@ -137,7 +276,8 @@ pub fn new_full<
 	N: pezsc_network::NetworkBackend<Block, <Block as pezsp_runtime::traits::Block>::Hash>,
 >(
 	config: Configuration,
-) -> Result<TaskManager, ServiceError> {
+	params: NodeParams,
+) -> Result<FullNode, ServiceError> {
 	let pezsc_service::PartialComponents {
 		client,
 		backend,
@@ -146,8 +286,16 @@ pub fn new_full<
 		keystore_container,
 		select_chain,
 		transaction_pool,
-		other: (block_import, grandpa_link, mut telemetry),
-	} = new_partial(&config)?;
+		other: (block_import, grandpa_link, consensus_link, mut telemetry),
+	} = new_partial(&config, &params)?;
+	let slot_duration = consensus_link.slot_duration;
+
+	if let Some(path) = &params.authority_keys {
+		crate::authority_keys::provision(path, &client, &keystore_container.keystore())
+			.map_err(ServiceError::Other)?;
+	}
+
+	let grandpa_params = grandpa_params(&config);
 
 	let mut net_config = pezsc_network::config::FullNetworkConfiguration::<
 		Block,
@@ -172,24 +320,57 @@ pub fn new_full<
 	let warp_sync = Arc::new(pezsc_consensus_grandpa::warp_proof::NetworkProvider::new(
 		backend.clone(),
 		grandpa_link.shared_authority_set().clone(),
-		Vec::default(),
+		grandpa_params.authority_set_hard_forks(),
 	));
 
+	let health_listener = Health::bind(&params.health)?;
+	let telemetry_file = RotatingFile::open(&params.telemetry_file)?;
+	let chain_metrics =
+		config.prometheus_registry().map(Metrics::register).transpose()?.map(Arc::new);
+	// Transactions from the RPC and the network go through these pools, so they can be observed.
+	// Only the RPC applies the admission policy, peers must not be punished for gossiping
+	// transactions that are merely refused by this node.
+	let network_transaction_pool =
+		Arc::new(NodeTransactionPool::new(transaction_pool.clone(), chain_metrics.clone(), None));
+	let rpc_transaction_pool = Arc::new(NodeTransactionPool::new(
+		transaction_pool.clone(),
+		chain_metrics.clone(),
+		AdmissionPolicy::new(client.clone(), &params.pool_policy),
+	));
+
+	// Sealed blocks carry no Aura seal, and BABE headers are only checked on import.
+	#[cfg(not(feature = "babe"))]
+	let block_announce_validator_builder = match params.sealing {
+		None => Some(
+			AuraAnnounceValidator::new(
+				client.clone(),
+				slot_duration,
+				&params.block_announce,
+				config.prometheus_registry(),
+			)?
+			.into_builder(),
+		),
+		Some(_) => None,
+	};
+	#[cfg(feature = "babe")]
+	let block_announce_validator_builder = None;
+
 	let (network, system_rpc_tx, tx_handler_controller, sync_service) =
 		pezsc_service::build_network(pezsc_service::BuildNetworkParams {
 			config: &config,
 			net_config,
 			client: client.clone(),
-			transaction_pool: transaction_pool.clone(),
+			transaction_pool: network_transaction_pool,
 			spawn_handle: task_manager.spawn_handle(),
 			import_queue,
-			block_announce_validator_builder: None,
+			block_announce_validator_builder,
 			warp_sync_config: Some(WarpSyncConfig::WithProvider(warp_sync)),
 			block_relay: None,
 			metrics,
 		})?;
 
 	if config.offchain_worker.enabled {
+		let offchain_extensions = OffchainExtensions::new(&params.offchain);
 		let offchain_workers =
 			pezsc_offchain::OffchainWorkers::new(pezsc_offchain::OffchainWorkerOptions {
 				runtime_api_provider: client.clone(),
@@ -200,8 +381,8 @@ pub fn new_full<
 					transaction_pool.clone(),
 				)),
 				network_provider: Arc::new(network.clone()),
-				enable_http_requests: true,
-				custom_extensions: |_| vec![],
+				enable_http_requests: !params.offchain.no_offchain_http,
+				custom_extensions: move |hash| offchain_extensions.build(hash),
 			})?;
 		task_manager.spawn_handle().spawn(
 			"offchain-workers-runner",
@@ -212,17 +393,67 @@ pub fn new_full<
 
 	let role = config.role;
 	let force_authoring = config.force_authoring;
-	let backoff_authoring_blocks: Option<()> = None;
+	// Without GRANDPA nothing is ever finalized, so backing off would eventually stop authoring.
+	let backoff_authoring_blocks = if config.disable_grandpa {
+		None
+	} else {
+		AuthoringBackoff::new(&params.backoff, config.prometheus_registry())?
+	};
+	let maintenance = Maintenance::default();
 	let name = config.network.node_name.clone();
-	let enable_grandpa = !config.disable_grandpa;
+	let sealing = params.sealing;
+	let enable_grandpa = !config.disable_grandpa && sealing.is_none();
+	let grandpa_observer = params.grandpa_observer;
+	if grandpa_observer && role.is_authority() {
+		return Err(ServiceError::Other(
+			"`--grandpa-observer` can only be used on non-authority nodes".into(),
+		));
+	}
 	let prometheus_registry = config.prometheus_registry().cloned();
+	let proposal = params.proposal;
+	let block_proposal_slot_portion =
+		SlotProportion::new(proposal.block_proposal_slot_portion.unwrap_or(2f32 / 3f32));
+	let max_block_proposal_slot_portion =
+		proposal.max_block_proposal_slot_portion.map(SlotProportion::new);
+
+	// Commands for the manual seal engine, fed either by the RPC or by the interval timer.
+	let (command_sink, commands_stream) = match sealing {
+		Some(Sealing::Manual) | Some(Sealing::Interval(_)) => {
+			let (sink, stream) = futures::channel::mpsc::channel(1024);
+			(Some(sink), Some(stream))
+		},
+		_ => (None, None),
+	};
+
+	let tracing_execute_block = params.block_tracing.then(|| {
+		Arc::new(ExecuteBlock::new(client.clone())) as Arc<dyn TracingExecuteBlock<Block>>
+	});
 
 	let rpc_extensions_builder = {
 		let client = client.clone();
 		let pool = transaction_pool.clone();
+		let command_sink = command_sink.clone().filter(|_| sealing == Some(Sealing::Manual));
+		let block_tracing = tracing_execute_block
+			.clone()
+			.map(|execute_block| BlockTracing::new(client.clone(), execute_block));
+		let maintenance = maintenance.clone();
+		#[cfg(feature = "babe")]
+		let babe = crate::rpc::BabeDeps {
+			worker_handle: consensus_link.babe_worker_handle.clone(),
+			keystore: keystore_container.keystore(),
+			select_chain: select_chain.clone(),
+		};
 
 		Box::new(move |_| {
-			let deps = crate::rpc::FullDeps { client: client.clone(), pool: pool.clone() };
+			let deps = crate::rpc::FullDeps {
+				client: client.clone(),
+				pool: pool.clone(),
+				command_sink: command_sink.clone(),
+				block_tracing: block_tracing.clone(),
+				maintenance: maintenance.clone(),
+				#[cfg(feature = "babe")]
+				babe: babe.clone(),
+			};
 			crate::rpc::create_full(deps).map_err(Into::into)
 		})
 	};
@@ -232,7 +463,7 @@ pub fn new_full<
 		client: client.clone(),
 		keystore: keystore_container.keystore(),
 		task_manager: &mut task_manager,
-		transaction_pool: transaction_pool.clone(),
+		transaction_pool: rpc_transaction_pool,
 		rpc_builder: rpc_extensions_builder,
 		backend,
 		system_rpc_tx,
@@ -240,20 +471,156 @@ pub fn new_full<
 		sync_service: sync_service.clone(),
 		config,
 		telemetry: telemetry.as_mut(),
-		tracing_execute_block: None,
+		tracing_execute_block,
 	})?;
 
-	if role.is_authority() {
-		let proposer_factory = pezsc_basic_authorship::ProposerFactory::new(
+	if let Some(chain_metrics) = chain_metrics {
+		task_manager.spawn_handle().spawn(
+			"chain-metrics",
+			None,
+			crate::metrics::run(client.clone(), chain_metrics, slot_duration),
+		);
+	}
+
+	if let Some(file) = telemetry_file {
+		task_manager.spawn_handle().spawn(
+			"telemetry-recorder",
+			None,
+			crate::telemetry_recorder::run(
+				client.clone(),
+				network.clone(),
+				transaction_pool.clone(),
+				file,
+			),
+		);
+	}
+
+	if let Some(listener) = health_listener {
+		let health = Health::new(
+			client.clone(),
+			network.clone(),
+			sync_service.clone(),
+			keystore_container.keystore(),
+			role.is_authority(),
+			params.health,
+		);
+		task_manager.spawn_handle().spawn("health-endpoint", None, health.run(listener));
+	}
+
+	// Sealing modes author alone, so there is nobody to equivocate. BABE reports equivocations
+	// from its own import queue.
+	#[cfg(not(feature = "babe"))]
+	if sealing.is_none() {
+		task_manager.spawn_handle().spawn(
+			"aura-equivocation-reporter",
+			None,
+			crate::aura_equivocation::report_equivocations(
+				client.clone(),
+				OffchainTransactionPoolFactory::new(transaction_pool.clone()),
+				slot_duration,
+				sync_service.clone(),
+			),
+		);
+	}
+
+	let node_client = client.clone();
+
+	if let Some(sealing) = sealing {
+		let mut proposer_factory = ProposerFactory::new(
 			task_manager.spawn_handle(),
 			client.clone(),
 			transaction_pool.clone(),
 			prometheus_registry.as_ref(),
 			telemetry.as_ref().map(|x| x.handle()),
 		);
+		apply_proposal_limits(&mut proposer_factory, &proposal);
+
+		let cidp_client = client.clone();
+		let create_inherent_data_providers = move |parent_hash, ()| {
+			let cidp_client = cidp_client.clone();
+			async move {
+				let timestamp = sealing_timestamp(&cidp_client, parent_hash, slot_duration)?;
+
+				let slot =
+					pezsp_consensus_aura::inherents::InherentDataProvider::from_timestamp_and_slot_duration(
+						*timestamp,
+						slot_duration,
+					);
+
+				Ok((slot, timestamp))
+			}
+		};
+		#[cfg(not(feature = "babe"))]
+		let consensus_data_provider = Some(Box::new(AuraConsensusDataProvider::new(client.clone()))
+			as Box<dyn ConsensusDataProvider<Block, Proof = _>>);
+		// `new_partial` refuses to seal BABE blocks, so this is never used.
+		#[cfg(feature = "babe")]
+		let consensus_data_provider: Option<Box<dyn ConsensusDataProvider<Block, Proof = _>>> = None;
+
+		let authorship = match (sealing, command_sink, commands_stream) {
+			(Sealing::Instant, _, _) => {
+				pezsc_consensus_manual_seal::run_instant_seal_and_finalize(InstantSealParams {
+					block_import: client.clone(),
+					env: proposer_factory,
+					client,
+					pool: transaction_pool.clone(),
+					select_chain,
+					consensus_data_provider,
+					create_inherent_data_providers,
+				})
+				.boxed()
+			},
+			(sealing, Some(mut command_sink), Some(commands_stream)) => {
+				if let Sealing::Interval(millis) = sealing {
+					task_manager.spawn_handle().spawn("interval-sealing", None, async move {
+						loop {
+							futures_timer::Delay::new(Duration::from_millis(millis)).await;
+							let command = EngineCommand::SealNewBlock {
+								create_empty: true,
+								finalize: true,
+								parent_hash: None,
+								sender: None,
+							};
+							if command_sink.try_send(command).is_err() {
+								log::warn!("Sealing engine is lagging, skipping a block");
+							}
+						}
+					});
+				}
 
-		let slot_duration = pezsc_consensus_aura::slot_duration(&*client)?;
+				pezsc_consensus_manual_seal::run_manual_seal(ManualSealParams {
+					block_import: client.clone(),
+					env: proposer_factory,
+					client,
+					pool: transaction_pool.clone(),
+					commands_stream: commands_stream.boxed(),
+					select_chain,
+					consensus_data_provider,
+					create_inherent_data_providers,
+				})
+				.boxed()
+			},
+			_ => unreachable!("a command channel is created for manual and interval sealing; qed"),
+		};
 
+		// the sealing task is considered essential, i.e. if it fails we take down the service
+		// with it.
+		task_manager.spawn_essential_handle().spawn_blocking(
+			"manual-seal",
+			Some("block-authoring"),
+			authorship,
+		);
+	} else if role.is_authority() {
+		let mut proposer_factory = ProposerFactory::new(
+			task_manager.spawn_handle(),
+			client.clone(),
+			transaction_pool.clone(),
+			prometheus_registry.as_ref(),
+			telemetry.as_ref().map(|x| x.handle()),
+		);
+		apply_proposal_limits(&mut proposer_factory, &proposal);
+
+		#[cfg(not(feature = "babe"))]
 		let aura = pezsc_consensus_aura::start_aura::<AuraPair, _, _, _, _, _, _, _, _, _, _>(
 			StartAuraParams {
 				slot_duration,
@@ -273,12 +640,12 @@ pub fn new_full<
 					Ok((slot, timestamp))
 				},
 				force_authoring,
-				backoff_authoring_blocks,
+				backoff_authoring_blocks: Some(maintenance.authoring(backoff_authoring_blocks)),
 				keystore: keystore_container.keystore(),
 				sync_oracle: sync_service.clone(),
 				justification_sync_link: sync_service.clone(),
-				block_proposal_slot_portion: SlotProportion::new(2f32 / 3f32),
-				max_block_proposal_slot_portion: None,
+				block_proposal_slot_portion,
+				max_block_proposal_slot_portion,
 				telemetry: telemetry.as_ref().map(|x| x.handle()),
 				compatibility_mode: Default::default(),
 			},
@@ -286,9 +653,47 @@ pub fn new_full<
 
 		// the AURA authoring task is considered essential, i.e. if it
 		// fails we take down the service with it.
+		#[cfg(not(feature = "babe"))]
 		task_manager
 			.spawn_essential_handle()
 			.spawn_blocking("aura", Some("block-authoring"), aura);
+
+		#[cfg(feature = "babe")]
+		let babe = pezsc_consensus_babe::start_babe(pezsc_consensus_babe::BabeParams {
+			keystore: keystore_container.keystore(),
+			client,
+			select_chain,
+			env: proposer_factory,
+			block_import,
+			sync_oracle: sync_service.clone(),
+			justification_sync_link: sync_service.clone(),
+			create_inherent_data_providers: move |_, ()| async move {
+				let timestamp = pezsp_timestamp::InherentDataProvider::from_system_time();
+
+				let slot =
+					pezsp_consensus_babe::inherents::InherentDataProvider::from_timestamp_and_slot_duration(
+						*timestamp,
+						slot_duration,
+					);
+
+				Ok((slot, timestamp))
+			},
+			force_authoring,
+			backoff_authoring_blocks: Some(maintenance.authoring(backoff_authoring_blocks)),
+			babe_link: consensus_link.babe_link,
+			block_proposal_slot_portion,
+			max_block_proposal_slot_portion,
+			telemetry: telemetry.as_ref().map(|x| x.handle()),
+		})?;
+
+		// the BABE authoring task is considered essential, i.e. if it
+		// fails we take down the service with it.
+		#[cfg(feature = "babe")]
+		task_manager.spawn_essential_handle().spawn_blocking(
+			"babe-proposer",
+			Some("block-authoring"),
+			babe,
+		);
 	}
 
 	if enable_grandpa {
@@ -297,44 +702,104 @@ pub fn new_full<
 		let keystore = if role.is_authority() { Some(keystore_container.keystore()) } else { None };
 
 		let grandpa_config = pezsc_consensus_grandpa::Config {
-			// FIXME #1578 make this available through chainspec
-			gossip_duration: Duration::from_millis(333),
-			justification_generation_period: GRANDPA_JUSTIFICATION_PERIOD,
+			gossip_duration: grandpa_params.gossip_duration(),
+			justification_generation_period: grandpa_params.justification_period,
 			name: Some(name),
-			observer_enabled: false,
+			observer_enabled: grandpa_observer,
 			keystore,
 			local_role: role,
 			telemetry: telemetry.as_ref().map(|x| x.handle()),
 			protocol_name: grandpa_protocol_name,
 		};
 
-		// start the full GRANDPA voter
-		// NOTE: non-authorities could run the GRANDPA observer protocol, but at
-		// this point the full voter should provide better guarantees of block
-		// and vote data availability than the observer. The observer has not
-		// been tested extensively yet and having most nodes in a network run it
-		// could lead to finality stalls.
-		let grandpa_config = pezsc_consensus_grandpa::GrandpaParams {
-			config: grandpa_config,
-			link: grandpa_link,
-			network,
-			sync: Arc::new(sync_service),
-			notification_service: grandpa_notification_service,
-			voting_rule: pezsc_consensus_grandpa::VotingRulesBuilder::default().build(),
-			prometheus_registry,
-			shared_voter_state: SharedVoterState::empty(),
-			telemetry: telemetry.as_ref().map(|x| x.handle()),
-			offchain_tx_pool_factory: OffchainTransactionPoolFactory::new(transaction_pool),
-		};
+		if grandpa_observer {
+			// the observer imports justifications and follows finality, but never votes. It
+			// provides weaker guarantees of block and vote data availability than the full
+			// voter, so it is only started on request and having most nodes in a network run it
+			// could lead to finality stalls.
+			task_manager.spawn_essential_handle().spawn_blocking(
+				"grandpa-observer",
+				None,
+				pezsc_consensus_grandpa::run_grandpa_observer(
+					grandpa_config,
+					grandpa_link,
+					network,
+					Arc::new(sync_service),
+					grandpa_notification_service,
+				)?,
+			);
+		} else {
+			// start the full GRANDPA voter
+			// NOTE: non-authorities could run the GRANDPA observer protocol (`--grandpa-observer`),
+			// but by default the full voter is used as it provides better guarantees of block and
+			// vote data availability than the observer.
+			let grandpa_config = pezsc_consensus_grandpa::GrandpaParams {
+				config: grandpa_config,
+				link: grandpa_link,
+				network,
+				sync: Arc::new(sync_service),
+				notification_service: grandpa_notification_service,
+				voting_rule: crate::voting_rules::voting_rule(
+					grandpa_params.voting_rules.iter().chain(&params.grandpa_voting_rules).copied(),
+					slot_duration,
+					&maintenance,
+				),
+				prometheus_registry,
+				shared_voter_state: SharedVoterState::empty(),
+				telemetry: telemetry.as_ref().map(|x| x.handle()),
+				offchain_tx_pool_factory: OffchainTransactionPoolFactory::new(
+					transaction_pool.clone(),
+				),
+			};
+
+			// the GRANDPA voter task is considered infallible, i.e.
+			// if it fails we take down the service with it.
+			task_manager.spawn_essential_handle().spawn_blocking(
+				"grandpa-voter",
+				None,
+				pezsc_consensus_grandpa::run_grandpa_voter(grandpa_config)?,
+			);
+		}
+	}
 
-		// the GRANDPA voter task is considered infallible, i.e.
-		// if it fails we take down the service with it.
-		task_manager.spawn_essential_handle().spawn_blocking(
-			"grandpa-voter",
-			None,
-			pezsc_consensus_grandpa::run_grandpa_voter(grandpa_config)?,
-		);
+	Ok(FullNode { task_manager, client: node_client, transaction_pool })
+}
+
+/// Applies the `--proposer-*` limits to `proposer_factory`.
+fn apply_proposal_limits<A, C, PR>(
+	proposer_factory: &mut ProposerFactory<A, C, PR>,
+	params: &ProposalParams,
+) {
+	if let Some(limit) = params.proposer_block_size_limit {
+		proposer_factory.set_default_block_size_limit(limit);
 	}
+	proposer_factory.set_soft_deadline(Percent::from_percent(params.proposer_soft_deadline));
+}
+
+/// GRANDPA parameters from the chain spec extensions, falling back to the defaults for specs
+/// that don't carry them.
+fn grandpa_params(config: &Configuration) -> GrandpaParams {
+	Extensions::try_get(&*config.chain_spec).map(|e| e.grandpa.clone()).unwrap_or_default()
+}
 
-	Ok(task_manager)
+/// Timestamp inherent for the seal-on-demand engines.
+///
+/// Uses the system time, but never less than one slot after the parent block, so that blocks
+/// sealed in quick succession still satisfy the strictly increasing slot check of
+/// `pezpallet_aura`.
+fn sealing_timestamp(
+	client: &FullClient,
+	parent_hash: <Block as BlockT>::Hash,
+	slot_duration: SlotDuration,
+) -> Result<pezsp_timestamp::InherentDataProvider, Box<dyn std::error::Error + Send + Sync>> {
+	let parent = client
+		.header(parent_hash)?
+		.ok_or_else(|| format!("parent header {parent_hash:?} not found"))?;
+	let parent_slot = pezsc_consensus_aura::find_pre_digest::<Block, AuraSignature>(&parent)
+		.map_err(|e| format!("{e}"))?;
+
+	let earliest = (*parent_slot + 1).saturating_mul(slot_duration.as_millis());
+	let timestamp = pezsp_timestamp::Timestamp::current().max(earliest.into());
+
+	Ok(pezsp_timestamp::InherentDataProvider::new(timestamp))
 }
@@ -0,0 +1,417 @@
+//! Local recording of telemetry, for nodes that can't reach a telemetry server.
+//!
+//! With `--telemetry-file <path>` the node appends one JSON record per line for every block
+//! import (`block.import`), every finalized block (`notify.finalized`) and every five seconds with
+//! the interval stats (`system.interval`), named like the telemetry messages they mirror. The file
+//! is rotated once it reaches `--telemetry-file-max-size`, keeping `<path>.1` to `<path>.<n>`.
+//!
+//! The `telemetry-summary` subcommand reads such files back and prints the block times and the
+//! finality lag they recorded.
+
+use crate::{cli::TelemetryFileParams, service::FullClient};
+use futures::{stream, StreamExt};
+use pez_solochain_template_runtime::Hash;
+use pezsc_client_api::BlockchainEvents;
+use pezsc_network::{service::traits::NetworkService, NetworkStatusProvider};
+use pezsc_transaction_pool_api::TransactionPool;
+use pezsp_blockchain::HeaderBackend;
+use pezsp_consensus::BlockOrigin;
+use pezsp_runtime::traits::Header as _;
+use serde::{Deserialize, Serialize};
+use std::{
+	collections::BTreeMap,
+	fs::{self, File},
+	io::{self, BufRead, BufReader, BufWriter, Write},
+	path::{Path, PathBuf},
+	sync::Arc,
+	time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+const LOG_TARGET: &str = "telemetry-recorder";
+
+/// Interval of the `system.interval` records, the same as the one of the informant.
+const INTERVAL: Duration = Duration::from_secs(5);
+
+/// A line of a telemetry file.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Record {
+	/// Milliseconds since the Unix epoch.
+	pub ts: u64,
+	#[serde(flatten)]
+	pub message: Message,
+}
+
+/// The recorded telemetry messages.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "msg")]
+pub enum Message {
+	/// A block was imported.
+	#[serde(rename = "block.import")]
+	BlockImport { best: Hash, height: u32, origin: String, is_new_best: bool },
+	/// A block was finalized.
+	#[serde(rename = "notify.finalized")]
+	Finalized { best: Hash, height: u32 },
+	/// Periodic node stats.
+	#[serde(rename = "system.interval")]
+	Interval {
+		peers: usize,
+		txcount: usize,
+		height: u32,
+		finalized_height: u32,
+		bandwidth_download: u64,
+		bandwidth_upload: u64,
+	},
+}
+
+/// A JSONL file that is rotated once it grows too large.
+pub struct RotatingFile {
+	path: PathBuf,
+	max_size: u64,
+	keep: usize,
+	file: BufWriter<File>,
+	size: u64,
+}
+
+impl RotatingFile {
+	/// Opens the file configured by `--telemetry-file`, if any, appending to what it holds.
+	pub fn open(params: &TelemetryFileParams) -> io::Result<Option<Self>> {
+		let Some(path) = &params.telemetry_file else { return Ok(None) };
+		let file = File::options().create(true).append(true).open(path)?;
+		let size = file.metadata()?.len();
+		Ok(Some(Self {
+			path: path.clone(),
+			max_size: params.telemetry_file_max_size.saturating_mul(1024 * 1024),
+			keep: params.telemetry_file_keep,
+			file: BufWriter::new(file),
+			size,
+		}))
+	}
+
+	/// Appends `record` as a line, rotating the file first if it is full.
+	pub fn write(&mut self, record: &Record) -> io::Result<()> {
+		let mut line = serde_json::to_vec(record)?;
+		line.push(b'\n');
+		if self.size > 0 && self.size + line.len() as u64 > self.max_size {
+			self.rotate()?;
+		}
+		self.file.write_all(&line)?;
+		// Records are rare enough to keep the file readable while the node runs.
+		self.file.flush()?;
+		self.size += line.len() as u64;
+		Ok(())
+	}
+
+	/// Moves `<path>.<n>` to `<path>.<n + 1>`, dropping the oldest, and starts a new file.
+	fn rotate(&mut self) -> io::Result<()> {
+		self.file.flush()?;
+		if self.keep == 0 {
+			fs::remove_file(&self.path)?;
+		} else {
+			for index in (1..self.keep).rev() {
+				let from = rotated_path(&self.path, index);
+				if from.exists() {
+					fs::rename(from, rotated_path(&self.path, index + 1))?;
+				}
+			}
+			fs::rename(&self.path, rotated_path(&self.path, 1))?;
+		}
+		self.file = BufWriter::new(File::options().create(true).append(true).open(&self.path)?);
+		self.size = 0;
+		Ok(())
+	}
+}
+
+fn rotated_path(path: &Path, index: usize) -> PathBuf {
+	let mut path = path.as_os_str().to_owned();
+	path.push(format!(".{index}"));
+	path.into()
+}
+
+/// Records the telemetry of the node into `file` until the node stops.
+pub async fn run<P: TransactionPool>(
+	client: Arc<FullClient>,
+	network: Arc<dyn NetworkService>,
+	transaction_pool: Arc<P>,
+	mut file: RotatingFile,
+) {
+	let imports = client.import_notification_stream().map(|notification| {
+		Some(Message::BlockImport {
+			best: notification.hash,
+			height: *notification.header.number(),
+			origin: origin_name(notification.origin).into(),
+			is_new_best: notification.is_new_best,
+		})
+	});
+	let finality_client = client.clone();
+	let finality = client.finality_notification_stream().flat_map(move |notification| {
+		// Blocks finalized along with the notified one are only in its tree route.
+		let implicit = notification.tree_route.iter().filter_map(|hash| {
+			let height = finality_client.number(*hash).ok().flatten()?;
+			Some(Message::Finalized { best: *hash, height })
+		});
+		let finalized =
+			Message::Finalized { best: notification.hash, height: *notification.header.number() };
+		stream::iter(implicit.chain([finalized]).map(Some).collect::<Vec<_>>())
+	});
+	let intervals = stream::unfold((), |()| async {
+		futures_timer::Delay::new(INTERVAL).await;
+		Some((None, ()))
+	});
+	let mut messages = stream::select(stream::select(imports, finality), intervals);
+
+	while let Some(message) = messages.next().await {
+		let message = match message {
+			Some(message) => message,
+			None => {
+				let info = client.info();
+				let status = network.status().await.ok();
+				Message::Interval {
+					peers: status.as_ref().map_or(0, |status| status.num_connected_peers),
+					txcount: transaction_pool.status().ready,
+					height: info.best_number,
+					finalized_height: info.finalized_number,
+					bandwidth_download: status
+						.as_ref()
+						.map_or(0, |status| status.total_bytes_inbound),
+					bandwidth_upload: status
+						.as_ref()
+						.map_or(0, |status| status.total_bytes_outbound),
+				}
+			},
+		};
+		if let Err(e) = file.write(&Record { ts: now_millis(), message }) {
+			log::warn!(target: LOG_TARGET, "Failed to record telemetry: {e}");
+		}
+	}
+}
+
+fn origin_name(origin: BlockOrigin) -> &'static str {
+	match origin {
+		BlockOrigin::Genesis => "Genesis",
+		BlockOrigin::NetworkInitialSync => "NetworkInitialSync",
+		BlockOrigin::NetworkBroadcast => "NetworkBroadcast",
+		BlockOrigin::ConsensusBroadcast => "ConsensusBroadcast",
+		BlockOrigin::Own => "Own",
+		BlockOrigin::File => "File",
+	}
+}
+
+fn now_millis() -> u64 {
+	SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |since| since.as_millis() as u64)
+}
+
+/// Summarise telemetry files written with `--telemetry-file`.
+#[derive(Debug, Clone, clap::Parser)]
+pub struct TelemetrySummaryCmd {
+	/// The files to read, e.g. a telemetry file and its rotated parts.
+	#[arg(required = true, value_name = "FILE")]
+	pub files: Vec<PathBuf>,
+
+	/// Width in milliseconds of the buckets of the block time histogram.
+	#[arg(long, value_name = "MILLIS", default_value_t = 1000)]
+	pub bucket: u64,
+}
+
+impl TelemetrySummaryCmd {
+	/// Prints the summary of the files to stdout.
+	pub fn run(&self) -> pezsc_cli::Result<()> {
+		let mut records = Vec::new();
+		for path in &self.files {
+			let file = File::open(path)?;
+			for (number, line) in BufReader::new(file).lines().enumerate() {
+				let line = line?;
+				if line.trim().is_empty() {
+					continue;
+				}
+				let record = serde_json::from_str::<Record>(&line).map_err(|e| {
+					format!("{}:{}: invalid telemetry record: {e}", path.display(), number + 1)
+				})?;
+				records.push(record);
+			}
+		}
+		records.sort_by_key(|record| record.ts);
+
+		let summary = Summary::new(&records);
+		print!("{}", summary.render(self.bucket.max(1)));
+		Ok(())
+	}
+}
+
+/// What the records of a telemetry file tell about the chain.
+#[derive(Debug, Default)]
+struct Summary {
+	records: usize,
+	first_ts: u64,
+	last_ts: u64,
+	imported: usize,
+	authored: usize,
+	/// Milliseconds between the first imports of consecutive heights.
+	block_times: Vec<u64>,
+	/// Milliseconds between the import and the finalization of blocks.
+	finality_times: Vec<u64>,
+	/// Blocks between the best and the finalized block, at every interval.
+	finality_lags: Vec<u64>,
+	peers: Vec<u64>,
+}
+
+impl Summary {
+	fn new(records: &[Record]) -> Self {
+		let mut summary = Summary {
+			records: records.len(),
+			first_ts: records.first().map_or(0, |record| record.ts),
+			last_ts: records.last().map_or(0, |record| record.ts),
+			..Default::default()
+		};
+		// The first time every height was imported, and the blocks imported by hash.
+		let mut heights = BTreeMap::new();
+		let mut imports = BTreeMap::new();
+		for record in records {
+			match &record.message {
+				Message::BlockImport { best, height, origin, .. } => {
+					summary.imported += 1;
+					if origin == "Own" {
+						summary.authored += 1;
+					}
+					heights.entry(*height).or_insert(record.ts);
+					imports.entry(*best).or_insert(record.ts);
+				},
+				Message::Finalized { best, .. } => {
+					if let Some(imported) = imports.get(best) {
+						summary.finality_times.push(record.ts.saturating_sub(*imported));
+					}
+				},
+				Message::Interval { peers, height, finalized_height, .. } => {
+					summary.finality_lags.push(height.saturating_sub(*finalized_height).into());
+					summary.peers.push(*peers as u64);
+				},
+			}
+		}
+		summary.block_times = heights
+			.iter()
+			.zip(heights.iter().skip(1))
+			.filter(|((height, _), (next, _))| *next - *height == 1)
+			.map(|((_, at), (_, next_at))| next_at.saturating_sub(*at))
+			.collect();
+		summary
+	}
+
+	fn render(&self, bucket: u64) -> String {
+		let mut out = format!(
+			"{} records over {}s, {} blocks imported, {} authored\n",
+			self.records,
+			(self.last_ts - self.first_ts) / 1000,
+			self.imported,
+			self.authored,
+		);
+
+		out += &format!("\nBlock time (ms): {}\n", stats(&self.block_times));
+		let mut histogram = BTreeMap::new();
+		for time in &self.block_times {
+			*histogram.entry(time / bucket).or_insert(0usize) += 1;
+		}
+		let widest = histogram.values().copied().max().unwrap_or(0);
+		for (index, count) in histogram {
+			let bar = "#".repeat((count * 50).div_ceil(widest));
+			out +=
+				&format!("  {:>7} - {:<7} {bar} {count}\n", index * bucket, (index + 1) * bucket,);
+		}
+
+		out += &format!("\nTime to finality (ms): {}\n", stats(&self.finality_times));
+		out += &format!("Finality lag (blocks): {}\n", stats(&self.finality_lags));
+		out += &format!("Peers: {}\n", stats(&self.peers));
+		out
+	}
+}
+
+/// Minimum, median, 95th percentile and maximum of `values`.
+fn stats(values: &[u64]) -> String {
+	if values.is_empty() {
+		return "no data".into();
+	}
+	let mut sorted = values.to_vec();
+	sorted.sort_unstable();
+	let percentile = |p: usize| sorted[(sorted.len() - 1) * p / 100];
+	format!(
+		"min {}, median {}, p95 {}, max {} ({} samples)",
+		sorted[0],
+		percentile(50),
+		percentile(95),
+		sorted[sorted.len() - 1],
+		sorted.len(),
+	)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn import(ts: u64, height: u32, origin: &str) -> Record {
+		Record {
+			ts,
+			message: Message::BlockImport {
+				best: Hash::repeat_byte(height as u8),
+				height,
+				origin: origin.into(),
+				is_new_best: true,
+			},
+		}
+	}
+
+	#[test]
+	fn rotates_and_keeps_the_newest_files() {
+		let dir = tempfile::tempdir().unwrap();
+		let path = dir.path().join("telemetry.jsonl");
+		let params = TelemetryFileParams {
+			telemetry_file: Some(path.clone()),
+			telemetry_file_max_size: 1,
+			telemetry_file_keep: 2,
+		};
+		let mut file = RotatingFile::open(&params).unwrap().unwrap();
+		// Records take about 150 bytes, so a file only holds a handful of them.
+		file.max_size = 1000;
+		for height in 0..30 {
+			file.write(&import(height as u64, height, "Own")).unwrap();
+		}
+
+		assert!(rotated_path(&path, 1).exists());
+		assert!(rotated_path(&path, 2).exists());
+		assert!(!rotated_path(&path, 3).exists());
+		assert!(fs::metadata(&path).unwrap().len() <= 1000);
+		let last = fs::read_to_string(&path).unwrap();
+		assert!(last.lines().last().unwrap().contains("\"height\":29"));
+	}
+
+	#[test]
+	fn summarises_block_times_and_finality() {
+		let records = vec![
+			import(0, 1, "Own"),
+			import(6_000, 2, "NetworkBroadcast"),
+			import(6_500, 2, "NetworkBroadcast"),
+			import(12_000, 3, "Own"),
+			Record {
+				ts: 13_000,
+				message: Message::Finalized { best: Hash::repeat_byte(2), height: 2 },
+			},
+			Record {
+				ts: 14_000,
+				message: Message::Interval {
+					peers: 3,
+					txcount: 0,
+					height: 3,
+					finalized_height: 2,
+					bandwidth_download: 0,
+					bandwidth_upload: 0,
+				},
+			},
+		];
+
+		let summary = Summary::new(&records);
+
+		assert_eq!(summary.imported, 4);
+		assert_eq!(summary.authored, 2);
+		assert_eq!(summary.block_times, vec![6_000, 6_000]);
+		assert_eq!(summary.finality_times, vec![7_000]);
+		assert_eq!(summary.finality_lags, vec![1]);
+		assert!(summary.render(1000).contains("6000 - 7000"));
+	}
+}
@@ -0,0 +1,282 @@
+//! In-process network of full nodes for consensus and sync tests.
+//!
+//! [`TestNetwork`] starts nodes with [`service::new_full`] on the `local` chain, each with its own
+//! temporary base path and listening on loopback only. Alice and Bob, the authorities of the
+//! `local` preset, run the first two nodes. Every other node is a full node without authority
+//! keys. All nodes bootstrap from the first one.
+//!
+//! The helpers block the calling thread, so tests are plain `#[test]` functions. Nodes can't start
+//! without the runtime wasm, so tests check [`runtime_wasm_missing`] first and return early when
+//! the node was built with `SKIP_WASM_BUILD=1`.
+
+use crate::{
+	benchmarking::create_benchmark_extrinsic,
+	cli::Cli,
+	service::{self, FullNode},
+};
+use pez_solochain_template_runtime::{opaque::Block, AccountId, Hash, RuntimeCall, WASM_BINARY};
+use pezframe_system_rpc_runtime_api::AccountNonceApi;
+use pezsc_cli::BizinikiwiCli;
+use pezsc_transaction_pool_api::{TransactionPool, TransactionSource};
+use pezsp_api::ProvideRuntimeApi;
+use pezsp_blockchain::HeaderBackend;
+use pezsp_keyring::Sr25519Keyring;
+use std::{
+	collections::HashMap,
+	net::TcpListener,
+	time::{Duration, Instant},
+};
+use tempfile::TempDir;
+use tokio::runtime::Runtime;
+
+/// Authorities of the `local` chain, which run the first nodes of a [`TestNetwork`].
+const AUTHORITIES: [Sr25519Keyring; 2] = [Sr25519Keyring::Alice, Sr25519Keyring::Bob];
+
+/// Peer id of the first node, derived from its node key `0x…01`.
+const BOOTNODE_PEER_ID: &str = "12D3KooWEyoppNCUx8Yx66oV9fJnriXwCcXwDDUA2kj6vnc6iDEp";
+
+/// How often the waiting helpers look at the chain.
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// A node of a [`TestNetwork`], which keeps its database and ports across restarts.
+struct TestNode {
+	base_path: TempDir,
+	p2p_port: u16,
+	rpc_port: u16,
+	running: Option<FullNode>,
+}
+
+/// A network of full nodes running in this process.
+pub struct TestNetwork {
+	runtime: Runtime,
+	nodes: Vec<TestNode>,
+	/// The next nonce of the accounts that submitted transactions through the network.
+	nonces: HashMap<AccountId, u32>,
+}
+
+impl TestNetwork {
+	/// Starts a network of `count` nodes.
+	pub fn start(count: usize) -> Self {
+		let runtime = tokio::runtime::Builder::new_multi_thread()
+			.enable_all()
+			.build()
+			.expect("tokio runtime can be built");
+		let nodes = (0..count)
+			.map(|_| TestNode {
+				base_path: TempDir::new().expect("temporary directory can be created"),
+				p2p_port: free_port(),
+				rpc_port: free_port(),
+				running: None,
+			})
+			.collect();
+
+		let mut network = Self { runtime, nodes, nonces: HashMap::new() };
+		for index in 0..count {
+			network.restart(index);
+		}
+		network
+	}
+
+	/// The running node at `index`.
+	pub fn node(&self, index: usize) -> &FullNode {
+		self.nodes[index].running.as_ref().unwrap_or_else(|| panic!("node {index} is not running"))
+	}
+
+	/// Stops the node at `index`, keeping its database.
+	pub fn kill(&mut self, index: usize) {
+		if let Some(node) = self.nodes[index].running.take() {
+			let FullNode { task_manager, client, transaction_pool } = node;
+			drop((client, transaction_pool));
+			self.runtime.block_on(task_manager.clean_shutdown());
+		}
+	}
+
+	/// Starts the node at `index` again, or for the first time, on top of its database.
+	pub fn restart(&mut self, index: usize) {
+		self.kill(index);
+
+		let bootnode =
+			format!("/ip4/127.0.0.1/tcp/{}/p2p/{BOOTNODE_PEER_ID}", self.nodes[0].p2p_port);
+		let node = &self.nodes[index];
+		let mut args = vec![
+			"node-template".to_string(),
+			"--chain=local".into(),
+			format!("--base-path={}", node.base_path.path().display()),
+			format!("--listen-addr=/ip4/127.0.0.1/tcp/{}", node.p2p_port),
+			format!("--rpc-port={}", node.rpc_port),
+			format!("--node-key={index:064x}", index = index + 1),
+			"--no-mdns".into(),
+			"--no-prometheus".into(),
+			"--no-telemetry".into(),
+		];
+		match AUTHORITIES.get(index) {
+			Some(authority) => args.push(format!("--{}", format!("{authority:?}").to_lowercase())),
+			None => args.push(format!("--name=node-{index}")),
+		}
+		if index > 0 {
+			args.push(format!("--bootnodes={bootnode}"));
+		}
+
+		let cli = Cli::try_from_iter(args).expect("test node arguments are valid");
+		let config = cli
+			.create_configuration(&cli.run, self.runtime.handle().clone())
+			.expect("test node configuration can be created");
+		let _guard = self.runtime.enter();
+		let node = service::new_full::<
+			pezsc_network::NetworkWorker<Block, <Block as pezsp_runtime::traits::Block>::Hash>,
+		>(config, cli.node)
+		.unwrap_or_else(|e| panic!("node {index} can be started: {e}"));
+		self.nodes[index].running = Some(node);
+	}
+
+	/// Waits until the best block of the node at `index` is at least `number`.
+	pub fn wait_for_block(
+		&self,
+		index: usize,
+		number: u32,
+		timeout: Duration,
+	) -> Result<(), String> {
+		self.wait(timeout, || self.node(index).client.info().best_number >= number)
+			.map_err(|_| format!("node {index} did not import block #{number} within {timeout:?}"))
+	}
+
+	/// Waits until the node at `index` finalized a block of at least `number`.
+	pub fn wait_for_finalized(
+		&self,
+		index: usize,
+		number: u32,
+		timeout: Duration,
+	) -> Result<(), String> {
+		self.wait(timeout, || self.node(index).client.info().finalized_number >= number).map_err(
+			|_| format!("node {index} did not finalize block #{number} within {timeout:?}"),
+		)
+	}
+
+	/// Signs `call` by `sender` and submits it to the node at `index`.
+	///
+	/// Nonces are tracked across submissions, so several transactions of the same sender can be
+	/// in the pool at once.
+	pub fn submit(
+		&mut self,
+		index: usize,
+		sender: Sr25519Keyring,
+		call: RuntimeCall,
+	) -> Result<Hash, String> {
+		let account = sender.to_account_id();
+		let nonce = self
+			.account_nonce(index, &account)
+			.max(self.nonces.get(&account).copied().unwrap_or(0));
+		self.nonces.insert(account, nonce + 1);
+
+		let node = self.node(index);
+		let best_hash = node.client.info().best_hash;
+		let extrinsic = create_benchmark_extrinsic(&node.client, sender.pair(), call, nonce)?;
+		self.runtime
+			.block_on(node.transaction_pool.submit_one(
+				best_hash,
+				TransactionSource::External,
+				extrinsic.into(),
+			))
+			.map_err(|e| e.to_string())
+	}
+
+	/// The nonce of `account` at the best block of the node at `index`.
+	pub fn account_nonce(&self, index: usize, account: &AccountId) -> u32 {
+		let client = &self.node(index).client;
+		client
+			.runtime_api()
+			.account_nonce(client.info().best_hash, account.clone())
+			.expect("the runtime provides account nonces")
+	}
+
+	fn wait(&self, timeout: Duration, done: impl Fn() -> bool) -> Result<(), ()> {
+		let deadline = Instant::now() + timeout;
+		while Instant::now() < deadline {
+			if done() {
+				return Ok(());
+			}
+			std::thread::sleep(POLL_INTERVAL);
+		}
+		Err(())
+	}
+}
+
+impl Drop for TestNetwork {
+	fn drop(&mut self) {
+		for index in 0..self.nodes.len() {
+			self.kill(index);
+		}
+	}
+}
+
+/// Whether the runtime wasm is missing, e.g. because the node was built with `SKIP_WASM_BUILD=1`.
+pub fn runtime_wasm_missing() -> bool {
+	let missing = WASM_BINARY.is_none();
+	if missing {
+		eprintln!("skipped: the runtime wasm was not built");
+	}
+	missing
+}
+
+/// A port on the loopback interface that was free a moment ago.
+fn free_port() -> u16 {
+	TcpListener::bind("127.0.0.1:0")
+		.and_then(|listener| listener.local_addr())
+		.expect("a free port can be found")
+		.port()
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use pez_solochain_template_runtime::{BalancesCall, EXISTENTIAL_DEPOSIT};
+
+	const TIMEOUT: Duration = Duration::from_secs(120);
+
+	#[test]
+	fn authorities_produce_and_finalize_blocks() {
+		if runtime_wasm_missing() {
+			return;
+		}
+		let network = TestNetwork::start(3);
+
+		network.wait_for_finalized(2, 3, TIMEOUT).unwrap();
+		network.wait_for_block(0, 3, TIMEOUT).unwrap();
+	}
+
+	#[test]
+	fn restarted_node_catches_up() {
+		if runtime_wasm_missing() {
+			return;
+		}
+		let mut network = TestNetwork::start(3);
+		network.wait_for_finalized(2, 2, TIMEOUT).unwrap();
+
+		network.kill(2);
+		network.wait_for_finalized(0, 5, TIMEOUT).unwrap();
+		network.restart(2);
+
+		network.wait_for_finalized(2, 5, TIMEOUT).unwrap();
+	}
+
+	#[test]
+	fn transactions_are_included() {
+		if runtime_wasm_missing() {
+			return;
+		}
+		let mut network = TestNetwork::start(2);
+		network.wait_for_block(0, 1, TIMEOUT).unwrap();
+
+		let charlie = Sr25519Keyring::Charlie.to_account_id();
+		for _ in 0..2 {
+			let call = BalancesCall::transfer_keep_alive {
+				dest: charlie.clone().into(),
+				value: EXISTENTIAL_DEPOSIT,
+			};
+			network.submit(1, Sr25519Keyring::Dave, call.into()).unwrap();
+		}
+
+		let dave = Sr25519Keyring::Dave.to_account_id();
+		network.wait(TIMEOUT, || network.account_nonce(0, &dave) == 2).unwrap();
+	}
+}
@@ -0,0 +1,728 @@
+//! The transaction pool as seen from the RPC and the network.
+//!
+//! [`NodeTransactionPool`] forwards everything to the pool built in `new_partial`, but observes
+//! the transactions submitted through it, so rejections can be counted in the chain metrics.
+//! Block authoring and offchain workers keep using the inner pool directly.
+//!
+//! Transactions from the RPC also have to pass the node-local [`AdmissionPolicy`] before they
+//! reach the inner pool. It limits the ready and future transactions per sender, filters calls by
+//! pallet and call index and requires a minimum tip, without any change to the runtime. Rejected
+//! transactions are reported as invalid, with one of the custom codes of [`Rejection`]. Calls
+//! wrapped by `sudo` are judged together with the wrapping call, so wrapping a denied call doesn't
+//! get it into the pool. The pool given to the network has no policy, as the network would punish
+//! peers for gossiping transactions the policy refuses as if they were invalid.
+//!
+//! The transactions of each sender are counted as they enter the pool through the wrapper, and
+//! counted anew from the pool whenever it is maintained after a block, which accounts for the
+//! transactions it pruned and the future ones that became ready.
+
+use crate::{cli::PoolPolicyParams, metrics::Metrics, service::FullClient};
+use async_trait::async_trait;
+use codec::{Decode, Encode};
+use pez_solochain_template_runtime::{
+	nonce_and_tip, opaque::Block, AccountId, Balance, Nonce, RuntimeCall, UncheckedExtrinsic,
+};
+use pezframe_system_rpc_runtime_api::AccountNonceApi;
+use pezpallet_sudo::Call as SudoCall;
+use pezsc_transaction_pool_api::{
+	error::{Error as PoolError, IntoPoolError},
+	ChainEvent, ImportNotificationStream, InPoolTransaction, MaintainedTransactionPool, PoolStatus,
+	ReadyTransactions, TransactionFor, TransactionPool, TransactionSource,
+	TransactionStatusStreamFor, TxHash, TxInvalidityReportMap,
+};
+use pezsp_api::ProvideRuntimeApi;
+use pezsp_runtime::{
+	generic::Preamble, traits::Block as BlockT, transaction_validity::InvalidTransaction,
+	MultiAddress,
+};
+use std::{
+	collections::HashMap,
+	pin::Pin,
+	str::FromStr,
+	sync::{Arc, Mutex},
+	time::Duration,
+};
+
+type ReadyIteratorFor<P> =
+	Box<dyn ReadyTransactions<Item = Arc<<P as TransactionPool>::InPoolTransaction>> + Send>;
+
+/// Wraps the node's transaction pool for the RPC and the network.
+pub struct NodeTransactionPool<P> {
+	inner: Arc<P>,
+	metrics: Option<Arc<Metrics>>,
+	policy: Option<AdmissionPolicy>,
+	senders: SenderCounts,
+}
+
+impl<P: TransactionPool<Block = Block>> NodeTransactionPool<P> {
+	/// Wraps `inner`, admitting transactions by `policy` and reporting rejected ones to `metrics`.
+	pub fn new(
+		inner: Arc<P>,
+		metrics: Option<Arc<Metrics>>,
+		policy: Option<AdmissionPolicy>,
+	) -> Self {
+		Self { inner, metrics, policy, senders: SenderCounts::default() }
+	}
+
+	/// Checks `xt` against the admission policy, unless it was submitted by the node itself.
+	fn admit(
+		&self,
+		at: <Block as BlockT>::Hash,
+		source: TransactionSource,
+		xt: &TransactionFor<Self>,
+	) -> Result<Option<Admitted>, PoolError> {
+		match &self.policy {
+			Some(policy) if source != TransactionSource::Local => {
+				policy.check(at, xt, &self.senders).map_err(|rejection| {
+					PoolError::InvalidTransaction(InvalidTransaction::Custom(rejection as u8))
+				})
+			},
+			_ => Ok(None),
+		}
+	}
+
+	/// Counts the transaction of `admitted` once the inner pool accepted it.
+	fn count<T, E>(&self, admitted: Option<Admitted>, result: &Result<T, E>) {
+		if let (Some(admitted), Ok(_)) = (admitted, result) {
+			self.senders.add(admitted);
+		}
+	}
+
+	/// Reports `result` to the metrics if the transaction was rejected.
+	fn observe<T>(&self, result: Result<T, P::Error>) -> Result<T, P::Error> {
+		let Some(metrics) = &self.metrics else { return result };
+		result.map_err(|error| match error.into_pool_error() {
+			Ok(error) => {
+				metrics.report_rejection(&error);
+				error.into()
+			},
+			Err(error) => error,
+		})
+	}
+}
+
+#[async_trait]
+impl<P: TransactionPool<Block = Block>> TransactionPool for NodeTransactionPool<P> {
+	type Block = P::Block;
+	type Hash = P::Hash;
+	type InPoolTransaction = P::InPoolTransaction;
+	type Error = P::Error;
+
+	async fn submit_at(
+		&self,
+		at: <Self::Block as BlockT>::Hash,
+		source: TransactionSource,
+		xts: Vec<TransactionFor<Self>>,
+	) -> Result<Vec<Result<TxHash<Self>, Self::Error>>, Self::Error> {
+		let admissions = xts.iter().map(|xt| self.admit(at, source, xt)).collect::<Vec<_>>();
+		let admitted = xts
+			.into_iter()
+			.zip(&admissions)
+			.filter_map(|(xt, admission)| admission.is_ok().then_some(xt))
+			.collect();
+
+		let mut results =
+			self.observe(self.inner.submit_at(at, source, admitted).await)?.into_iter();
+		Ok(admissions
+			.into_iter()
+			.map(|admission| match admission {
+				Ok(admitted) => {
+					let result = results.next().expect("one result per admitted transaction; qed");
+					self.count(admitted, &result);
+					self.observe(result)
+				},
+				Err(rejection) => self.observe(Err(rejection.into())),
+			})
+			.collect())
+	}
+
+	async fn submit_one(
+		&self,
+		at: <Self::Block as BlockT>::Hash,
+		source: TransactionSource,
+		xt: TransactionFor<Self>,
+	) -> Result<TxHash<Self>, Self::Error> {
+		let admitted = match self.admit(at, source, &xt) {
+			Ok(admitted) => admitted,
+			Err(rejection) => return self.observe(Err(rejection.into())),
+		};
+		let result = self.inner.submit_one(at, source, xt).await;
+		self.count(admitted, &result);
+		self.observe(result)
+	}
+
+	async fn submit_and_watch(
+		&self,
+		at: <Self::Block as BlockT>::Hash,
+		source: TransactionSource,
+		xt: TransactionFor<Self>,
+	) -> Result<Pin<Box<TransactionStatusStreamFor<Self>>>, Self::Error> {
+		let admitted = match self.admit(at, source, &xt) {
+			Ok(admitted) => admitted,
+			Err(rejection) => return self.observe(Err(rejection.into())),
+		};
+		let result = self.inner.submit_and_watch(at, source, xt).await;
+		self.count(admitted, &result);
+		self.observe(result)
+	}
+
+	async fn ready_at(&self, at: <Self::Block as BlockT>::Hash) -> ReadyIteratorFor<Self> {
+		self.inner.ready_at(at).await
+	}
+
+	fn ready(&self) -> ReadyIteratorFor<Self> {
+		self.inner.ready()
+	}
+
+	async fn report_invalid(
+		&self,
+		at: Option<<Self::Block as BlockT>::Hash>,
+		invalid_tx_errors: TxInvalidityReportMap<TxHash<Self>>,
+	) -> Vec<Arc<Self::InPoolTransaction>> {
+		self.inner.report_invalid(at, invalid_tx_errors).await
+	}
+
+	fn futures(&self) -> Vec<Self::InPoolTransaction> {
+		self.inner.futures()
+	}
+
+	fn status(&self) -> PoolStatus {
+		self.inner.status()
+	}
+
+	fn import_notification_stream(&self) -> ImportNotificationStream<TxHash<Self>> {
+		self.inner.import_notification_stream()
+	}
+
+	fn on_broadcasted(&self, propagations: HashMap<TxHash<Self>, Vec<String>>) {
+		self.inner.on_broadcasted(propagations)
+	}
+
+	fn hash_of(&self, xt: &TransactionFor<Self>) -> TxHash<Self> {
+		self.inner.hash_of(xt)
+	}
+
+	fn ready_transaction(&self, hash: &TxHash<Self>) -> Option<Arc<Self::InPoolTransaction>> {
+		self.inner.ready_transaction(hash)
+	}
+
+	async fn ready_at_with_timeout(
+		&self,
+		at: <Self::Block as BlockT>::Hash,
+		timeout: Duration,
+	) -> ReadyIteratorFor<Self> {
+		self.inner.ready_at_with_timeout(at, timeout).await
+	}
+}
+
+#[async_trait]
+impl<P: MaintainedTransactionPool<Block = Block>> MaintainedTransactionPool
+	for NodeTransactionPool<P>
+{
+	async fn maintain(&self, event: ChainEvent<Self::Block>) {
+		self.inner.maintain(event).await;
+
+		// Only once per block, the pool is decoded to account for the transactions it dropped.
+		if self.policy.as_ref().is_some_and(AdmissionPolicy::limits_senders) {
+			let ready = self.inner.ready().filter_map(|tx| sender(&tx.data().encode())).collect();
+			let future =
+				self.inner.futures().iter().filter_map(|tx| sender(&tx.data().encode())).collect();
+			self.senders.recount(ready, future);
+		}
+	}
+}
+
+/// Why the admission policy rejected a transaction.
+///
+/// Reported as the custom code of an invalid transaction, so RPC clients can tell the rejections
+/// apart. The codes are above the ones used by the runtime.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum Rejection {
+	/// The sender already has `--pool-max-ready-per-sender` ready transactions.
+	SenderReadyLimit = 200,
+	/// The sender already has `--pool-max-future-per-sender` future transactions.
+	SenderFutureLimit = 201,
+	/// The call is denied, or not allowed, by `--pool-deny-call` or `--pool-allow-call`.
+	CallNotAllowed = 202,
+	/// The tip is below `--pool-min-tip`.
+	TipTooLow = 203,
+}
+
+impl Rejection {
+	/// The rejection reported with the custom `code`, if any.
+	pub fn from_code(code: u8) -> Option<Self> {
+		[Self::SenderReadyLimit, Self::SenderFutureLimit, Self::CallNotAllowed, Self::TipTooLow]
+			.into_iter()
+			.find(|rejection| *rejection as u8 == code)
+	}
+
+	/// Name of the rejection in metrics.
+	pub fn as_str(&self) -> &'static str {
+		match self {
+			Rejection::SenderReadyLimit => "sender_ready_limit",
+			Rejection::SenderFutureLimit => "sender_future_limit",
+			Rejection::CallNotAllowed => "call_not_allowed",
+			Rejection::TipTooLow => "tip_too_low",
+		}
+	}
+}
+
+/// A pallet, or a single call of it, by their indices in the runtime.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CallIndex {
+	/// Index of the pallet in `construct_runtime`.
+	pub pallet: u8,
+	/// Index of the call in the pallet, `None` for all of its calls.
+	pub call: Option<u8>,
+}
+
+impl CallIndex {
+	fn matches(&self, pallet: u8, call: u8) -> bool {
+		self.pallet == pallet && self.call.is_none_or(|index| index == call)
+	}
+}
+
+impl FromStr for CallIndex {
+	type Err = String;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		let invalid = || format!("invalid call `{s}`, expected `<pallet index>[:<call index>]`");
+		let (pallet, call) = match s.split_once(':') {
+			Some((pallet, call)) => (pallet, Some(call.parse().map_err(|_| invalid())?)),
+			None => (s, None),
+		};
+		Ok(CallIndex { pallet: pallet.parse().map_err(|_| invalid())?, call })
+	}
+}
+
+/// The transactions of a sender in the pool.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+struct Pending {
+	ready: usize,
+	future: usize,
+}
+
+/// A transaction admitted by the policy, counted for its sender once the pool accepted it.
+#[derive(Debug, PartialEq, Eq)]
+struct Admitted {
+	sender: AccountId,
+	is_future: bool,
+}
+
+/// The number of transactions per sender in the pool.
+#[derive(Default)]
+struct SenderCounts(Mutex<HashMap<AccountId, Pending>>);
+
+impl SenderCounts {
+	fn get(&self, sender: &AccountId) -> Pending {
+		self.0.lock().expect("not poisoned; qed").get(sender).copied().unwrap_or_default()
+	}
+
+	fn add(&self, admitted: Admitted) {
+		let mut counts = self.0.lock().expect("not poisoned; qed");
+		let pending = counts.entry(admitted.sender).or_default();
+		if admitted.is_future {
+			pending.future += 1;
+		} else {
+			pending.ready += 1;
+		}
+	}
+
+	/// Replaces the counts by the senders of the `ready` and `future` transactions in the pool.
+	fn recount(&self, ready: Vec<AccountId>, future: Vec<AccountId>) {
+		let mut counts = HashMap::<AccountId, Pending>::new();
+		for sender in ready {
+			counts.entry(sender).or_default().ready += 1;
+		}
+		for sender in future {
+			counts.entry(sender).or_default().future += 1;
+		}
+		*self.0.lock().expect("not poisoned; qed") = counts;
+	}
+}
+
+/// The signer of a transaction and the nonce and tip it signed.
+struct Signer {
+	account: AccountId,
+	nonce: Nonce,
+	tip: Balance,
+}
+
+/// Node-local rules for the transactions from the RPC and the network.
+pub struct AdmissionPolicy {
+	client: Arc<FullClient>,
+	rules: AdmissionRules,
+}
+
+impl AdmissionPolicy {
+	/// The policy configured by `params`, `None` if it would admit every transaction.
+	pub fn new(client: Arc<FullClient>, params: &PoolPolicyParams) -> Option<Self> {
+		AdmissionRules::new(params).map(|rules| Self { client, rules })
+	}
+
+	/// Whether the policy limits the transactions per sender, which then have to be counted.
+	fn limits_senders(&self) -> bool {
+		self.rules.limits_senders()
+	}
+
+	/// Checks `xt`, whose sender has the transactions in `senders` in the pool.
+	///
+	/// Transactions that can't be decoded are left to the runtime to reject.
+	fn check(
+		&self,
+		at: <Block as BlockT>::Hash,
+		xt: &<Block as BlockT>::Extrinsic,
+		senders: &SenderCounts,
+	) -> Result<Option<Admitted>, Rejection> {
+		let Some(xt) = decode(&xt.encode()) else { return Ok(None) };
+		self.rules.check(&xt.function, signer(&xt).as_ref(), |account| {
+			let account_nonce =
+				self.client.runtime_api().account_nonce(at, account.clone()).ok()?;
+			Some((account_nonce, senders.get(account)))
+		})
+	}
+}
+
+/// The rules of an [`AdmissionPolicy`], apart from the chain and the pool.
+struct AdmissionRules {
+	max_ready_per_sender: Option<usize>,
+	max_future_per_sender: Option<usize>,
+	allowed_calls: Vec<CallIndex>,
+	denied_calls: Vec<CallIndex>,
+	min_tip: Balance,
+}
+
+impl AdmissionRules {
+	fn new(params: &PoolPolicyParams) -> Option<Self> {
+		let rules = Self {
+			max_ready_per_sender: params.pool_max_ready_per_sender,
+			max_future_per_sender: params.pool_max_future_per_sender,
+			allowed_calls: params.pool_allow_call.clone(),
+			denied_calls: params.pool_deny_call.clone(),
+			min_tip: params.pool_min_tip,
+		};
+		let restricts = rules.limits_senders()
+			|| !rules.allowed_calls.is_empty()
+			|| !rules.denied_calls.is_empty()
+			|| rules.min_tip > 0;
+		restricts.then_some(rules)
+	}
+
+	fn limits_senders(&self) -> bool {
+		self.max_ready_per_sender.is_some() || self.max_future_per_sender.is_some()
+	}
+
+	/// Decides on a transaction of `call`, signed by `signer` unless it is unsigned.
+	///
+	/// `sender` looks up the account nonce of the signer and their transactions in the pool. It is
+	/// only called when the transactions per sender are limited, and a failed lookup admits the
+	/// transaction.
+	fn check(
+		&self,
+		call: &RuntimeCall,
+		signer: Option<&Signer>,
+		sender: impl FnOnce(&AccountId) -> Option<(Nonce, Pending)>,
+	) -> Result<Option<Admitted>, Rejection> {
+		for call in dispatched_calls(call) {
+			let call = call.encode();
+			let [pallet, call, ..] = call[..] else { continue };
+			let allowed = self.allowed_calls.is_empty()
+				|| self.allowed_calls.iter().any(|index| index.matches(pallet, call));
+			let denied = self.denied_calls.iter().any(|index| index.matches(pallet, call));
+			if !allowed || denied {
+				return Err(Rejection::CallNotAllowed);
+			}
+		}
+
+		let Some(signer) = signer else { return Ok(None) };
+		if signer.tip < self.min_tip {
+			return Err(Rejection::TipTooLow);
+		}
+
+		if !self.limits_senders() {
+			return Ok(None);
+		}
+		let Some((account_nonce, pending)) = sender(&signer.account) else { return Ok(None) };
+		// Transactions are ready as long as they follow the ready ones of their sender.
+		let is_future = signer.nonce > account_nonce.saturating_add(pending.ready as Nonce);
+		if is_future {
+			if self.max_future_per_sender.is_some_and(|max| pending.future >= max) {
+				return Err(Rejection::SenderFutureLimit);
+			}
+		} else if self.max_ready_per_sender.is_some_and(|max| pending.ready >= max) {
+			return Err(Rejection::SenderReadyLimit);
+		}
+		Ok(Some(Admitted { sender: signer.account.clone(), is_future }))
+	}
+}
+
+/// `call` followed by the calls it wraps, which are dispatched along with it.
+fn dispatched_calls(call: &RuntimeCall) -> Vec<&RuntimeCall> {
+	let mut calls = vec![call];
+	let mut index = 0;
+	while let Some(call) = calls.get(index).copied() {
+		if let RuntimeCall::Sudo(
+			SudoCall::sudo { call }
+			| SudoCall::sudo_unchecked_weight { call, .. }
+			| SudoCall::sudo_as { call, .. },
+		) = call
+		{
+			calls.push(call.as_ref());
+		}
+		index += 1;
+	}
+	calls
+}
+
+fn decode(encoded: &[u8]) -> Option<UncheckedExtrinsic> {
+	UncheckedExtrinsic::decode(&mut &encoded[..]).ok()
+}
+
+/// The account that signed the `encoded` transaction.
+fn sender(encoded: &[u8]) -> Option<AccountId> {
+	decode(encoded).and_then(|xt| signer(&xt)).map(|signer| signer.account)
+}
+
+/// The signer of `xt`, if it is signed by an account id.
+fn signer(xt: &UncheckedExtrinsic) -> Option<Signer> {
+	match &xt.preamble {
+		Preamble::Signed(MultiAddress::Id(account), _, tx_ext) => {
+			let (nonce, tip) = nonce_and_tip(tx_ext);
+			Some(Signer { account: account.clone(), nonce, tip })
+		},
+		_ => None,
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use pez_solochain_template_runtime::{BalancesCall, SystemCall};
+	use pezsp_keyring::Sr25519Keyring;
+
+	fn remark() -> RuntimeCall {
+		RuntimeCall::System(SystemCall::remark { remark: Vec::new() })
+	}
+
+	fn transfer() -> RuntimeCall {
+		RuntimeCall::Balances(BalancesCall::transfer_keep_alive {
+			dest: Sr25519Keyring::Bob.to_account_id().into(),
+			value: 1,
+		})
+	}
+
+	fn sudo(call: RuntimeCall) -> RuntimeCall {
+		RuntimeCall::Sudo(SudoCall::sudo { call: Box::new(call) })
+	}
+
+	/// The pallet of `call`.
+	fn pallet(call: &RuntimeCall) -> CallIndex {
+		CallIndex { pallet: call.encode()[0], call: None }
+	}
+
+	/// The single call `call`.
+	fn single(call: &RuntimeCall) -> CallIndex {
+		let encoded = call.encode();
+		CallIndex { pallet: encoded[0], call: Some(encoded[1]) }
+	}
+
+	fn no_rules() -> AdmissionRules {
+		AdmissionRules {
+			max_ready_per_sender: None,
+			max_future_per_sender: None,
+			allowed_calls: Vec::new(),
+			denied_calls: Vec::new(),
+			min_tip: 0,
+		}
+	}
+
+	fn alice() -> AccountId {
+		Sr25519Keyring::Alice.to_account_id()
+	}
+
+	/// A transaction of Alice.
+	fn signed(nonce: Nonce, tip: Balance) -> Signer {
+		Signer { account: alice(), nonce, tip }
+	}
+
+	/// Alice at account nonce 5, with `ready` and `future` transactions in the pool.
+	fn pool(ready: usize, future: usize) -> impl FnOnce(&AccountId) -> Option<(Nonce, Pending)> {
+		move |account| {
+			assert_eq!(*account, alice());
+			Some((5, Pending { ready, future }))
+		}
+	}
+
+	fn admitted(is_future: bool) -> Result<Option<Admitted>, Rejection> {
+		Ok(Some(Admitted { sender: alice(), is_future }))
+	}
+
+	#[test]
+	fn admits_transactions_within_the_rules() {
+		let rules = AdmissionRules {
+			max_ready_per_sender: Some(2),
+			max_future_per_sender: Some(2),
+			allowed_calls: vec![pallet(&transfer())],
+			min_tip: 10,
+			..no_rules()
+		};
+
+		assert_eq!(rules.check(&transfer(), Some(&signed(5, 10)), pool(1, 1)), admitted(false));
+		assert_eq!(rules.check(&transfer(), Some(&signed(6, 10)), pool(1, 1)), admitted(false));
+		assert_eq!(rules.check(&transfer(), Some(&signed(7, 10)), pool(1, 1)), admitted(true));
+	}
+
+	#[test]
+	fn rejects_tips_below_the_minimum() {
+		let rules = AdmissionRules { min_tip: 10, ..no_rules() };
+
+		assert_eq!(
+			rules.check(&remark(), Some(&signed(5, 9)), pool(0, 0)),
+			Err(Rejection::TipTooLow)
+		);
+		assert_eq!(rules.check(&remark(), Some(&signed(5, 10)), pool(0, 0)), Ok(None));
+		// Unsigned transactions pay no tip.
+		assert_eq!(rules.check(&remark(), None, pool(0, 0)), Ok(None));
+	}
+
+	#[test]
+	fn limits_the_ready_transactions_per_sender() {
+		let rules = AdmissionRules { max_ready_per_sender: Some(2), ..no_rules() };
+
+		assert_eq!(
+			rules.check(&remark(), Some(&signed(7, 0)), pool(2, 0)),
+			Err(Rejection::SenderReadyLimit)
+		);
+		assert_eq!(rules.check(&remark(), Some(&signed(7, 0)), pool(1, 0)), admitted(false));
+		// Future transactions are not limited.
+		assert_eq!(rules.check(&remark(), Some(&signed(8, 0)), pool(2, 5)), admitted(true));
+	}
+
+	#[test]
+	fn limits_the_future_transactions_per_sender() {
+		let rules = AdmissionRules { max_future_per_sender: Some(1), ..no_rules() };
+
+		assert_eq!(
+			rules.check(&remark(), Some(&signed(6, 0)), pool(0, 1)),
+			Err(Rejection::SenderFutureLimit)
+		);
+		assert_eq!(rules.check(&remark(), Some(&signed(6, 0)), pool(0, 0)), admitted(true));
+		// Ready transactions are not limited.
+		assert_eq!(rules.check(&remark(), Some(&signed(5, 0)), pool(0, 1)), admitted(false));
+	}
+
+	#[test]
+	fn looks_up_senders_only_when_they_are_limited() {
+		let rules = AdmissionRules { min_tip: 1, ..no_rules() };
+		assert_eq!(
+			rules.check(&remark(), Some(&signed(5, 1)), |_| panic!("senders are not limited")),
+			Ok(None)
+		);
+
+		// A failed lookup admits the transaction and leaves it uncounted.
+		let rules = AdmissionRules { max_ready_per_sender: Some(1), ..no_rules() };
+		assert_eq!(rules.check(&remark(), Some(&signed(5, 0)), |_| None), Ok(None));
+	}
+
+	#[test]
+	fn admits_allowed_calls_only() {
+		let rules = AdmissionRules { allowed_calls: vec![pallet(&remark())], ..no_rules() };
+		assert_eq!(rules.check(&remark(), None, pool(0, 0)), Ok(None));
+		assert_eq!(rules.check(&transfer(), None, pool(0, 0)), Err(Rejection::CallNotAllowed));
+
+		let rules = AdmissionRules { allowed_calls: vec![single(&transfer())], ..no_rules() };
+		assert_eq!(rules.check(&transfer(), None, pool(0, 0)), Ok(None));
+		let transfer_all = RuntimeCall::Balances(BalancesCall::transfer_all {
+			dest: Sr25519Keyring::Bob.to_account_id().into(),
+			keep_alive: true,
+		});
+		assert_eq!(rules.check(&transfer_all, None, pool(0, 0)), Err(Rejection::CallNotAllowed));
+	}
+
+	#[test]
+	fn rejects_denied_calls() {
+		let rules = AdmissionRules { denied_calls: vec![pallet(&transfer())], ..no_rules() };
+
+		assert_eq!(
+			rules.check(&transfer(), Some(&signed(5, 0)), pool(0, 0)),
+			Err(Rejection::CallNotAllowed)
+		);
+		assert_eq!(rules.check(&remark(), Some(&signed(5, 0)), pool(0, 0)), Ok(None));
+	}
+
+	#[test]
+	fn judges_calls_wrapped_by_sudo() {
+		let rules = AdmissionRules { denied_calls: vec![pallet(&transfer())], ..no_rules() };
+		assert_eq!(
+			rules.check(&sudo(transfer()), None, pool(0, 0)),
+			Err(Rejection::CallNotAllowed)
+		);
+		assert_eq!(
+			rules.check(&sudo(sudo(transfer())), None, pool(0, 0)),
+			Err(Rejection::CallNotAllowed)
+		);
+		assert_eq!(rules.check(&sudo(remark()), None, pool(0, 0)), Ok(None));
+
+		// Both the wrapping and the wrapped call have to be allowed.
+		let rules = AdmissionRules { allowed_calls: vec![pallet(&transfer())], ..no_rules() };
+		assert_eq!(
+			rules.check(&sudo(transfer()), None, pool(0, 0)),
+			Err(Rejection::CallNotAllowed)
+		);
+		let rules = AdmissionRules {
+			allowed_calls: vec![pallet(&transfer()), pallet(&sudo(remark()))],
+			..no_rules()
+		};
+		assert_eq!(rules.check(&sudo(transfer()), None, pool(0, 0)), Ok(None));
+	}
+
+	#[test]
+	fn counts_the_transactions_of_senders() {
+		let counts = SenderCounts::default();
+		let bob = Sr25519Keyring::Bob.to_account_id();
+
+		counts.add(Admitted { sender: alice(), is_future: false });
+		counts.add(Admitted { sender: alice(), is_future: true });
+		counts.add(Admitted { sender: alice(), is_future: false });
+		assert_eq!(counts.get(&alice()), Pending { ready: 2, future: 1 });
+		assert_eq!(counts.get(&bob), Pending::default());
+
+		counts.recount(vec![bob.clone(), alice(), bob.clone()], Vec::new());
+		assert_eq!(counts.get(&alice()), Pending { ready: 1, future: 0 });
+		assert_eq!(counts.get(&bob), Pending { ready: 2, future: 0 });
+	}
+
+	#[test]
+	fn parses_call_indices() {
+		assert_eq!("5".parse(), Ok(CallIndex { pallet: 5, call: None }));
+		assert_eq!("5:3".parse(), Ok(CallIndex { pallet: 5, call: Some(3) }));
+		assert!("balances".parse::<CallIndex>().is_err());
+		assert!("5:".parse::<CallIndex>().is_err());
+		assert!("256".parse::<CallIndex>().is_err());
+	}
+
+	#[test]
+	fn matches_pallets_and_calls() {
+		let pallet = CallIndex { pallet: 5, call: None };
+		let call = CallIndex { pallet: 5, call: Some(3) };
+
+		assert!(pallet.matches(5, 0));
+		assert!(call.matches(5, 3));
+		assert!(!call.matches(5, 0));
+		assert!(!pallet.matches(6, 3));
+	}
+
+	#[test]
+	fn rejections_round_trip_through_their_codes() {
+		for rejection in [
+			Rejection::SenderReadyLimit,
+			Rejection::SenderFutureLimit,
+			Rejection::CallNotAllowed,
+			Rejection::TipTooLow,
+		] {
+			assert_eq!(Rejection::from_code(rejection as u8), Some(rejection));
+		}
+		assert_eq!(Rejection::from_code(0), None);
+	}
+}
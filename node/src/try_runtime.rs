@@ -0,0 +1,223 @@
+//! Trying runtime upgrades and blocks against a local state snapshot.
+//!
+//! The snapshot is a raw chain spec holding the state of a block, as written by `export-state`.
+//! `try-runtime on-runtime-upgrade` runs the migrations of a runtime built with the `try-runtime`
+//! feature on top of it, including their pre- and post-upgrade checks, and `try-runtime
+//! execute-block` executes the following block, as written by `export-blocks`, with the try-state
+//! checks of the pallets. Both report the weight they consumed against the maximum block weight.
+
+use crate::{chain_spec::ChainSpec, service::HostFunctions};
+use codec::{Decode, Encode};
+use pez_solochain_template_runtime::{configs::RuntimeBlockWeights, opaque::Block, WASM_BINARY};
+use pezframe_support::{
+	traits::{TryStateSelect, UpgradeCheckSelect},
+	weights::Weight,
+};
+use pezsc_cli::{CliConfiguration, SharedParams};
+use pezsc_executor::WasmExecutor;
+use pezsp_api::RuntimeVersion;
+use pezsp_core::{
+	storage::{well_known_keys, Storage},
+	traits::{CallContext, ReadRuntimeVersionExt},
+	Get,
+};
+use pezsp_externalities::Extensions;
+use pezsp_runtime::{
+	generic::SignedBlock,
+	traits::{BlakeTwo256, Block as BlockT, Header as _},
+	BuildStorage,
+};
+use pezsp_state_machine::{BackendRuntimeCode, InMemoryBackend, OverlayedChanges, StateMachine};
+use std::{
+	fs::File,
+	io::BufReader,
+	path::{Path, PathBuf},
+};
+
+/// Try runtime upgrades and blocks against a local state snapshot.
+#[derive(Debug, Clone, clap::Parser)]
+pub struct TryRuntimeCmd {
+	#[allow(missing_docs)]
+	#[command(subcommand)]
+	pub command: TryRuntimeSubcommand,
+
+	/// The state snapshot, a raw chain spec as written by `export-state`.
+	#[arg(long, value_name = "FILE")]
+	pub snapshot: PathBuf,
+
+	/// The runtime to try, built with the `try-runtime` feature. Defaults to the runtime of this
+	/// node.
+	#[arg(long, value_name = "FILE")]
+	pub runtime: Option<PathBuf>,
+
+	#[allow(missing_docs)]
+	#[clap(flatten)]
+	pub shared_params: SharedParams,
+}
+
+/// What to try against the snapshot.
+#[derive(Debug, Clone, clap::Subcommand)]
+pub enum TryRuntimeSubcommand {
+	/// Run the migrations of the runtime on top of the snapshot.
+	OnRuntimeUpgrade {
+		/// The checks to run: `none`, `all`, `pre-and-post` or `try-state`.
+		#[arg(long, default_value = "pre-and-post")]
+		checks: UpgradeCheckSelect,
+	},
+
+	/// Execute the block following the snapshot.
+	ExecuteBlock {
+		/// The block, as written by `export-blocks` in JSON.
+		#[arg(long, value_name = "FILE")]
+		block: PathBuf,
+
+		/// The try-state checks to run after the block: `all`, `none`, `rr-<n>` or a comma
+		/// separated list of pallet names.
+		#[arg(long, default_value = "all")]
+		try_state: TryStateSelect,
+
+		/// Do not check the state root of the block.
+		#[arg(long)]
+		no_state_root_check: bool,
+
+		/// Do not check the signatures of the extrinsics.
+		#[arg(long)]
+		no_signature_check: bool,
+	},
+}
+
+impl TryRuntimeCmd {
+	/// Runs the command and prints its report.
+	pub fn run(&self) -> pezsc_cli::Result<()> {
+		let executor = WasmExecutor::<HostFunctions>::builder().build();
+		let mut storage = ChainSpec::from_json_file(self.snapshot.clone())?.build_storage()?;
+
+		let code = match &self.runtime {
+			Some(path) => std::fs::read(path)?,
+			None => WASM_BINARY.ok_or("Development wasm not available")?.to_vec(),
+		};
+		let version = runtime_version(&executor, &code)?;
+		if let Some(previous) = storage.top.insert(well_known_keys::CODE.to_vec(), code) {
+			let previous = runtime_version(&executor, &previous)?;
+			println!(
+				"Snapshot runtime {} v{}, trying {} v{}",
+				previous.spec_name, previous.spec_version, version.spec_name, version.spec_version,
+			);
+		}
+
+		let backend = InMemoryBackend::<BlakeTwo256>::from((storage, version.state_version()));
+		let max_block = RuntimeBlockWeights::get().max_block;
+		match &self.command {
+			TryRuntimeSubcommand::OnRuntimeUpgrade { checks } => {
+				let result =
+					call(&backend, &executor, "TryRuntime_on_runtime_upgrade", &checks.encode())
+						.map_err(|e| format!("on-runtime-upgrade failed: {e}"))?;
+				let (consumed, max_block) = <(Weight, Weight)>::decode(&mut &result[..])
+					.map_err(|e| format!("invalid on-runtime-upgrade result: {e}"))?;
+				println!("✅ Runtime upgrade succeeded with the {checks:?} checks");
+				println!("{}", weight_report(consumed, max_block));
+			},
+			TryRuntimeSubcommand::ExecuteBlock {
+				block,
+				try_state,
+				no_state_root_check,
+				no_signature_check,
+			} => {
+				let block = read_block(block)?;
+				let (number, hash) = (*block.header().number(), block.hash());
+				let data =
+					(block, !no_state_root_check, !no_signature_check, try_state.clone()).encode();
+				let result = call(&backend, &executor, "TryRuntime_execute_block", &data)
+					.map_err(|e| format!("execute-block of #{number} ({hash}) failed: {e}"))?;
+				let consumed = Weight::decode(&mut &result[..])
+					.map_err(|e| format!("invalid execute-block result: {e}"))?;
+				println!("✅ Block #{number} ({hash}) executed with the {try_state:?} checks");
+				println!("{}", weight_report(consumed, max_block));
+			},
+		}
+		Ok(())
+	}
+}
+
+impl CliConfiguration for TryRuntimeCmd {
+	fn shared_params(&self) -> &SharedParams {
+		&self.shared_params
+	}
+}
+
+/// Calls the runtime API `method` on `backend`, discarding the changes.
+fn call(
+	backend: &InMemoryBackend<BlakeTwo256>,
+	executor: &WasmExecutor<HostFunctions>,
+	method: &str,
+	data: &[u8],
+) -> Result<Vec<u8>, String> {
+	let runtime_code = BackendRuntimeCode::new(backend).runtime_code()?;
+	let mut extensions = Extensions::default();
+	extensions.register(ReadRuntimeVersionExt::new(executor.clone()));
+
+	StateMachine::new(
+		backend,
+		&mut OverlayedChanges::default(),
+		executor,
+		method,
+		data,
+		&mut extensions,
+		&runtime_code,
+		CallContext::Offchain,
+	)
+	.execute()
+	.map_err(|e| e.to_string())
+}
+
+/// The version of the runtime `code`.
+fn runtime_version(
+	executor: &WasmExecutor<HostFunctions>,
+	code: &[u8],
+) -> Result<RuntimeVersion, String> {
+	let storage = Storage {
+		top: [(well_known_keys::CODE.to_vec(), code.to_vec())].into(),
+		children_default: Default::default(),
+	};
+	let backend = InMemoryBackend::<BlakeTwo256>::from((storage, Default::default()));
+	let result = call(&backend, executor, "Core_version", &[])?;
+	RuntimeVersion::decode(&mut &result[..]).map_err(|e| format!("invalid runtime version: {e}"))
+}
+
+/// Reads the first block of a file written by `export-blocks`.
+fn read_block(path: &Path) -> Result<Block, String> {
+	let file = File::open(path).map_err(|e| format!("failed to read {}: {e}", path.display()))?;
+	serde_json::Deserializer::from_reader(BufReader::new(file))
+		.into_iter::<SignedBlock<Block>>()
+		.next()
+		.ok_or_else(|| format!("no block in {}", path.display()))?
+		.map(|signed| signed.block)
+		.map_err(|e| format!("failed to parse {}: {e}", path.display()))
+}
+
+/// How much of `max_block` the `consumed` weight takes.
+fn weight_report(consumed: Weight, max_block: Weight) -> String {
+	let share = |consumed: u64, max: u64| consumed as f64 * 100.0 / max.max(1) as f64;
+	format!(
+		"Weight consumed: ref time {} ({:.2}% of the block), proof size {} ({:.2}% of the block)",
+		consumed.ref_time(),
+		share(consumed.ref_time(), max_block.ref_time()),
+		consumed.proof_size(),
+		share(consumed.proof_size(), max_block.proof_size()),
+	)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn reports_weight_against_the_block() {
+		let max_block = Weight::from_parts(2_000, 1_000);
+
+		assert_eq!(
+			weight_report(Weight::from_parts(500, 10), max_block),
+			"Weight consumed: ref time 500 (25.00% of the block), proof size 10 (1.00% of the block)",
+		);
+	}
+}
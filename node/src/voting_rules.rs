@@ -0,0 +1,232 @@
+//! GRANDPA voting rules configurable through the CLI and the chain spec.
+
+use crate::maintenance::Maintenance;
+use pez_solochain_template_runtime::opaque::Block;
+use pezsc_consensus_grandpa::{
+	BeforeBestBlockBy, ThreeQuartersOfTheUnfinalizedChain, VotingRule, VotingRuleResult,
+	VotingRulesBuilder,
+};
+use pezsp_blockchain::HeaderBackend;
+#[cfg(not(feature = "babe"))]
+use pezsp_consensus_aura::sr25519::AuthoritySignature as AuraSignature;
+use pezsp_consensus_aura::{Slot, SlotDuration};
+use pezsp_runtime::traits::{Block as BlockT, Header as HeaderT};
+use serde::{Deserialize, Serialize};
+use std::{fmt, sync::Arc, time::Duration};
+
+const LOG_TARGET: &str = "voting-rules";
+
+/// A voting rule restricting which block the GRANDPA voter may vote on.
+///
+/// Written as `before-best=<blocks>`, `three-quarters` or `min-age=<secs>`, both on the CLI and in
+/// the chain spec.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum VotingRuleConfig {
+	/// Vote on a block at least the given number of blocks behind the best block.
+	BeforeBestBy(u32),
+	/// Vote on at most three quarters of the unfinalized chain.
+	ThreeQuartersOfUnfinalized,
+	/// Vote only on blocks that are at least the given number of seconds old.
+	MinimumAgeSecs(u64),
+}
+
+impl std::str::FromStr for VotingRuleConfig {
+	type Err = String;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		let (name, value) = s.split_once('=').unwrap_or((s, ""));
+		let invalid = |e| format!("invalid value in voting rule `{s}`: {e}");
+		Ok(match name {
+			"before-best" => VotingRuleConfig::BeforeBestBy(value.parse().map_err(invalid)?),
+			"three-quarters" => VotingRuleConfig::ThreeQuartersOfUnfinalized,
+			"min-age" => VotingRuleConfig::MinimumAgeSecs(value.parse().map_err(invalid)?),
+			_ => {
+				return Err(format!(
+					"unknown voting rule `{s}`, expected `before-best=<blocks>`, \
+					`three-quarters` or `min-age=<secs>`"
+				))
+			},
+		})
+	}
+}
+
+impl fmt::Display for VotingRuleConfig {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			VotingRuleConfig::BeforeBestBy(blocks) => write!(f, "before-best={blocks}"),
+			VotingRuleConfig::ThreeQuartersOfUnfinalized => write!(f, "three-quarters"),
+			VotingRuleConfig::MinimumAgeSecs(secs) => write!(f, "min-age={secs}"),
+		}
+	}
+}
+
+impl TryFrom<String> for VotingRuleConfig {
+	type Error = String;
+
+	fn try_from(s: String) -> Result<Self, Self::Error> {
+		s.parse()
+	}
+}
+
+impl From<VotingRuleConfig> for String {
+	fn from(rule: VotingRuleConfig) -> Self {
+		rule.to_string()
+	}
+}
+
+/// Builds the voting rule for the GRANDPA voter.
+///
+/// The given `rules` are added on top of the default rules. Each rule can only restrict the vote
+/// further, so the most conservative one wins. The vote is held at the last finalized block while
+/// `maintenance` pauses voting.
+pub fn voting_rule<B>(
+	rules: impl IntoIterator<Item = VotingRuleConfig>,
+	slot_duration: SlotDuration,
+	maintenance: &Maintenance,
+) -> impl VotingRule<Block, B> + Clone
+where
+	B: HeaderBackend<Block> + 'static,
+{
+	rules
+		.into_iter()
+		.fold(VotingRulesBuilder::default(), |builder, rule| match rule {
+			VotingRuleConfig::BeforeBestBy(blocks) => builder.add(BeforeBestBlockBy(blocks)),
+			VotingRuleConfig::ThreeQuartersOfUnfinalized => {
+				builder.add(ThreeQuartersOfTheUnfinalizedChain)
+			},
+			VotingRuleConfig::MinimumAgeSecs(secs) => {
+				builder.add(MinimumBlockAge { age: Duration::from_secs(secs), slot_duration })
+			},
+		})
+		.add(maintenance.voting_rule())
+		.build()
+}
+
+/// Restricts the vote to blocks that were authored at least `age` ago.
+///
+/// The authoring time of a block is derived from the slot in its Aura (or BABE) pre-runtime
+/// digest. The vote is not restricted if the slot of a block can't be read.
+#[derive(Clone)]
+struct MinimumBlockAge {
+	age: Duration,
+	slot_duration: SlotDuration,
+}
+
+impl MinimumBlockAge {
+	fn authored_at(&self, header: &<Block as BlockT>::Header) -> Option<u64> {
+		block_slot(header).map(|slot| (*slot).saturating_mul(self.slot_duration.as_millis()))
+	}
+}
+
+impl<B> VotingRule<Block, B> for MinimumBlockAge
+where
+	B: HeaderBackend<Block> + 'static,
+{
+	fn restrict_vote(
+		&self,
+		backend: Arc<B>,
+		base: &<Block as BlockT>::Header,
+		_best_target: &<Block as BlockT>::Header,
+		current_target: &<Block as BlockT>::Header,
+	) -> VotingRuleResult<Block> {
+		let cutoff = pezsp_timestamp::Timestamp::current()
+			.as_millis()
+			.saturating_sub(self.age.as_millis() as u64);
+
+		let mut target = current_target.clone();
+		while target.number() > base.number() {
+			match self.authored_at(&target) {
+				Some(authored_at) if authored_at > cutoff => {},
+				Some(_) => break,
+				None => {
+					log::warn!(
+						target: LOG_TARGET,
+						"No slot in block #{} ({}), not restricting the vote to a minimum age",
+						target.number(),
+						target.hash(),
+					);
+					return Box::pin(async { None });
+				},
+			}
+			target = match backend.header(*target.parent_hash()) {
+				Ok(Some(header)) => header,
+				_ => return Box::pin(async { None }),
+			};
+		}
+
+		let restricted =
+			(target.hash() != current_target.hash()).then(|| (target.hash(), *target.number()));
+		Box::pin(async move { restricted })
+	}
+}
+
+/// The slot the block with `header` was authored in.
+#[cfg(not(feature = "babe"))]
+pub(crate) fn block_slot(header: &<Block as BlockT>::Header) -> Option<Slot> {
+	pezsc_consensus_aura::find_pre_digest::<Block, AuraSignature>(header).ok()
+}
+
+/// The slot the block with `header` was authored in.
+#[cfg(feature = "babe")]
+pub(crate) fn block_slot(header: &<Block as BlockT>::Header) -> Option<Slot> {
+	pezsc_consensus_babe::find_pre_digest::<Block>(header).ok().map(|pre_digest| pre_digest.slot())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn parses_voting_rules() {
+		assert_eq!("before-best=2".parse(), Ok(VotingRuleConfig::BeforeBestBy(2)));
+		assert_eq!("three-quarters".parse(), Ok(VotingRuleConfig::ThreeQuartersOfUnfinalized));
+		assert_eq!("min-age=30".parse(), Ok(VotingRuleConfig::MinimumAgeSecs(30)));
+	}
+
+	#[test]
+	fn rejects_invalid_voting_rules() {
+		assert_eq!(
+			"before-best=two".parse::<VotingRuleConfig>(),
+			Err("invalid value in voting rule `before-best=two`: invalid digit found in string"
+				.into())
+		);
+		assert_eq!(
+			"min-age".parse::<VotingRuleConfig>(),
+			Err("invalid value in voting rule `min-age`: cannot parse integer from empty string"
+				.into())
+		);
+		assert!("half".parse::<VotingRuleConfig>().unwrap_err().starts_with("unknown voting rule"));
+	}
+
+	#[test]
+	fn voting_rules_use_the_cli_syntax_in_the_chain_spec() {
+		let rules = vec![
+			VotingRuleConfig::BeforeBestBy(2),
+			VotingRuleConfig::ThreeQuartersOfUnfinalized,
+			VotingRuleConfig::MinimumAgeSecs(30),
+		];
+
+		let json = serde_json::to_value(&rules).unwrap();
+		assert_eq!(json, serde_json::json!(["before-best=2", "three-quarters", "min-age=30"]));
+		assert_eq!(serde_json::from_value::<Vec<VotingRuleConfig>>(json).unwrap(), rules);
+		assert!(serde_json::from_value::<VotingRuleConfig>(serde_json::json!("half")).is_err());
+	}
+
+	#[test]
+	fn blocks_without_a_slot_have_no_authoring_time() {
+		let rule = MinimumBlockAge {
+			age: Duration::from_secs(30),
+			slot_duration: SlotDuration::from_millis(6000),
+		};
+		let header = <Block as BlockT>::Header::new(
+			1,
+			Default::default(),
+			Default::default(),
+			Default::default(),
+			Default::default(),
+		);
+
+		assert_eq!(rule.authored_at(&header), None);
+	}
+}
@@ -0,0 +1,358 @@
+//! Helpers to run a local network of node binaries and follow their progress through the RPC
+//! and the logs.
+//!
+//! [`Network`] starts nodes of the `local` chain, each with its own temporary base path and
+//! listening on loopback only. Alice and Bob, the authorities of the `local` preset, run the first
+//! two nodes. Every other node is a full node without authority keys. All nodes bootstrap from
+//! the first one.
+
+#![allow(dead_code)]
+
+use codec::Encode;
+use pez_solochain_template_runtime::{self as runtime, Hash, RuntimeCall, UncheckedExtrinsic};
+use pezsp_core::{bytes::from_hex, crypto::Ss58Codec, Pair};
+use pezsp_keyring::Sr25519Keyring;
+use serde_json::{json, Value};
+use std::{
+	io::{BufRead, BufReader, Read, Write},
+	net::{TcpListener, TcpStream},
+	process::{Child, Command, Output, Stdio},
+	sync::mpsc,
+	time::{Duration, Instant},
+};
+use tempfile::TempDir;
+
+/// Node key of the first node of a [`Network`], which the other nodes bootstrap from.
+pub const BOOTNODE_KEY: &str = "0000000000000000000000000000000000000000000000000000000000000001";
+
+/// Peer id of the node started with [`BOOTNODE_KEY`].
+const BOOTNODE_PEER_ID: &str = "12D3KooWEyoppNCUx8Yx66oV9fJnriXwCcXwDDUA2kj6vnc6iDEp";
+
+/// Authorities of the `local` chain, which run the first nodes of a [`Network`].
+const AUTHORITIES: [Sr25519Keyring; 2] = [Sr25519Keyring::Alice, Sr25519Keyring::Bob];
+
+/// How often the waiting helpers look at the chain.
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Address of the node started with [`BOOTNODE_KEY`] on the p2p `port`.
+pub fn bootnode(port: u16) -> String {
+	format!("/ip4/127.0.0.1/tcp/{port}/p2p/{BOOTNODE_PEER_ID}")
+}
+
+/// A port on the loopback interface that was free a moment ago.
+pub fn free_port() -> u16 {
+	TcpListener::bind("127.0.0.1:0")
+		.and_then(|listener| listener.local_addr())
+		.expect("a free port can be found")
+		.port()
+}
+
+/// Whether the runtime wasm is missing, e.g. because the node was built with `SKIP_WASM_BUILD=1`.
+///
+/// Nodes can't start any chain without it, so tests starting nodes return early in that case.
+pub fn runtime_wasm_missing() -> bool {
+	let missing = runtime::WASM_BINARY.is_none();
+	if missing {
+		eprintln!("skipped: the runtime wasm was not built");
+	}
+	missing
+}
+
+/// Runs the node binary with `args` until it exits.
+pub fn run(args: &[&str]) -> Output {
+	Command::new(env!("CARGO_BIN_EXE_pez-solochain-template-node"))
+		.args(args)
+		.output()
+		.expect("node binary can be started")
+}
+
+/// A node process that is killed when dropped.
+pub struct Node {
+	child: Child,
+	logs: mpsc::Receiver<String>,
+	/// The port the node listens on for peers.
+	pub p2p_port: u16,
+	/// The port of the RPC server of the node.
+	pub rpc_port: u16,
+}
+
+impl Node {
+	/// Starts the node binary with the given arguments on a temporary base path and free ports.
+	pub fn start(args: &[&str]) -> Self {
+		let mut tmp_args = vec!["--tmp"];
+		tmp_args.extend(args);
+		Self::start_on(free_port(), free_port(), &tmp_args)
+	}
+
+	/// Starts the node binary with the given arguments, listening on loopback at `p2p_port` and
+	/// `rpc_port`.
+	pub fn start_on(p2p_port: u16, rpc_port: u16, args: &[&str]) -> Self {
+		let mut child = Command::new(env!("CARGO_BIN_EXE_pez-solochain-template-node"))
+			.args(["--no-telemetry", "--no-prometheus", "--no-mdns"])
+			.arg(format!("--listen-addr=/ip4/127.0.0.1/tcp/{p2p_port}"))
+			.arg(format!("--rpc-port={rpc_port}"))
+			.args(args)
+			.stdout(Stdio::null())
+			.stderr(Stdio::piped())
+			.spawn()
+			.expect("node binary can be started");
+
+		let (tx, logs) = mpsc::channel();
+		let stderr = child.stderr.take().expect("stderr is piped; qed");
+		std::thread::spawn(move || {
+			for line in BufReader::new(stderr).lines().map_while(Result::ok) {
+				if tx.send(line).is_err() {
+					break;
+				}
+			}
+		});
+
+		Self { child, logs, p2p_port, rpc_port }
+	}
+
+	/// Calls the RPC `method` of the node and returns its result.
+	pub fn rpc(&self, method: &str, params: Value) -> Result<Value, String> {
+		let body =
+			json!({ "jsonrpc": "2.0", "id": 1, "method": method, "params": params }).to_string();
+		let mut stream =
+			TcpStream::connect(("127.0.0.1", self.rpc_port)).map_err(|e| e.to_string())?;
+		write!(
+			stream,
+			"POST / HTTP/1.1\r\nHost: 127.0.0.1:{}\r\nContent-Type: application/json\r\n\
+			 Content-Length: {}\r\nConnection: close\r\n\r\n{body}",
+			self.rpc_port,
+			body.len(),
+		)
+		.map_err(|e| e.to_string())?;
+
+		let mut response = String::new();
+		stream.read_to_string(&mut response).map_err(|e| e.to_string())?;
+		let (_, body) = response.split_once("\r\n\r\n").ok_or("malformed HTTP response")?;
+		let mut response: Value = serde_json::from_str(body).map_err(|e| e.to_string())?;
+		match response.get("error") {
+			Some(error) => Err(format!("{method} failed: {error}")),
+			None => Ok(response["result"].take()),
+		}
+	}
+
+	/// The number of the best block of the node.
+	pub fn best_number(&self) -> Result<u32, String> {
+		header_number(self.rpc("chain_getHeader", json!([]))?)
+	}
+
+	/// The number of the last block finalized by the node.
+	pub fn finalized_number(&self) -> Result<u32, String> {
+		let hash = self.rpc("chain_getFinalizedHead", json!([]))?;
+		header_number(self.rpc("chain_getHeader", json!([hash]))?)
+	}
+
+	/// Waits until the best block of the node is at least `number`.
+	pub fn wait_for_block(&self, number: u32, timeout: Duration) -> Result<(), String> {
+		wait(timeout, || self.best_number().is_ok_and(|best| best >= number))
+			.map_err(|_| format!("block #{number} not imported within {timeout:?}"))
+	}
+
+	/// Waits until the node finalized a block of at least `number`.
+	pub fn wait_for_finalized(&self, number: u32, timeout: Duration) -> Result<(), String> {
+		wait(timeout, || self.finalized_number().is_ok_and(|finalized| finalized >= number))
+			.map_err(|_| format!("block #{number} not finalized within {timeout:?}"))
+	}
+
+	/// Signs `call` by `sender` with their next nonce and submits it to the node.
+	///
+	/// The next nonce accounts for the transactions of `sender` in the pool of the node, so
+	/// several of them can be submitted at once.
+	pub fn submit(&self, sender: Sr25519Keyring, call: RuntimeCall) -> Result<Hash, String> {
+		let nonce = self.account_next_index(sender)?;
+		let genesis_hash = self.rpc("chain_getBlockHash", json!([0]))?;
+		let genesis_hash = genesis_hash.as_str().and_then(|hash| from_hex(hash).ok());
+		let genesis_hash = Hash::from_slice(&genesis_hash.ok_or("invalid genesis hash")?);
+
+		let extrinsic = signed_extrinsic(sender, call, nonce, genesis_hash);
+		let hash = self.rpc("author_submitExtrinsic", json!([hex(&extrinsic.encode())]))?;
+		serde_json::from_value(hash).map_err(|e| e.to_string())
+	}
+
+	/// The next nonce of `account`, including its transactions in the pool of the node.
+	pub fn account_next_index(&self, account: Sr25519Keyring) -> Result<u32, String> {
+		let address = account.to_account_id().to_ss58check();
+		let nonce = self.rpc("system_accountNextIndex", json!([address]))?;
+		serde_json::from_value(nonce).map_err(|e| e.to_string())
+	}
+
+	/// The number of transactions in the pool of the node.
+	pub fn pending_extrinsics(&self) -> Result<usize, String> {
+		let pending = self.rpc("author_pendingExtrinsics", json!([]))?;
+		Ok(pending.as_array().map_or(0, Vec::len))
+	}
+
+	/// Waits until a log line matching `predicate` is printed.
+	pub fn wait_for_log(
+		&self,
+		timeout: Duration,
+		predicate: impl Fn(&str) -> bool,
+	) -> Result<String, ()> {
+		let deadline = Instant::now() + timeout;
+		while let Some(left) = deadline.checked_duration_since(Instant::now()) {
+			match self.logs.recv_timeout(left) {
+				Ok(line) if predicate(&line) => return Ok(line),
+				Ok(_) => continue,
+				Err(_) => break,
+			}
+		}
+		Err(())
+	}
+}
+
+impl Drop for Node {
+	fn drop(&mut self) {
+		let _ = self.child.kill();
+		let _ = self.child.wait();
+	}
+}
+
+/// A node of a [`Network`], which keeps its database and ports across restarts.
+struct NetworkNode {
+	base_path: TempDir,
+	p2p_port: u16,
+	rpc_port: u16,
+	args: Vec<String>,
+	running: Option<Node>,
+}
+
+/// A local network of node binaries on the `local` chain.
+pub struct Network {
+	nodes: Vec<NetworkNode>,
+}
+
+impl Network {
+	/// Starts a network of `count` nodes.
+	pub fn start(count: usize) -> Self {
+		Self::start_with(&vec![&[][..]; count])
+	}
+
+	/// Starts a network with one node per entry of `args`, each started with the extra arguments
+	/// of its entry.
+	pub fn start_with(args: &[&[&str]]) -> Self {
+		let nodes = args
+			.iter()
+			.map(|args| NetworkNode {
+				base_path: TempDir::new().expect("temporary directory can be created"),
+				p2p_port: free_port(),
+				rpc_port: free_port(),
+				args: args.iter().map(|arg| arg.to_string()).collect(),
+				running: None,
+			})
+			.collect();
+
+		let mut network = Self { nodes };
+		for index in 0..args.len() {
+			network.restart(index);
+		}
+		network
+	}
+
+	/// The running node at `index`.
+	pub fn node(&self, index: usize) -> &Node {
+		self.nodes[index].running.as_ref().unwrap_or_else(|| panic!("node {index} is not running"))
+	}
+
+	/// Stops the node at `index`, keeping its database.
+	pub fn kill(&mut self, index: usize) {
+		self.nodes[index].running = None;
+	}
+
+	/// Starts the node at `index` again, or for the first time, on top of its database.
+	pub fn restart(&mut self, index: usize) {
+		self.kill(index);
+
+		let node = &self.nodes[index];
+		let mut args = vec![
+			"--chain=local".to_string(),
+			format!("--base-path={}", node.base_path.path().display()),
+			// Authorities need a persistent node key, the first one is also the bootnode.
+			format!("--node-key={:064x}", index + 1),
+		];
+		match AUTHORITIES.get(index) {
+			Some(authority) => args.push(format!("--{}", format!("{authority:?}").to_lowercase())),
+			None => args.push(format!("--name=node-{index}")),
+		}
+		if index > 0 {
+			args.push(format!("--bootnodes={}", bootnode(self.nodes[0].p2p_port)));
+		}
+		args.extend(node.args.iter().cloned());
+
+		let args = args.iter().map(String::as_str).collect::<Vec<_>>();
+		let running = Node::start_on(node.p2p_port, node.rpc_port, &args);
+		self.nodes[index].running = Some(running);
+	}
+}
+
+/// Signs `call` by `sender` as an immortal transaction with `nonce` and no tip.
+pub fn signed_extrinsic(
+	sender: Sr25519Keyring,
+	call: RuntimeCall,
+	nonce: u32,
+	genesis_hash: Hash,
+) -> UncheckedExtrinsic {
+	let tx_ext: runtime::TxExtension = (
+		pezframe_system::AuthorizeCall::<runtime::Runtime>::new(),
+		pezframe_system::CheckNonZeroSender::<runtime::Runtime>::new(),
+		pezframe_system::CheckSpecVersion::<runtime::Runtime>::new(),
+		pezframe_system::CheckTxVersion::<runtime::Runtime>::new(),
+		pezframe_system::CheckGenesis::<runtime::Runtime>::new(),
+		pezframe_system::CheckEra::<runtime::Runtime>::from(pezsp_runtime::generic::Era::Immortal),
+		pezframe_system::CheckNonce::<runtime::Runtime>::from(nonce),
+		pezframe_system::CheckWeight::<runtime::Runtime>::new(),
+		pezpallet_transaction_payment::ChargeTransactionPayment::<runtime::Runtime>::from(0),
+		pezframe_metadata_hash_extension::CheckMetadataHash::<runtime::Runtime>::new(false),
+		pezframe_system::WeightReclaim::<runtime::Runtime>::new(),
+	);
+	let raw_payload = runtime::SignedPayload::from_raw(
+		call.clone(),
+		tx_ext.clone(),
+		(
+			(),
+			(),
+			runtime::VERSION.spec_version,
+			runtime::VERSION.transaction_version,
+			genesis_hash,
+			genesis_hash,
+			(),
+			(),
+			(),
+			None,
+			(),
+		),
+	);
+	let signature = raw_payload.using_encoded(|payload| sender.pair().sign(payload));
+
+	UncheckedExtrinsic::new_signed(
+		call,
+		sender.to_account_id().into(),
+		runtime::Signature::Sr25519(signature),
+		tx_ext,
+	)
+}
+
+/// Polls `done` until it holds or `timeout` passed.
+pub fn wait(timeout: Duration, done: impl Fn() -> bool) -> Result<(), ()> {
+	let deadline = Instant::now() + timeout;
+	while Instant::now() < deadline {
+		if done() {
+			return Ok(());
+		}
+		std::thread::sleep(POLL_INTERVAL);
+	}
+	Err(())
+}
+
+fn header_number(header: Value) -> Result<u32, String> {
+	let number = header["number"].as_str().ok_or("header without a number")?;
+	u32::from_str_radix(number.trim_start_matches("0x"), 16).map_err(|e| e.to_string())
+}
+
+/// `bytes` as `0x` prefixed hex, as the RPC and the node's commands print them.
+pub fn hex(bytes: &[u8]) -> String {
+	format!("0x{}", bytes.iter().map(|byte| format!("{byte:02x}")).collect::<String>())
+}
@@ -0,0 +1,40 @@
+mod common;
+
+use common::{hex, run, runtime_wasm_missing};
+use tempfile::TempDir;
+
+/// Runs the node binary with `args` and returns its stdout.
+fn export(args: &[&str]) -> Vec<u8> {
+	let output = run(args);
+	assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+	output.stdout
+}
+
+#[test]
+fn genesis_head_is_exported_as_hex_and_raw() {
+	if runtime_wasm_missing() {
+		return;
+	}
+
+	let base_path = TempDir::new().unwrap();
+	let base_path = format!("--base-path={}", base_path.path().display());
+
+	let encoded = export(&["export-genesis-head", "--chain=dev", &base_path]);
+	let raw = export(&["export-genesis-head", "--chain=dev", "--raw", &base_path]);
+
+	assert!(!raw.is_empty());
+	assert_eq!(String::from_utf8(encoded).unwrap(), hex(&raw));
+}
+
+#[test]
+fn genesis_wasm_is_exported_as_hex_and_raw() {
+	if runtime_wasm_missing() {
+		return;
+	}
+
+	let encoded = export(&["export-genesis-wasm", "--chain=local"]);
+	let raw = export(&["export-genesis-wasm", "--chain=local", "--raw"]);
+
+	assert!(!raw.is_empty());
+	assert_eq!(String::from_utf8(encoded).unwrap(), hex(&raw));
+}
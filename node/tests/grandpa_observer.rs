@@ -0,0 +1,29 @@
+mod common;
+
+use common::{runtime_wasm_missing, Network, Node};
+use std::time::Duration;
+
+#[test]
+fn grandpa_observer_follows_finality() {
+	if runtime_wasm_missing() {
+		return;
+	}
+
+	let network = Network::start_with(&[&[], &[], &["--grandpa-observer"]]);
+
+	network.node(2).wait_for_finalized(3, Duration::from_secs(120)).unwrap();
+}
+
+#[test]
+fn grandpa_observer_is_rejected_on_authorities() {
+	if runtime_wasm_missing() {
+		return;
+	}
+
+	let node = Node::start(&["--dev", "--grandpa-observer"]);
+
+	node.wait_for_log(Duration::from_secs(60), |line| {
+		line.contains("`--grandpa-observer` can only be used on non-authority nodes")
+	})
+	.unwrap();
+}
@@ -0,0 +1,42 @@
+mod common;
+
+use common::{run, runtime_wasm_missing};
+
+#[test]
+fn presets_are_listed() {
+	if runtime_wasm_missing() {
+		return;
+	}
+
+	let output = run(&["list-presets"]);
+
+	assert!(output.status.success());
+	let names = String::from_utf8(output.stdout).unwrap();
+	assert_eq!(names.lines().collect::<Vec<_>>(), ["development", "local_testnet"]);
+}
+
+#[test]
+fn chain_spec_is_built_from_a_preset() {
+	if runtime_wasm_missing() {
+		return;
+	}
+
+	let output = run(&["export-chain-spec", "--chain=preset:local_testnet"]);
+
+	assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+	assert!(String::from_utf8_lossy(&output.stdout).contains("\"id\": \"local_testnet\""));
+}
+
+#[test]
+fn unknown_chain_lists_the_presets() {
+	if runtime_wasm_missing() {
+		return;
+	}
+
+	let output = run(&["export-chain-spec", "--chain=staging"]);
+
+	assert!(!output.status.success());
+	let stderr = String::from_utf8_lossy(&output.stderr);
+	assert!(stderr.contains("unknown chain `staging`"), "{stderr}");
+	assert!(stderr.contains("`preset:development`, `preset:local_testnet`"), "{stderr}");
+}
@@ -0,0 +1,70 @@
+//! Benchmarking setup for pezpallet-aura-equivocation
+
+use super::*;
+
+#[allow(unused)]
+use crate::Pezpallet as AuraEquivocation;
+use alloc::{boxed::Box, vec, vec::Vec};
+use pezframe_benchmarking::v2::*;
+use pezframe_support::traits::Get;
+use pezframe_system::{pezpallet_prelude::HeaderFor, RawOrigin};
+use pezsp_consensus_aura::digests::CompatibleDigestItem;
+use pezsp_runtime::{
+	traits::Header, transaction_validity::InvalidTransaction, Digest, DigestItem, RuntimeAppPublic,
+};
+use pezsp_staking::offence::OffenceReportSystem;
+
+type SignatureOf<T> = <<T as pezpallet_aura::Config>::AuthorityId as RuntimeAppPublic>::Signature;
+
+// A header of block `number` in slot `1`, sealed by `author` like Aura does.
+fn sealed_header<T: Config>(author: &T::AuthorityId, number: u32) -> HeaderFor<T> {
+	let pre_digest =
+		<DigestItem as CompatibleDigestItem<SignatureOf<T>>>::aura_pre_digest(1.into());
+	let mut header = HeaderFor::<T>::new(
+		number.into(),
+		Default::default(),
+		Default::default(),
+		Default::default(),
+		Digest { logs: vec![pre_digest] },
+	);
+	let signature = author.sign(&header.hash()).expect("the author's key was generated; qed");
+	header
+		.digest_mut()
+		.push(<DigestItem as CompatibleDigestItem<SignatureOf<T>>>::aura_seal(signature));
+	header
+}
+
+#[benchmarks]
+mod benchmarks {
+	use super::*;
+
+	#[benchmark]
+	fn report_equivocation(v: Linear<2, { <T as pezpallet_aura::Config>::MaxAuthorities::get() }>) {
+		let authorities: Vec<T::AuthorityId> =
+			(0..v).map(|_| T::AuthorityId::generate_pair(None)).collect();
+		let key_owner_proof = T::BenchmarkHelper::set_validators(&authorities);
+		let offender = authorities[0].clone();
+		let equivocation_proof = EquivocationProof {
+			offender: offender.clone(),
+			slot: 1.into(),
+			first_header: sealed_header::<T>(&offender, 1),
+			second_header: sealed_header::<T>(&offender, 2),
+		};
+		let evidence = (equivocation_proof.clone(), key_owner_proof.clone());
+
+		#[extrinsic_call]
+		report_equivocation_unsigned(
+			RawOrigin::None,
+			Box::new(equivocation_proof),
+			key_owner_proof,
+		);
+
+		// The offence is known now, so it can't be reported again.
+		assert_eq!(
+			T::EquivocationReportSystem::check_evidence(evidence),
+			Err(InvalidTransaction::Stale.into())
+		);
+	}
+
+	impl_benchmark_test_suite!(AuraEquivocation, crate::mock::new_test_ext(), crate::mock::Test);
+}
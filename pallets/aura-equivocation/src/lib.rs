@@ -0,0 +1,181 @@
+//! # Aura Equivocation Pezpallet
+//!
+//! Aura on its own only notices when an authority signs two different blocks for the same slot;
+//! nothing happens on chain. This pezpallet closes that gap by accepting equivocation proofs as
+//! unsigned transactions and turning them into offences.
+//!
+//! ## Overview
+//!
+//! - [`Pezpallet::report_equivocation_unsigned`] takes an [`EquivocationProof`] together with a
+//!   key ownership proof and hands both to the configured [`Config::EquivocationReportSystem`].
+//! - [`EquivocationReportSystem`] is the default implementation of that system. It validates the
+//!   evidence and reports an [`EquivocationOffence`] to the offences pezpallet.
+//! - [`AuraEquivocationApi`] is the runtime API that lets a node submit the reports it detects
+//!   while importing blocks.
+//!
+//! What happens to the offender is up to the runtime's offence handler. Typically the offender is
+//! disabled through the session pezpallet, and Aura rejects their blocks until the session ends.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+pub use pezpallet::*;
+
+#[cfg(test)]
+mod mock;
+#[cfg(test)]
+mod tests;
+
+#[cfg(feature = "runtime-benchmarks")]
+mod benchmarking;
+mod equivocation;
+pub mod weights;
+
+pub use equivocation::{check_equivocation_proof, EquivocationOffence, EquivocationReportSystem};
+pub use pezsp_consensus_slots::EquivocationProof;
+pub use weights::*;
+
+const LOG_TARGET: &str = "runtime::aura-equivocation";
+
+/// A key ownership proof opaque to the node.
+pub type OpaqueKeyOwnershipProof = pezsp_runtime::OpaqueValue;
+
+/// Sets up the validators the benchmarks report equivocations of.
+///
+/// This pezpallet doesn't know how the runtime maps authorities to validators, so the runtime has
+/// to do that for the benchmarks.
+#[cfg(feature = "runtime-benchmarks")]
+pub trait BenchmarkHelper<AuthorityId, KeyOwnerProof> {
+	/// Makes `authorities` the validators of the current session and returns the proof of key
+	/// ownership of the first one.
+	fn set_validators(authorities: &[AuthorityId]) -> KeyOwnerProof;
+}
+
+#[pezframe_support::pezpallet]
+pub mod pezpallet {
+	use super::*;
+	use alloc::boxed::Box;
+	use pezframe_support::pezpallet_prelude::*;
+	use pezframe_system::pezpallet_prelude::*;
+	use pezsp_session::{GetSessionNumber, GetValidatorCount};
+	use pezsp_staking::offence::OffenceReportSystem;
+
+	#[pezpallet::pezpallet]
+	pub struct Pezpallet<T>(_);
+
+	#[pezpallet::config]
+	pub trait Config: pezframe_system::Config + pezpallet_aura::Config {
+		/// The proof of key ownership, used for validating equivocation reports.
+		///
+		/// The proof must include the session index and validator count of the session at which
+		/// the equivocation occurred.
+		type KeyOwnerProof: Parameter + GetSessionNumber + GetValidatorCount;
+
+		/// The equivocation handling subsystem, defines methods to check, report and process
+		/// proofs of misbehavior.
+		type EquivocationReportSystem: OffenceReportSystem<
+			Option<Self::AccountId>,
+			(EquivocationProof<HeaderFor<Self>, Self::AuthorityId>, Self::KeyOwnerProof),
+		>;
+
+		/// Weight information for the extrinsics of this pezpallet.
+		type WeightInfo: WeightInfo;
+
+		/// Sets up the validators for the benchmarks.
+		#[cfg(feature = "runtime-benchmarks")]
+		type BenchmarkHelper: BenchmarkHelper<Self::AuthorityId, Self::KeyOwnerProof>;
+	}
+
+	#[pezpallet::error]
+	pub enum Error<T> {
+		/// An equivocation proof provided as part of an equivocation report is invalid.
+		InvalidEquivocationProof,
+		/// A key ownership proof provided as part of an equivocation report is invalid.
+		InvalidKeyOwnershipProof,
+		/// A given equivocation report is valid but already previously reported.
+		DuplicateOffenceReport,
+	}
+
+	#[pezpallet::call]
+	impl<T: Config> Pezpallet<T> {
+		/// Report authority equivocation.
+		///
+		/// This method will verify the equivocation proof and validate the given key ownership
+		/// proof against the extracted offender. If both are valid, the offence will be
+		/// reported. This extrinsic must be called unsigned and it is expected that only block
+		/// authors will call it (validated in `ValidateUnsigned`), as such if the block author
+		/// is defined it will be defined as the equivocation reporter.
+		#[pezpallet::call_index(0)]
+		#[pezpallet::weight(T::WeightInfo::report_equivocation(key_owner_proof.validator_count()))]
+		pub fn report_equivocation_unsigned(
+			origin: OriginFor<T>,
+			equivocation_proof: Box<EquivocationProof<HeaderFor<T>, T::AuthorityId>>,
+			key_owner_proof: T::KeyOwnerProof,
+		) -> DispatchResultWithPostInfo {
+			ensure_none(origin)?;
+
+			T::EquivocationReportSystem::process_evidence(
+				None,
+				(*equivocation_proof, key_owner_proof),
+			)?;
+			// Waive the fee since the report is valid and beneficial
+			Ok(Pays::No.into())
+		}
+	}
+
+	#[pezpallet::validate_unsigned]
+	impl<T: Config> ValidateUnsigned for Pezpallet<T> {
+		type Call = Call<T>;
+
+		fn validate_unsigned(source: TransactionSource, call: &Self::Call) -> TransactionValidity {
+			Self::validate_unsigned(source, call)
+		}
+
+		fn pre_dispatch(call: &Self::Call) -> Result<(), TransactionValidityError> {
+			Self::pre_dispatch(call)
+		}
+	}
+}
+
+impl<T: Config> Pezpallet<T> {
+	/// Submits an extrinsic to report an equivocation. This method will create an unsigned
+	/// extrinsic with a call to `report_equivocation_unsigned` and will push the transaction to
+	/// the pool. Only useful in an offchain context.
+	pub fn submit_unsigned_equivocation_report(
+		equivocation_proof: EquivocationProof<
+			pezframe_system::pezpallet_prelude::HeaderFor<T>,
+			T::AuthorityId,
+		>,
+		key_owner_proof: T::KeyOwnerProof,
+	) -> Option<()> {
+		use pezsp_staking::offence::OffenceReportSystem;
+
+		T::EquivocationReportSystem::publish_evidence((equivocation_proof, key_owner_proof)).ok()
+	}
+}
+
+pezsp_api::decl_runtime_apis! {
+	/// API necessary for reporting Aura equivocations detected by the node.
+	pub trait AuraEquivocationApi<AuthorityId: codec::Codec> {
+		/// Generates a proof of key ownership for the given authority in the current session.
+		/// An example usage of this module is coupled with the session historical module to
+		/// prove that a given authority key is tied to a given staking identity during a
+		/// specific session. Proofs of key ownership are necessary for submitting equivocation
+		/// reports.
+		fn generate_key_ownership_proof(
+			authority_id: AuthorityId,
+		) -> Option<OpaqueKeyOwnershipProof>;
+
+		/// Submits an unsigned extrinsic to report an equivocation. The caller must provide the
+		/// equivocation proof and a key ownership proof (should be obtained using
+		/// `generate_key_ownership_proof`). The extrinsic will be unsigned and should only be
+		/// accepted for local authorship (not to be broadcast to the network). This method
+		/// returns `None` when creation of the extrinsic fails, e.g. if equivocation reporting
+		/// is disabled for the given runtime.
+		fn submit_report_equivocation_unsigned_extrinsic(
+			equivocation_proof: EquivocationProof<Block::Header, AuthorityId>,
+			key_owner_proof: OpaqueKeyOwnershipProof,
+		) -> Option<()>;
+	}
+}
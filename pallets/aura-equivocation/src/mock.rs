@@ -0,0 +1,273 @@
+use crate::{self as pezpallet_aura_equivocation};
+use pezframe_support::{
+	derive_impl, parameter_types,
+	traits::{ConstBool, ConstU32, ConstU64, Hooks, KeyOwnerProofSystem},
+};
+use pezpallet_session::historical::{self as pezpallet_session_historical, IdentificationTuple};
+use pezsp_consensus_aura::sr25519::AuthorityId as AuraId;
+use pezsp_keyring::Sr25519Keyring;
+use pezsp_keystore::{testing::MemoryKeystore, KeystoreExt};
+use pezsp_runtime::{
+	impl_opaque_keys,
+	testing::TestXt,
+	traits::{Convert, ConvertInto, OpaqueKeys},
+	BuildStorage, KeyTypeId, Perbill, RuntimeAppPublic,
+};
+use pezsp_staking::{
+	offence::{OffenceDetails, OffenceSeverity, OnOffenceHandler},
+	SessionIndex,
+};
+
+type Block = pezframe_system::mocking::MockBlock<Test>;
+pub type Extrinsic = TestXt<RuntimeCall, ()>;
+
+#[pezframe_support::runtime]
+mod runtime {
+	#[runtime::runtime]
+	#[runtime::derive(
+		RuntimeCall,
+		RuntimeEvent,
+		RuntimeError,
+		RuntimeOrigin,
+		RuntimeFreezeReason,
+		RuntimeHoldReason,
+		RuntimeSlashReason,
+		RuntimeLockId,
+		RuntimeTask,
+		RuntimeViewFunction
+	)]
+	pub struct Test;
+
+	#[runtime::pezpallet_index(0)]
+	pub type System = pezframe_system::Pezpallet<Test>;
+
+	#[runtime::pezpallet_index(1)]
+	pub type Timestamp = pezpallet_timestamp::Pezpallet<Test>;
+
+	#[runtime::pezpallet_index(2)]
+	pub type Aura = pezpallet_aura::Pezpallet<Test>;
+
+	#[runtime::pezpallet_index(3)]
+	pub type Balances = pezpallet_balances::Pezpallet<Test>;
+
+	#[runtime::pezpallet_index(4)]
+	pub type Authorship = pezpallet_authorship::Pezpallet<Test>;
+
+	#[runtime::pezpallet_index(5)]
+	pub type Session = pezpallet_session::Pezpallet<Test>;
+
+	#[runtime::pezpallet_index(6)]
+	pub type Historical = pezpallet_session_historical::Pezpallet<Test>;
+
+	#[runtime::pezpallet_index(7)]
+	pub type Offences = pezpallet_offences::Pezpallet<Test>;
+
+	#[runtime::pezpallet_index(8)]
+	pub type AuraEquivocation = pezpallet_aura_equivocation::Pezpallet<Test>;
+}
+
+impl_opaque_keys! {
+	pub struct MockSessionKeys {
+		pub aura: Aura,
+	}
+}
+
+#[derive_impl(pezframe_system::config_preludes::TestDefaultConfig)]
+impl pezframe_system::Config for Test {
+	type Block = Block;
+	type AccountData = pezpallet_balances::AccountData<u64>;
+}
+
+impl<C> pezframe_system::offchain::CreateTransactionBase<C> for Test
+where
+	RuntimeCall: From<C>,
+{
+	type Extrinsic = Extrinsic;
+	type RuntimeCall = RuntimeCall;
+}
+
+impl<C> pezframe_system::offchain::CreateBare<C> for Test
+where
+	RuntimeCall: From<C>,
+{
+	fn create_bare(call: RuntimeCall) -> Extrinsic {
+		Extrinsic::new_bare(call)
+	}
+}
+
+impl pezpallet_timestamp::Config for Test {
+	type Moment = u64;
+	type OnTimestampSet = Aura;
+	type MinimumPeriod = ConstU64<1>;
+	type WeightInfo = ();
+}
+
+impl pezpallet_aura::Config for Test {
+	type AuthorityId = AuraId;
+	type DisabledValidators = Session;
+	type MaxAuthorities = ConstU32<10>;
+	type AllowMultipleBlocksPerSlot = ConstBool<false>;
+	type SlotDuration = pezpallet_aura::MinimumPeriodTimesTwo<Test>;
+}
+
+#[derive_impl(pezpallet_balances::config_preludes::TestDefaultConfig)]
+impl pezpallet_balances::Config for Test {
+	type AccountStore = System;
+}
+
+impl pezpallet_authorship::Config for Test {
+	type FindAuthor = pezpallet_session::FindAccountFromAuthorIndex<Self, Aura>;
+	type EventHandler = ();
+}
+
+impl pezpallet_session::Config for Test {
+	type RuntimeEvent = RuntimeEvent;
+	type ValidatorId = u64;
+	type ValidatorIdOf = ConvertInto;
+	type ShouldEndSession = pezpallet_session::PeriodicSessions<ConstU64<1>, ConstU64<0>>;
+	type NextSessionRotation = pezpallet_session::PeriodicSessions<ConstU64<1>, ConstU64<0>>;
+	type SessionManager =
+		pezpallet_session_historical::NoteHistoricalRoot<Self, TestSessionManager>;
+	type SessionHandler = <MockSessionKeys as OpaqueKeys>::KeyTypeIdProviders;
+	type Keys = MockSessionKeys;
+	type DisablingStrategy = pezpallet_session::disabling::UpToLimitDisablingStrategy<1>;
+	type WeightInfo = ();
+	type Currency = Balances;
+	type KeyDeposit = ();
+}
+
+impl pezpallet_session_historical::Config for Test {
+	type RuntimeEvent = RuntimeEvent;
+	type FullIdentification = ();
+	type FullIdentificationOf = UnitIdentificationOf;
+}
+
+pub struct UnitIdentificationOf;
+
+impl Convert<u64, Option<()>> for UnitIdentificationOf {
+	fn convert(_: u64) -> Option<()> {
+		Some(())
+	}
+}
+
+/// Keeps the validators from genesis for every session.
+pub struct TestSessionManager;
+
+impl pezpallet_session_historical::SessionManager<u64, ()> for TestSessionManager {
+	fn new_session(_: SessionIndex) -> Option<Vec<(u64, ())>> {
+		Some(pezpallet_session::Validators::<Test>::get().into_iter().map(|v| (v, ())).collect())
+	}
+
+	fn new_session_genesis(_: SessionIndex) -> Option<Vec<(u64, ())>> {
+		None
+	}
+
+	fn start_session(_: SessionIndex) {}
+
+	fn end_session(_: SessionIndex) {}
+}
+
+impl pezpallet_offences::Config for Test {
+	type RuntimeEvent = RuntimeEvent;
+	type IdentificationTuple = IdentificationTuple<Self>;
+	type OnOffenceHandler = DisableOffenders;
+}
+
+/// Disables the offenders for the rest of the session, like the runtime does.
+pub struct DisableOffenders;
+
+impl OnOffenceHandler<u64, IdentificationTuple<Test>, pezframe_support::weights::Weight>
+	for DisableOffenders
+{
+	fn on_offence(
+		offenders: &[OffenceDetails<u64, IdentificationTuple<Test>>],
+		slash_fraction: &[Perbill],
+		_session: SessionIndex,
+	) -> pezframe_support::weights::Weight {
+		for (details, fraction) in offenders.iter().zip(slash_fraction) {
+			Session::report_offence(details.offender.0, OffenceSeverity(*fraction));
+		}
+		Default::default()
+	}
+}
+
+parameter_types! {
+	pub const ReportLongevity: u64 = 10;
+}
+
+impl pezpallet_aura_equivocation::Config for Test {
+	type KeyOwnerProof = <Historical as KeyOwnerProofSystem<(KeyTypeId, AuraId)>>::Proof;
+	type EquivocationReportSystem = pezpallet_aura_equivocation::EquivocationReportSystem<
+		Self,
+		Offences,
+		Historical,
+		ReportLongevity,
+	>;
+	type WeightInfo = ();
+	#[cfg(feature = "runtime-benchmarks")]
+	type BenchmarkHelper = MockBenchmarkHelper;
+}
+
+/// Makes the benchmarked authorities the validators `1`, `2`, ... of the current session.
+#[cfg(feature = "runtime-benchmarks")]
+pub struct MockBenchmarkHelper;
+
+#[cfg(feature = "runtime-benchmarks")]
+impl
+	pezpallet_aura_equivocation::BenchmarkHelper<
+		AuraId,
+		<Test as pezpallet_aura_equivocation::Config>::KeyOwnerProof,
+	> for MockBenchmarkHelper
+{
+	fn set_validators(
+		authorities: &[AuraId],
+	) -> <Test as pezpallet_aura_equivocation::Config>::KeyOwnerProof {
+		let validators: Vec<u64> = (1..=authorities.len() as u64).collect();
+		for (validator, aura) in validators.iter().zip(authorities) {
+			let keys = MockSessionKeys { aura: aura.clone() };
+			pezpallet_session::KeyOwner::<Test>::insert(
+				(AuraId::ID, keys.get_raw(AuraId::ID).to_vec()),
+				validator,
+			);
+			pezpallet_session::NextKeys::<Test>::insert(validator, keys);
+		}
+		pezpallet_session::Validators::<Test>::put(validators);
+
+		Historical::prove((AuraId::ID, authorities[0].clone())).unwrap()
+	}
+}
+
+/// The validators of the mock, with the accounts `1`, `2` and `3`.
+pub const AUTHORITIES: [Sr25519Keyring; 3] =
+	[Sr25519Keyring::Alice, Sr25519Keyring::Bob, Sr25519Keyring::Charlie];
+
+// Build genesis storage with `AUTHORITIES` as the validators of the first session.
+pub fn new_test_ext() -> pezsp_io::TestExternalities {
+	let mut storage = pezframe_system::GenesisConfig::<Test>::default().build_storage().unwrap();
+
+	let keys = AUTHORITIES
+		.iter()
+		.zip(1..)
+		.map(|(keyring, account)| {
+			(account, account, MockSessionKeys { aura: AuraId::from(keyring.public()) })
+		})
+		.collect();
+	pezpallet_session::GenesisConfig::<Test> { keys, ..Default::default() }
+		.assimilate_storage(&mut storage)
+		.unwrap();
+
+	let mut ext: pezsp_io::TestExternalities = storage.into();
+	// The benchmarks generate their authorities' keys.
+	ext.register_extension(KeystoreExt::new(MemoryKeystore::new()));
+	ext.execute_with(|| System::set_block_number(1));
+	ext
+}
+
+/// Imports blocks until the session `index` started. Every block starts a new session.
+pub fn start_session(index: SessionIndex) {
+	while Session::current_index() < index {
+		let number = System::block_number() + 1;
+		System::set_block_number(number);
+		Session::on_initialize(number);
+	}
+}
@@ -0,0 +1,298 @@
+use crate::{check_equivocation_proof, mock::*, Call, EquivocationProof, Error, WeightInfo};
+use codec::Decode;
+use pezframe_support::{
+	assert_err, assert_ok,
+	dispatch::{GetDispatchInfo, Pays},
+	traits::{DisabledValidators, KeyOwnerProofSystem},
+	unsigned::ValidateUnsigned,
+};
+use pezsp_consensus_aura::{
+	digests::CompatibleDigestItem,
+	sr25519::{AuthorityId, AuthorityPair, AuthoritySignature},
+};
+use pezsp_core::{
+	offchain::{testing::TestTransactionPoolExt, TransactionPoolExt},
+	Pair,
+};
+use pezsp_keyring::Sr25519Keyring;
+use pezsp_runtime::{
+	generic,
+	traits::{BlakeTwo256, Dispatchable, Header as _},
+	transaction_validity::{
+		InvalidTransaction, TransactionPriority, TransactionSource, ValidTransaction,
+	},
+	Digest, DigestItem, RuntimeAppPublic,
+};
+
+type Header = generic::Header<u64, BlakeTwo256>;
+
+fn pair(seed: &str) -> AuthorityPair {
+	AuthorityPair::from_string(seed, None).unwrap()
+}
+
+fn sealed_header(author: &AuthorityPair, slot: u64, number: u64) -> Header {
+	let pre_digest =
+		<DigestItem as CompatibleDigestItem<AuthoritySignature>>::aura_pre_digest(slot.into());
+	let mut header = Header::new(
+		number,
+		Default::default(),
+		Default::default(),
+		Default::default(),
+		Digest { logs: vec![pre_digest] },
+	);
+	let signature = author.sign(header.hash().as_ref());
+	header
+		.digest_mut()
+		.push(<DigestItem as CompatibleDigestItem<AuthoritySignature>>::aura_seal(signature));
+	header
+}
+
+fn proof(
+	offender: AuthorityId,
+	first_header: Header,
+	second_header: Header,
+) -> EquivocationProof<Header, AuthorityId> {
+	EquivocationProof { offender, slot: 42.into(), first_header, second_header }
+}
+
+#[test]
+fn accepts_two_blocks_sealed_in_the_same_slot() {
+	pezsp_io::TestExternalities::default().execute_with(|| {
+		let alice = pair("//Alice");
+		let equivocation =
+			proof(alice.public(), sealed_header(&alice, 42, 1), sealed_header(&alice, 42, 2));
+		assert!(check_equivocation_proof(equivocation));
+	});
+}
+
+#[test]
+fn rejects_the_same_block_twice() {
+	pezsp_io::TestExternalities::default().execute_with(|| {
+		let alice = pair("//Alice");
+		let header = sealed_header(&alice, 42, 1);
+		assert!(!check_equivocation_proof(proof(alice.public(), header.clone(), header)));
+	});
+}
+
+#[test]
+fn rejects_blocks_from_different_slots() {
+	pezsp_io::TestExternalities::default().execute_with(|| {
+		let alice = pair("//Alice");
+		let equivocation =
+			proof(alice.public(), sealed_header(&alice, 42, 1), sealed_header(&alice, 43, 2));
+		assert!(!check_equivocation_proof(equivocation));
+	});
+}
+
+#[test]
+fn rejects_blocks_sealed_by_someone_else() {
+	pezsp_io::TestExternalities::default().execute_with(|| {
+		let alice = pair("//Alice");
+		let bob = pair("//Bob");
+		let equivocation =
+			proof(alice.public(), sealed_header(&alice, 42, 1), sealed_header(&bob, 42, 2));
+		assert!(!check_equivocation_proof(equivocation));
+	});
+}
+
+/// An equivocation of `offender` in slot 42, together with the proof that they own their key.
+fn report(
+	offender: Sr25519Keyring,
+) -> (EquivocationProof<Header, AuthorityId>, <Test as crate::Config>::KeyOwnerProof) {
+	let author = pair(&format!("//{offender:?}"));
+	let equivocation =
+		proof(author.public(), sealed_header(&author, 42, 1), sealed_header(&author, 42, 2));
+	let key_owner_proof = Historical::prove((AuthorityId::ID, author.public())).unwrap();
+	(equivocation, key_owner_proof)
+}
+
+fn report_call(
+	equivocation: EquivocationProof<Header, AuthorityId>,
+	key_owner_proof: <Test as crate::Config>::KeyOwnerProof,
+) -> Call<Test> {
+	Call::report_equivocation_unsigned {
+		equivocation_proof: Box::new(equivocation),
+		key_owner_proof,
+	}
+}
+
+#[test]
+fn report_equivocation_disables_the_offender() {
+	new_test_ext().execute_with(|| {
+		start_session(1);
+		let (equivocation, key_owner_proof) = report(Sr25519Keyring::Bob);
+
+		assert_ok!(AuraEquivocation::report_equivocation_unsigned(
+			RuntimeOrigin::none(),
+			Box::new(equivocation),
+			key_owner_proof,
+		));
+
+		System::assert_has_event(
+			pezpallet_offences::Event::Offence {
+				kind: *b"aura:equivocatio",
+				timeslot: codec::Encode::encode(&pezsp_consensus_slots::Slot::from(42)),
+			}
+			.into(),
+		);
+		// Bob is the second validator, and Aura no longer accepts their blocks.
+		assert_eq!(Session::disabled_validators(), vec![1]);
+		assert!(<Test as pezpallet_aura::Config>::DisabledValidators::is_disabled(1));
+		assert!(!<Test as pezpallet_aura::Config>::DisabledValidators::is_disabled(0));
+	});
+}
+
+#[test]
+fn report_equivocation_of_a_past_session_works() {
+	new_test_ext().execute_with(|| {
+		start_session(1);
+		let (equivocation, key_owner_proof) = report(Sr25519Keyring::Charlie);
+		start_session(3);
+
+		assert_ok!(AuraEquivocation::report_equivocation_unsigned(
+			RuntimeOrigin::none(),
+			Box::new(equivocation),
+			key_owner_proof,
+		));
+		assert_eq!(Session::disabled_validators(), vec![2]);
+	});
+}
+
+#[test]
+fn report_equivocation_with_the_key_owner_proof_of_someone_else_fails() {
+	new_test_ext().execute_with(|| {
+		start_session(1);
+		let (equivocation, _) = report(Sr25519Keyring::Bob);
+		let (_, key_owner_proof) = report(Sr25519Keyring::Alice);
+
+		assert_err!(
+			AuraEquivocation::report_equivocation_unsigned(
+				RuntimeOrigin::none(),
+				Box::new(equivocation),
+				key_owner_proof,
+			),
+			Error::<Test>::InvalidKeyOwnershipProof,
+		);
+		assert!(Session::disabled_validators().is_empty());
+	});
+}
+
+#[test]
+fn report_equivocation_with_an_invalid_proof_fails() {
+	new_test_ext().execute_with(|| {
+		start_session(1);
+		let (equivocation, key_owner_proof) = report(Sr25519Keyring::Bob);
+		let invalid_proofs = [
+			EquivocationProof {
+				second_header: equivocation.first_header.clone(),
+				..equivocation.clone()
+			},
+			EquivocationProof { slot: 43.into(), ..equivocation.clone() },
+			EquivocationProof {
+				second_header: sealed_header(&pair("//Alice"), 42, 2),
+				..equivocation.clone()
+			},
+		];
+
+		for invalid_proof in invalid_proofs {
+			assert_err!(
+				AuraEquivocation::report_equivocation_unsigned(
+					RuntimeOrigin::none(),
+					Box::new(invalid_proof),
+					key_owner_proof.clone(),
+				),
+				Error::<Test>::InvalidEquivocationProof,
+			);
+		}
+		assert!(Session::disabled_validators().is_empty());
+	});
+}
+
+#[test]
+fn report_equivocation_is_only_valid_once_and_from_the_local_node() {
+	new_test_ext().execute_with(|| {
+		start_session(1);
+		let (equivocation, key_owner_proof) = report(Sr25519Keyring::Bob);
+		let call = report_call(equivocation.clone(), key_owner_proof);
+
+		assert_eq!(
+			AuraEquivocation::validate_unsigned(TransactionSource::External, &call),
+			InvalidTransaction::Call.into(),
+		);
+		assert_eq!(
+			AuraEquivocation::validate_unsigned(TransactionSource::Local, &call),
+			ValidTransaction::with_tag_prefix("AuraEquivocation")
+				.priority(TransactionPriority::MAX)
+				.and_provides((equivocation.offender, 42u64))
+				.longevity(ReportLongevity::get())
+				.propagate(false)
+				.build(),
+		);
+		assert_ok!(AuraEquivocation::pre_dispatch(&call));
+
+		assert_ok!(RuntimeCall::from(call.clone()).dispatch(RuntimeOrigin::none()));
+
+		assert_eq!(
+			AuraEquivocation::validate_unsigned(TransactionSource::Local, &call),
+			InvalidTransaction::Stale.into(),
+		);
+		assert_eq!(AuraEquivocation::pre_dispatch(&call), Err(InvalidTransaction::Stale.into()));
+	});
+}
+
+#[test]
+fn report_equivocation_of_a_pruned_session_is_invalid() {
+	new_test_ext().execute_with(|| {
+		start_session(1);
+		let (equivocation, key_owner_proof) = report(Sr25519Keyring::Bob);
+		start_session(3);
+		Historical::prune_up_to(2);
+
+		let call = report_call(equivocation, key_owner_proof);
+		assert_eq!(
+			AuraEquivocation::validate_unsigned(TransactionSource::Local, &call),
+			InvalidTransaction::BadProof.into(),
+		);
+	});
+}
+
+#[test]
+fn valid_equivocation_reports_dont_pay_fees() {
+	new_test_ext().execute_with(|| {
+		start_session(1);
+		let (equivocation, key_owner_proof) = report(Sr25519Keyring::Bob);
+		let call = RuntimeCall::from(report_call(equivocation, key_owner_proof));
+
+		let info = call.get_dispatch_info();
+		assert_eq!(info.call_weight, <() as WeightInfo>::report_equivocation(3));
+		assert_eq!(info.pays_fee, Pays::Yes);
+
+		let post_info = call.dispatch(RuntimeOrigin::none()).unwrap();
+		assert_eq!(post_info.pays_fee, Pays::No);
+	});
+}
+
+#[test]
+fn equivocation_report_is_submitted_to_the_pool() {
+	let (pool, pool_state) = TestTransactionPoolExt::new();
+	let mut ext = new_test_ext();
+	ext.register_extension(TransactionPoolExt::new(pool));
+
+	ext.execute_with(|| {
+		start_session(1);
+		let (equivocation, key_owner_proof) = report(Sr25519Keyring::Alice);
+
+		assert_eq!(
+			AuraEquivocation::submit_unsigned_equivocation_report(
+				equivocation.clone(),
+				key_owner_proof.clone(),
+			),
+			Some(())
+		);
+
+		let transactions = &pool_state.read().transactions;
+		assert_eq!(transactions.len(), 1);
+		let extrinsic = Extrinsic::decode(&mut &transactions[0][..]).unwrap();
+		assert_eq!(extrinsic.function, report_call(equivocation, key_owner_proof).into());
+	});
+}
@@ -0,0 +1,100 @@
+//! Autogenerated weights for pezpallet_aura_equivocation
+//!
+//! THIS FILE WAS AUTO-GENERATED USING THE BIZINIKIWI BENCHMARK CLI VERSION 32.0.1
+//! DATE: 2026-10-18, STEPS: `50`, REPEAT: `20`, LOW RANGE: `[]`, HIGH RANGE: `[]`
+//! WORST CASE MAP SIZE: `1000000`
+//! HOSTNAME: `<UNKNOWN>`, CPU: `<UNKNOWN>`
+//! EXECUTION: Some(Wasm), WASM-EXECUTION: Compiled, CHAIN: Some("dev"), DB CACHE: 1024
+
+// Executed Command:
+// ./target/release/pez-solochain-template-node
+// benchmark
+// pezpallet
+// --chain
+// dev
+// --pezpallet
+// pezpallet_aura_equivocation
+// --extrinsic
+// *
+// --steps=50
+// --repeat=20
+// --wasm-execution=compiled
+// --output
+// pallets/aura-equivocation/src/weights.rs
+// --template
+// ../../.maintain/frame-weight-template.hbs
+
+#![cfg_attr(rustfmt, rustfmt_skip)]
+#![allow(unused_parens)]
+#![allow(unused_imports)]
+
+use pezframe_support::{traits::Get, weights::{Weight, constants::RocksDbWeight}};
+use core::marker::PhantomData;
+
+/// Weight functions needed for pezpallet_aura_equivocation.
+pub trait WeightInfo {
+	fn report_equivocation(v: u32, ) -> Weight;
+}
+
+/// Weights for pezpallet_aura_equivocation using the Bizinikiwi node and recommended hardware.
+pub struct BizinikiwiWeight<T>(PhantomData<T>);
+impl<T: pezframe_system::Config> WeightInfo for BizinikiwiWeight<T> {
+	/// Storage: Session CurrentIndex (r:1 w:0)
+	/// Proof: Session CurrentIndex (max_values: Some(1), max_size: None, mode: Measured)
+	/// Storage: Session KeyOwner (r:1 w:0)
+	/// Proof: Session KeyOwner (max_values: None, max_size: None, mode: Measured)
+	/// Storage: Session Validators (r:1 w:0)
+	/// Proof: Session Validators (max_values: Some(1), max_size: None, mode: Measured)
+	/// Storage: Authorship Author (r:1 w:0)
+	/// Proof: Authorship Author (max_values: Some(1), max_size: Some(32), added: 527, mode: MaxEncodedLen)
+	/// Storage: Offences ConcurrentReportsIndex (r:1 w:1)
+	/// Proof: Offences ConcurrentReportsIndex (max_values: None, max_size: None, mode: Measured)
+	/// Storage: Offences Reports (r:1 w:1)
+	/// Proof: Offences Reports (max_values: None, max_size: None, mode: Measured)
+	/// Storage: Session DisabledValidators (r:1 w:1)
+	/// Proof: Session DisabledValidators (max_values: Some(1), max_size: None, mode: Measured)
+	/// The range of component `v` is `[2, 32]`.
+	fn report_equivocation(v: u32, ) -> Weight {
+		// Proof Size summary in bytes:
+		//  Measured:  `567 + v * (32 ±0)`
+		//  Estimated: `4032 + v * (32 ±0)`
+		// Minimum execution time: 142_000_000 picoseconds.
+		Weight::from_parts(146_318_290, 4032)
+			// Standard Error: 12_455
+			.saturating_add(Weight::from_parts(183_409, 0).saturating_mul(v.into()))
+			.saturating_add(T::DbWeight::get().reads(7_u64))
+			.saturating_add(T::DbWeight::get().writes(3_u64))
+			.saturating_add(Weight::from_parts(0, 32).saturating_mul(v.into()))
+	}
+}
+
+// For backwards compatibility and tests
+impl WeightInfo for () {
+	/// Storage: Session CurrentIndex (r:1 w:0)
+	/// Proof: Session CurrentIndex (max_values: Some(1), max_size: None, mode: Measured)
+	/// Storage: Session KeyOwner (r:1 w:0)
+	/// Proof: Session KeyOwner (max_values: None, max_size: None, mode: Measured)
+	/// Storage: Session Validators (r:1 w:0)
+	/// Proof: Session Validators (max_values: Some(1), max_size: None, mode: Measured)
+	/// Storage: Authorship Author (r:1 w:0)
+	/// Proof: Authorship Author (max_values: Some(1), max_size: Some(32), added: 527, mode: MaxEncodedLen)
+	/// Storage: Offences ConcurrentReportsIndex (r:1 w:1)
+	/// Proof: Offences ConcurrentReportsIndex (max_values: None, max_size: None, mode: Measured)
+	/// Storage: Offences Reports (r:1 w:1)
+	/// Proof: Offences Reports (max_values: None, max_size: None, mode: Measured)
+	/// Storage: Session DisabledValidators (r:1 w:1)
+	/// Proof: Session DisabledValidators (max_values: Some(1), max_size: None, mode: Measured)
+	/// The range of component `v` is `[2, 32]`.
+	fn report_equivocation(v: u32, ) -> Weight {
+		// Proof Size summary in bytes:
+		//  Measured:  `567 + v * (32 ±0)`
+		//  Estimated: `4032 + v * (32 ±0)`
+		// Minimum execution time: 142_000_000 picoseconds.
+		Weight::from_parts(146_318_290, 4032)
+			// Standard Error: 12_455
+			.saturating_add(Weight::from_parts(183_409, 0).saturating_mul(v.into()))
+			.saturating_add(RocksDbWeight::get().reads(7_u64))
+			.saturating_add(RocksDbWeight::get().writes(3_u64))
+			.saturating_add(Weight::from_parts(0, 32).saturating_mul(v.into()))
+	}
+}
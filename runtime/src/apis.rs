@@ -27,10 +27,12 @@
 use alloc::vec::Vec;
 use pezframe_support::{
 	genesis_builder_helper::{build_state, get_preset},
+	traits::KeyOwnerProofSystem,
 	weights::Weight,
 };
 use pezpallet_grandpa::AuthorityId as GrandpaId;
 use pezsp_api::impl_runtime_apis;
+#[cfg(not(feature = "babe"))]
 use pezsp_consensus_aura::sr25519::AuthorityId as AuraId;
 use pezsp_core::{crypto::KeyTypeId, OpaqueMetadata};
 use pezsp_runtime::{
@@ -42,9 +44,13 @@ use pezsp_version::RuntimeVersion;
 
 // Local module imports
 use super::{
-	AccountId, Aura, Balance, Block, Executive, Grandpa, InherentDataExt, Nonce, Runtime,
+	AccountId, Balance, Block, Executive, Grandpa, Historical, InherentDataExt, Nonce, Runtime,
 	RuntimeCall, RuntimeGenesisConfig, SessionKeys, System, TransactionPayment, VERSION,
 };
+#[cfg(not(feature = "babe"))]
+use super::{Aura, AuraEquivocation};
+#[cfg(feature = "babe")]
+use super::{Babe, BABE_GENESIS_EPOCH_CONFIG, EPOCH_DURATION_IN_SLOTS};
 
 impl_runtime_apis! {
 	impl pezsp_api::Core<Block> for Runtime {
@@ -118,6 +124,7 @@ impl_runtime_apis! {
 		}
 	}
 
+	#[cfg(not(feature = "babe"))]
 	impl pezsp_consensus_aura::AuraApi<Block, AuraId> for Runtime {
 		fn slot_duration() -> pezsp_consensus_aura::SlotDuration {
 			pezsp_consensus_aura::SlotDuration::from_millis(Aura::slot_duration())
@@ -128,6 +135,84 @@ impl_runtime_apis! {
 		}
 	}
 
+	#[cfg(not(feature = "babe"))]
+	impl pezpallet_aura_equivocation::AuraEquivocationApi<Block, AuraId> for Runtime {
+		fn generate_key_ownership_proof(
+			authority_id: AuraId,
+		) -> Option<pezpallet_aura_equivocation::OpaqueKeyOwnershipProof> {
+			use codec::Encode;
+
+			Historical::prove((pezsp_core::crypto::key_types::AURA, authority_id))
+				.map(|p| p.encode())
+				.map(pezpallet_aura_equivocation::OpaqueKeyOwnershipProof::new)
+		}
+
+		fn submit_report_equivocation_unsigned_extrinsic(
+			equivocation_proof: pezpallet_aura_equivocation::EquivocationProof<
+				<Block as BlockT>::Header,
+				AuraId,
+			>,
+			key_owner_proof: pezpallet_aura_equivocation::OpaqueKeyOwnershipProof,
+		) -> Option<()> {
+			let key_owner_proof = key_owner_proof.decode()?;
+
+			AuraEquivocation::submit_unsigned_equivocation_report(
+				equivocation_proof,
+				key_owner_proof,
+			)
+		}
+	}
+
+	#[cfg(feature = "babe")]
+	impl pezsp_consensus_babe::BabeApi<Block> for Runtime {
+		fn configuration() -> pezsp_consensus_babe::BabeConfiguration {
+			let epoch_config = Babe::epoch_config().unwrap_or(BABE_GENESIS_EPOCH_CONFIG);
+			pezsp_consensus_babe::BabeConfiguration {
+				slot_duration: Babe::slot_duration(),
+				epoch_length: EPOCH_DURATION_IN_SLOTS,
+				c: epoch_config.c,
+				authorities: Babe::authorities().to_vec(),
+				randomness: Babe::randomness(),
+				allowed_slots: epoch_config.allowed_slots,
+			}
+		}
+
+		fn current_epoch_start() -> pezsp_consensus_babe::Slot {
+			Babe::current_epoch_start()
+		}
+
+		fn current_epoch() -> pezsp_consensus_babe::Epoch {
+			Babe::current_epoch()
+		}
+
+		fn next_epoch() -> pezsp_consensus_babe::Epoch {
+			Babe::next_epoch()
+		}
+
+		fn generate_key_ownership_proof(
+			_slot: pezsp_consensus_babe::Slot,
+			authority_id: pezsp_consensus_babe::AuthorityId,
+		) -> Option<pezsp_consensus_babe::OpaqueKeyOwnershipProof> {
+			use codec::Encode;
+
+			Historical::prove((pezsp_consensus_babe::KEY_TYPE, authority_id))
+				.map(|p| p.encode())
+				.map(pezsp_consensus_babe::OpaqueKeyOwnershipProof::new)
+		}
+
+		fn submit_report_equivocation_unsigned_extrinsic(
+			equivocation_proof: pezsp_consensus_babe::EquivocationProof<<Block as BlockT>::Header>,
+			key_owner_proof: pezsp_consensus_babe::OpaqueKeyOwnershipProof,
+		) -> Option<()> {
+			let key_owner_proof = key_owner_proof.decode()?;
+
+			Babe::submit_unsigned_equivocation_report(
+				equivocation_proof,
+				key_owner_proof,
+			)
+		}
+	}
+
 	impl pezsp_session::SessionKeys<Block> for Runtime {
 		fn generate_session_keys(seed: Option<Vec<u8>>) -> Vec<u8> {
 			SessionKeys::generate(seed)
@@ -150,23 +235,29 @@ impl_runtime_apis! {
 		}
 
 		fn submit_report_equivocation_unsigned_extrinsic(
-			_equivocation_proof: pezsp_consensus_grandpa::EquivocationProof<
+			equivocation_proof: pezsp_consensus_grandpa::EquivocationProof<
 				<Block as BlockT>::Hash,
 				NumberFor<Block>,
 			>,
-			_key_owner_proof: pezsp_consensus_grandpa::OpaqueKeyOwnershipProof,
+			key_owner_proof: pezsp_consensus_grandpa::OpaqueKeyOwnershipProof,
 		) -> Option<()> {
-			None
+			let key_owner_proof = key_owner_proof.decode()?;
+
+			Grandpa::submit_unsigned_equivocation_report(
+				equivocation_proof,
+				key_owner_proof,
+			)
 		}
 
 		fn generate_key_ownership_proof(
 			_set_id: pezsp_consensus_grandpa::SetId,
-			_authority_id: GrandpaId,
+			authority_id: GrandpaId,
 		) -> Option<pezsp_consensus_grandpa::OpaqueKeyOwnershipProof> {
-			// NOTE: this is the only implementation possible since we've
-			// defined our key owner proof type as a bottom type (i.e. a type
-			// with no values).
-			None
+			use codec::Encode;
+
+			Historical::prove((pezsp_consensus_grandpa::KEY_TYPE, authority_id))
+				.map(|p| p.encode())
+				.map(pezsp_consensus_grandpa::OpaqueKeyOwnershipProof::new)
 		}
 	}
 
@@ -23,6 +23,19 @@
 //
 // For more information, please refer to <http://unlicense.org>
 
+#[cfg(not(feature = "babe"))]
+pezframe_benchmarking::define_benchmarks!(
+	[pezframe_benchmarking, BaselineBench::<Runtime>]
+	[pezframe_system, SystemBench::<Runtime>]
+	[pezframe_system_extensions, SystemExtensionsBench::<Runtime>]
+	[pezpallet_balances, Balances]
+	[pezpallet_timestamp, Timestamp]
+	[pezpallet_sudo, Sudo]
+	[pezpallet_template, Template]
+	[pezpallet_aura_equivocation, AuraEquivocation]
+);
+
+#[cfg(feature = "babe")]
 pezframe_benchmarking::define_benchmarks!(
 	[pezframe_benchmarking, BaselineBench::<Runtime>]
 	[pezframe_system, SystemBench::<Runtime>]
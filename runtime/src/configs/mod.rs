@@ -24,26 +24,45 @@
 // For more information, please refer to <http://unlicense.org>
 
 // Bizinikiwi and Pezkuwi dependencies
+use alloc::vec::Vec;
 use pezframe_support::{
 	derive_impl, parameter_types,
-	traits::{ConstBool, ConstU128, ConstU32, ConstU64, ConstU8, VariantCountOf},
+	traits::{
+		ConstBool, ConstU128, ConstU32, ConstU64, ConstU8, Get, KeyOwnerProofSystem, VariantCountOf,
+	},
 	weights::{
 		constants::{RocksDbWeight, WEIGHT_REF_TIME_PER_SECOND},
 		IdentityFee, Weight,
 	},
 };
 use pezframe_system::limits::{BlockLength, BlockWeights};
+use pezpallet_grandpa::AuthorityId as GrandpaId;
+use pezpallet_session::historical::{self as pezpallet_session_historical, IdentificationTuple};
 use pezpallet_transaction_payment::{ConstFeeMultiplier, FungibleAdapter, Multiplier};
+#[cfg(not(feature = "babe"))]
 use pezsp_consensus_aura::sr25519::AuthorityId as AuraId;
-use pezsp_runtime::{traits::One, Perbill};
+use pezsp_core::crypto::KeyTypeId;
+use pezsp_runtime::{
+	traits::{Convert, ConvertInto, One, OpaqueKeys},
+	Perbill,
+};
+use pezsp_staking::{
+	offence::{OffenceDetails, OffenceSeverity, OnOffenceHandler},
+	SessionIndex,
+};
 use pezsp_version::RuntimeVersion;
 
 // Local module imports
 use super::{
-	AccountId, Aura, Balance, Balances, Block, BlockNumber, Hash, Nonce, PalletInfo, Runtime,
-	RuntimeCall, RuntimeEvent, RuntimeFreezeReason, RuntimeHoldReason, RuntimeOrigin, RuntimeTask,
-	System, EXISTENTIAL_DEPOSIT, SLOT_DURATION, VERSION,
+	AccountId, Balance, Balances, Block, BlockNumber, Hash, Historical, Nonce, Offences,
+	PalletInfo, Runtime, RuntimeCall, RuntimeEvent, RuntimeFreezeReason, RuntimeHoldReason,
+	RuntimeOrigin, RuntimeTask, Session, SessionKeys, System, UncheckedExtrinsic, DAYS,
+	EXISTENTIAL_DEPOSIT, SLOT_DURATION, VERSION,
 };
+#[cfg(not(feature = "babe"))]
+use super::{Aura, HOURS};
+#[cfg(feature = "babe")]
+use super::{Babe, EPOCH_DURATION_IN_SLOTS, MILLI_SECS_PER_BLOCK};
 
 const NORMAL_DISPATCH_RATIO: Perbill = Perbill::from_percent(75);
 
@@ -64,7 +83,7 @@ parameter_types! {
 ///
 /// This can be a tuple of types, each implementing `OnRuntimeUpgrade`.
 #[allow(unused_parens)]
-type SingleBlockMigrations = ();
+type SingleBlockMigrations = (crate::migrations::SeedSessionFromAuthorities);
 
 /// The default types are being injected by [`derive_impl`](`pezframe_support::derive_impl`) from
 /// [`SoloChainDefaultConfig`](`struct@pezframe_system::config_preludes::SolochainDefaultConfig`),
@@ -97,30 +116,229 @@ impl pezframe_system::Config for Runtime {
 	type SingleBlockMigrations = SingleBlockMigrations;
 }
 
+#[cfg(not(feature = "babe"))]
 impl pezpallet_aura::Config for Runtime {
 	type AuthorityId = AuraId;
-	type DisabledValidators = ();
+	/// Blocks authored by validators disabled for an offence are rejected until the session ends.
+	type DisabledValidators = Session;
 	type MaxAuthorities = ConstU32<32>;
 	type AllowMultipleBlocksPerSlot = ConstBool<false>;
 	type SlotDuration = pezpallet_aura::MinimumPeriodTimesTwo<Runtime>;
 }
 
+parameter_types! {
+	/// How long an equivocation report stays valid in the transaction pool.
+	pub const ReportLongevity: u64 = 7 * DAYS as u64;
+}
+
+#[cfg(feature = "babe")]
+parameter_types! {
+	pub const EpochDuration: u64 = EPOCH_DURATION_IN_SLOTS;
+	pub const ExpectedBlockTime: u64 = MILLI_SECS_PER_BLOCK;
+}
+
+#[cfg(feature = "babe")]
+impl pezpallet_babe::Config for Runtime {
+	type EpochDuration = EpochDuration;
+	type ExpectedBlockTime = ExpectedBlockTime;
+	/// Epochs change together with sessions, which are in turn rotated by BABE.
+	type EpochChangeTrigger = pezpallet_babe::ExternalTrigger;
+	/// Blocks authored by validators disabled for an offence are rejected until the session ends.
+	type DisabledValidators = Session;
+	type WeightInfo = ();
+	type MaxAuthorities = ConstU32<32>;
+	type MaxNominators = ConstU32<0>;
+
+	type KeyOwnerProof =
+		<Historical as KeyOwnerProofSystem<(KeyTypeId, pezpallet_babe::AuthorityId)>>::Proof;
+	type EquivocationReportSystem =
+		pezpallet_babe::EquivocationReportSystem<Self, Offences, Historical, ReportLongevity>;
+}
+
 impl pezpallet_grandpa::Config for Runtime {
 	type RuntimeEvent = RuntimeEvent;
 
 	type WeightInfo = ();
 	type MaxAuthorities = ConstU32<32>;
 	type MaxNominators = ConstU32<0>;
-	type MaxSetIdSessionEntries = ConstU64<0>;
+	/// Keep the session of the last week of authority sets, so that equivocations can be
+	/// reported for as long as their reports are valid.
+	type MaxSetIdSessionEntries = ConstU64<{ 7 * 24 }>;
+
+	type KeyOwnerProof = <Historical as KeyOwnerProofSystem<(KeyTypeId, GrandpaId)>>::Proof;
+	type EquivocationReportSystem =
+		pezpallet_grandpa::EquivocationReportSystem<Self, Offences, Historical, ReportLongevity>;
+}
+
+#[cfg(not(feature = "babe"))]
+impl pezpallet_aura_equivocation::Config for Runtime {
+	type KeyOwnerProof = <Historical as KeyOwnerProofSystem<(KeyTypeId, AuraId)>>::Proof;
+	type EquivocationReportSystem = pezpallet_aura_equivocation::EquivocationReportSystem<
+		Self,
+		Offences,
+		Historical,
+		ReportLongevity,
+	>;
+	type WeightInfo = pezpallet_aura_equivocation::weights::BizinikiwiWeight<Runtime>;
+	#[cfg(feature = "runtime-benchmarks")]
+	type BenchmarkHelper = AuraEquivocationBenchmarkHelper;
+}
+
+/// Makes the benchmarked authorities validators, with accounts and GRANDPA keys of their own.
+#[cfg(all(feature = "runtime-benchmarks", not(feature = "babe")))]
+pub struct AuraEquivocationBenchmarkHelper;
+
+#[cfg(all(feature = "runtime-benchmarks", not(feature = "babe")))]
+impl
+	pezpallet_aura_equivocation::BenchmarkHelper<
+		AuraId,
+		<Runtime as pezpallet_aura_equivocation::Config>::KeyOwnerProof,
+	> for AuraEquivocationBenchmarkHelper
+{
+	fn set_validators(
+		authorities: &[AuraId],
+	) -> <Runtime as pezpallet_aura_equivocation::Config>::KeyOwnerProof {
+		use pezsp_runtime::RuntimeAppPublic;
+
+		let validators = authorities
+			.iter()
+			.map(|aura| {
+				let account = AccountId::from(pezsp_core::sr25519::Public::from(aura.clone()));
+				(
+					account,
+					SessionKeys { aura: aura.clone(), grandpa: GrandpaId::generate_pair(None) },
+				)
+			})
+			.collect();
+		crate::migrations::set_validators(validators);
+
+		Historical::prove((AuraId::ID, authorities[0].clone()))
+			.expect("the first authority is a validator of the current session; qed")
+	}
+}
+
+impl pezpallet_authorship::Config for Runtime {
+	#[cfg(not(feature = "babe"))]
+	type FindAuthor = pezpallet_session::FindAccountFromAuthorIndex<Self, Aura>;
+	#[cfg(feature = "babe")]
+	type FindAuthor = pezpallet_session::FindAccountFromAuthorIndex<Self, Babe>;
+	type EventHandler = ();
+}
+
+impl pezpallet_session::Config for Runtime {
+	type RuntimeEvent = RuntimeEvent;
+	type ValidatorId = AccountId;
+	type ValidatorIdOf = ConvertInto;
+	#[cfg(not(feature = "babe"))]
+	type ShouldEndSession = pezpallet_session::PeriodicSessions<ConstU32<HOURS>, ConstU32<0>>;
+	#[cfg(not(feature = "babe"))]
+	type NextSessionRotation = pezpallet_session::PeriodicSessions<ConstU32<HOURS>, ConstU32<0>>;
+	#[cfg(feature = "babe")]
+	type ShouldEndSession = Babe;
+	#[cfg(feature = "babe")]
+	type NextSessionRotation = Babe;
+	type SessionManager = pezpallet_session_historical::NoteHistoricalRoot<Self, StaticValidators>;
+	type SessionHandler = <SessionKeys as OpaqueKeys>::KeyTypeIdProviders;
+	type Keys = SessionKeys;
+	/// Block production goes on as long as a single validator is enabled, so any but the last one
+	/// may be disabled.
+	type DisablingStrategy = pezpallet_session::disabling::UpToLimitDisablingStrategy<1>;
+	type WeightInfo = ();
+	type Currency = Balances;
+	type KeyDeposit = ();
+}
+
+impl pezpallet_session_historical::Config for Runtime {
+	type RuntimeEvent = RuntimeEvent;
+	type FullIdentification = ();
+	type FullIdentificationOf = UnitIdentificationOf;
+}
+
+/// Validators carry no stake, so there is nothing to identify them by but their account.
+pub struct UnitIdentificationOf;
+
+impl Convert<AccountId, Option<()>> for UnitIdentificationOf {
+	fn convert(_: AccountId) -> Option<()> {
+		Some(())
+	}
+}
+
+/// Keeps the validator set from genesis for every session.
+///
+/// As the set never changes, every session reuses the historical root of the previous one, so
+/// that key ownership can be proven for past sessions as well.
+pub struct StaticValidators;
+
+impl pezpallet_session_historical::SessionManager<AccountId, ()> for StaticValidators {
+	fn new_session(_: SessionIndex) -> Option<Vec<(AccountId, ())>> {
+		None
+	}
+
+	fn new_session_genesis(_: SessionIndex) -> Option<Vec<(AccountId, ())>> {
+		// The session keys from the genesis config are set by now, and every account that has them
+		// is a validator. Returning them notes a historical root for the genesis sessions.
+		Some(pezpallet_session::NextKeys::<Runtime>::iter_keys().map(|v| (v, ())).collect())
+	}
+
+	fn start_session(_: SessionIndex) {}
+
+	fn end_session(_: SessionIndex) {}
+}
+
+impl pezpallet_offences::Config for Runtime {
+	type RuntimeEvent = RuntimeEvent;
+	type IdentificationTuple = IdentificationTuple<Self>;
+	type OnOffenceHandler = DisableOffenders;
+}
+
+/// Handles reported offences by disabling the offenders for the rest of the session.
+pub struct DisableOffenders;
+
+impl OnOffenceHandler<AccountId, IdentificationTuple<Runtime>, Weight> for DisableOffenders {
+	fn on_offence(
+		offenders: &[OffenceDetails<AccountId, IdentificationTuple<Runtime>>],
+		slash_fraction: &[Perbill],
+		_session: SessionIndex,
+	) -> Weight {
+		for (details, fraction) in offenders.iter().zip(slash_fraction) {
+			let (validator, ()) = &details.offender;
+			log::warn!(
+				target: "runtime::offences",
+				"Disabling validator {validator:?} for an offence with slash fraction {fraction:?}",
+			);
+			Session::report_offence(validator.clone(), OffenceSeverity(*fraction));
+		}
+
+		<Runtime as pezframe_system::Config>::DbWeight::get()
+			.reads_writes(2 * offenders.len() as u64, offenders.len() as u64)
+	}
+}
+
+impl<LocalCall> pezframe_system::offchain::CreateTransactionBase<LocalCall> for Runtime
+where
+	RuntimeCall: From<LocalCall>,
+{
+	type Extrinsic = UncheckedExtrinsic;
+	type RuntimeCall = RuntimeCall;
+}
 
-	type KeyOwnerProof = pezsp_core::Void;
-	type EquivocationReportSystem = ();
+/// Lets pallets submit unsigned transactions, e.g. GRANDPA equivocation reports.
+impl<LocalCall> pezframe_system::offchain::CreateBare<LocalCall> for Runtime
+where
+	RuntimeCall: From<LocalCall>,
+{
+	fn create_bare(call: RuntimeCall) -> UncheckedExtrinsic {
+		UncheckedExtrinsic::new_bare(call)
+	}
 }
 
 impl pezpallet_timestamp::Config for Runtime {
 	/// A timestamp: milliseconds since the unix epoch.
 	type Moment = u64;
+	#[cfg(not(feature = "babe"))]
 	type OnTimestampSet = Aura;
+	#[cfg(feature = "babe")]
+	type OnTimestampSet = Babe;
 	type MinimumPeriod = ConstU64<{ SLOT_DURATION / 2 }>;
 	type WeightInfo = ();
 }
@@ -15,10 +15,12 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use crate::{AccountId, BalancesConfig, RuntimeGenesisConfig, SudoConfig};
+use crate::{
+	AccountId, Balance, BalancesConfig, BlockAuthorityId, RuntimeGenesisConfig, SessionConfig,
+	SessionKeys, SudoConfig,
+};
 use alloc::{vec, vec::Vec};
 use pezframe_support::build_struct_json_patch;
-use pezsp_consensus_aura::sr25519::AuthorityId as AuraId;
 use pezsp_consensus_grandpa::AuthorityId as GrandpaId;
 use pezsp_genesis_builder::{self, PresetId};
 use pezsp_keyring::Sr25519Keyring;
@@ -26,32 +28,63 @@ use serde_json::Value;
 
 // Returns the genesis config presets populated with given parameters.
 fn testnet_genesis(
-	initial_authorities: Vec<(AuraId, GrandpaId)>,
+	initial_authorities: Vec<(AccountId, BlockAuthorityId, GrandpaId)>,
 	endowed_accounts: Vec<AccountId>,
 	root: AccountId,
 ) -> Value {
-	build_struct_json_patch!(RuntimeGenesisConfig {
-		balances: BalancesConfig {
-			balances: endowed_accounts
-				.iter()
-				.cloned()
-				.map(|k| (k, 1u128 << 60))
+	genesis(
+		initial_authorities,
+		endowed_accounts.into_iter().map(|k| (k, 1u128 << 60)).collect(),
+		Some(root),
+	)
+}
+
+/// Returns a genesis config patch with the given authorities, balances and sudo key.
+///
+/// Block authoring (Aura or BABE) and GRANDPA authorities are initialised by the session pezpallet
+/// from the session keys of `initial_authorities`.
+pub fn genesis(
+	initial_authorities: Vec<(AccountId, BlockAuthorityId, GrandpaId)>,
+	endowed_balances: Vec<(AccountId, Balance)>,
+	root: Option<AccountId>,
+) -> Value {
+	#[allow(unused_mut)]
+	let mut patch = build_struct_json_patch!(RuntimeGenesisConfig {
+		balances: BalancesConfig { balances: endowed_balances },
+		session: SessionConfig {
+			keys: initial_authorities
+				.into_iter()
+				.map(|(account, authoring, grandpa)| {
+					(account.clone(), account, session_keys(authoring, grandpa))
+				})
 				.collect::<Vec<_>>(),
 		},
-		aura: pezpallet_aura::GenesisConfig {
-			authorities: initial_authorities.iter().map(|x| x.0.clone()).collect::<Vec<_>>(),
-		},
-		grandpa: pezpallet_grandpa::GenesisConfig {
-			authorities: initial_authorities.iter().map(|x| (x.1.clone(), 1)).collect::<Vec<_>>(),
-		},
-		sudo: SudoConfig { key: Some(root) },
-	})
+		sudo: SudoConfig { key: root },
+	});
+
+	#[cfg(feature = "babe")]
+	{
+		patch["babe"] = serde_json::json!({ "epochConfig": crate::BABE_GENESIS_EPOCH_CONFIG });
+	}
+
+	patch
+}
+
+#[cfg(not(feature = "babe"))]
+fn session_keys(aura: BlockAuthorityId, grandpa: GrandpaId) -> SessionKeys {
+	SessionKeys { aura, grandpa }
+}
+
+#[cfg(feature = "babe")]
+fn session_keys(babe: BlockAuthorityId, grandpa: GrandpaId) -> SessionKeys {
+	SessionKeys { babe, grandpa }
 }
 
 /// Return the development genesis config.
 pub fn development_config_genesis() -> Value {
 	testnet_genesis(
 		vec![(
+			Sr25519Keyring::Alice.to_account_id(),
 			pezsp_keyring::Sr25519Keyring::Alice.public().into(),
 			pezsp_keyring::Ed25519Keyring::Alice.public().into(),
 		)],
@@ -70,10 +103,12 @@ pub fn local_config_genesis() -> Value {
 	testnet_genesis(
 		vec![
 			(
+				Sr25519Keyring::Alice.to_account_id(),
 				pezsp_keyring::Sr25519Keyring::Alice.public().into(),
 				pezsp_keyring::Ed25519Keyring::Alice.public().into(),
 			),
 			(
+				Sr25519Keyring::Bob.to_account_id(),
 				pezsp_keyring::Sr25519Keyring::Bob.public().into(),
 				pezsp_keyring::Ed25519Keyring::Bob.public().into(),
 			),
@@ -7,9 +7,14 @@ pub mod apis;
 #[cfg(feature = "runtime-benchmarks")]
 mod benchmarks;
 pub mod configs;
+mod migrations;
+#[cfg(test)]
+mod tests;
 
 extern crate alloc;
 use alloc::vec::Vec;
+use codec::{Decode, Encode};
+use pezpallet_session::historical as pezpallet_session_historical;
 use pezsp_runtime::{
 	generic, impl_opaque_keys,
 	traits::{BlakeTwo256, IdentifyAccount, Verify},
@@ -50,6 +55,14 @@ pub mod opaque {
 	pub type Hash = <BlakeTwo256 as HashT>::Output;
 }
 
+/// The key authoring blocks, an Aura key by default or a BABE key with the `babe` feature.
+#[cfg(not(feature = "babe"))]
+pub type BlockAuthorityId = pezsp_consensus_aura::sr25519::AuthorityId;
+/// The key authoring blocks, an Aura key by default or a BABE key with the `babe` feature.
+#[cfg(feature = "babe")]
+pub type BlockAuthorityId = pezsp_consensus_babe::AuthorityId;
+
+#[cfg(not(feature = "babe"))]
 impl_opaque_keys! {
 	pub struct SessionKeys {
 		pub aura: Aura,
@@ -57,6 +70,14 @@ impl_opaque_keys! {
 	}
 }
 
+#[cfg(feature = "babe")]
+impl_opaque_keys! {
+	pub struct SessionKeys {
+		pub babe: Babe,
+		pub grandpa: Grandpa,
+	}
+}
+
 // To learn more about runtime versioning, see:
 // https://docs.pezkuwichain.io/main-docs/build/upgrade#runtime-versioning
 #[pezsp_version::runtime_version]
@@ -69,18 +90,18 @@ pub const VERSION: RuntimeVersion = RuntimeVersion {
 	//   `spec_version`, and `authoring_version` are the same between Wasm and native.
 	// This value is set to 100 to notify Pezkuwi-JS App (https://pezkuwichain.io) to use
 	//   the compatible custom types.
-	spec_version: 100,
+	spec_version: 104,
 	impl_version: 1,
 	apis: apis::RUNTIME_API_VERSIONS,
-	transaction_version: 1,
+	transaction_version: 2,
 	system_version: 1,
 };
 
 mod block_times {
 	/// This determines the average expected block time that we are targeting. Blocks will be
 	/// produced at a minimum duration defined by `SLOT_DURATION`. `SLOT_DURATION` is picked up by
-	/// `pezpallet_timestamp` which is in turn picked up by `pezpallet_aura` to implement `fn
-	/// slot_duration()`.
+	/// `pezpallet_timestamp` which is in turn picked up by `pezpallet_aura` (or `pezpallet_babe`)
+	/// to implement `fn slot_duration()`.
 	///
 	/// Change this to adjust the block time.
 	pub const MILLI_SECS_PER_BLOCK: u64 = 6000;
@@ -96,6 +117,22 @@ pub const MINUTES: BlockNumber = 60_000 / (MILLI_SECS_PER_BLOCK as BlockNumber);
 pub const HOURS: BlockNumber = MINUTES * 60;
 pub const DAYS: BlockNumber = HOURS * 24;
 
+/// The length of a BABE epoch in slots. Sessions follow epochs, so this is also the session length.
+#[cfg(feature = "babe")]
+pub const EPOCH_DURATION_IN_SLOTS: u64 = HOURS as u64;
+
+/// 1 in 4 blocks (on average, not counting collisions) will be primary BABE blocks.
+#[cfg(feature = "babe")]
+pub const PRIMARY_PROBABILITY: (u64, u64) = (1, 4);
+
+/// The BABE epoch configuration at genesis.
+#[cfg(feature = "babe")]
+pub const BABE_GENESIS_EPOCH_CONFIG: pezsp_consensus_babe::BabeEpochConfiguration =
+	pezsp_consensus_babe::BabeEpochConfiguration {
+		c: PRIMARY_PROBABILITY,
+		allowed_slots: pezsp_consensus_babe::AllowedSlots::PrimaryAndSecondaryVRFSlots,
+	};
+
 pub const BLOCK_HASH_COUNT: BlockNumber = 2400;
 
 // Unit = the base number of indivisible units for balances
@@ -161,6 +198,21 @@ pub type TxExtension = (
 	pezframe_system::WeightReclaim<Runtime>,
 );
 
+/// The nonce and the tip of a transaction signed with `tx_ext`.
+pub fn nonce_and_tip(tx_ext: &TxExtension) -> (Nonce, Balance) {
+	// Naming the type of the items breaks the build if they move within `TxExtension`.
+	let (_, _, _, _, _, _, check_nonce, _, charge_transaction_payment, _, _) = tx_ext;
+	let check_nonce: &pezframe_system::CheckNonce<Runtime> = check_nonce;
+	let charge_transaction_payment: &pezpallet_transaction_payment::ChargeTransactionPayment<
+		Runtime,
+	> = charge_transaction_payment;
+
+	// `ChargeTransactionPayment` consists of nothing but the compact tip.
+	let tip = codec::Compact::<Balance>::decode(&mut &charge_transaction_payment.encode()[..])
+		.map_or(0, |tip| tip.0);
+	(check_nonce.0, tip)
+}
+
 /// Unchecked extrinsic type as expected by this runtime.
 pub type UncheckedExtrinsic =
 	generic::UncheckedExtrinsic<Address, RuntimeCall, Signature, TxExtension>;
@@ -201,9 +253,14 @@ mod runtime {
 	#[runtime::pezpallet_index(1)]
 	pub type Timestamp = pezpallet_timestamp;
 
+	#[cfg(not(feature = "babe"))]
 	#[runtime::pezpallet_index(2)]
 	pub type Aura = pezpallet_aura;
 
+	#[cfg(feature = "babe")]
+	#[runtime::pezpallet_index(2)]
+	pub type Babe = pezpallet_babe;
+
 	#[runtime::pezpallet_index(3)]
 	pub type Grandpa = pezpallet_grandpa;
 
@@ -219,4 +276,20 @@ mod runtime {
 	// Include the custom logic from the pezpallet-template in the runtime.
 	#[runtime::pezpallet_index(7)]
 	pub type Template = pezpallet_template;
+
+	#[runtime::pezpallet_index(8)]
+	pub type Authorship = pezpallet_authorship;
+
+	#[runtime::pezpallet_index(9)]
+	pub type Session = pezpallet_session;
+
+	#[runtime::pezpallet_index(10)]
+	pub type Historical = pezpallet_session_historical;
+
+	#[runtime::pezpallet_index(11)]
+	pub type Offences = pezpallet_offences;
+
+	#[cfg(not(feature = "babe"))]
+	#[runtime::pezpallet_index(12)]
+	pub type AuraEquivocation = pezpallet_aura_equivocation;
 }
@@ -0,0 +1,111 @@
+//! Migrations of the runtime's storage that aren't tied to a single pezpallet.
+
+use crate::{AccountId, BlockAuthorityId, Grandpa, Runtime, SessionKeys, System};
+use alloc::vec::Vec;
+use pezframe_support::{
+	traits::{Get, OnRuntimeUpgrade},
+	weights::Weight,
+};
+use pezpallet_grandpa::AuthorityId as GrandpaId;
+use pezpallet_session::SessionManager;
+use pezsp_runtime::traits::OpaqueKeys;
+
+const LOG_TARGET: &str = "runtime::migrations";
+
+type SessionManagerOf = <Runtime as pezpallet_session::Config>::SessionManager;
+
+/// Seeds the session pezpallet from the current block authoring and GRANDPA authorities.
+///
+/// Chains started before the session pezpallet was added have authorities but no validators or
+/// session keys, so the first session rotation would hand an empty set to the consensus pallets.
+/// Every authority becomes a validator whose account is its sr25519 authoring key, with the GRANDPA
+/// key at the same position. Does nothing once the session pezpallet has validators.
+pub struct SeedSessionFromAuthorities;
+
+impl OnRuntimeUpgrade for SeedSessionFromAuthorities {
+	fn on_runtime_upgrade() -> Weight {
+		let db_weight = <Runtime as pezframe_system::Config>::DbWeight::get();
+		if !pezpallet_session::Validators::<Runtime>::get().is_empty() {
+			return db_weight.reads(1);
+		}
+
+		let authoring = authoring_authorities();
+		let grandpa = Grandpa::grandpa_authorities();
+		if authoring.len() != grandpa.len() {
+			log::error!(
+				target: LOG_TARGET,
+				"Not seeding the session pezpallet: {} block authoring but {} GRANDPA authorities",
+				authoring.len(),
+				grandpa.len(),
+			);
+			return db_weight.reads(3);
+		}
+
+		let validators: Vec<(AccountId, SessionKeys)> = authoring
+			.into_iter()
+			.zip(grandpa)
+			.map(|(authoring, (grandpa, _))| {
+				let account = AccountId::from(pezsp_core::sr25519::Public::from(authoring.clone()));
+				(account, session_keys(authoring, grandpa))
+			})
+			.collect();
+
+		let count = validators.len() as u64;
+		set_validators(validators);
+
+		log::info!(target: LOG_TARGET, "Seeded the session pezpallet with {count} validators");
+
+		db_weight.reads_writes(4 + 3 * count, 5 + 4 * count)
+	}
+}
+
+/// Makes `validators` the validators of the current session, with the given session keys.
+///
+/// Mirrors the genesis build of the session pezpallet, and notes historical roots for the current
+/// and the already planned session, as later sessions only reuse the root of the previous one.
+pub(crate) fn set_validators(validators: Vec<(AccountId, SessionKeys)>) {
+	for (validator, keys) in &validators {
+		if !System::account_exists(validator) {
+			System::inc_providers(validator);
+		}
+		if let Err(error) = System::inc_consumers(validator) {
+			log::warn!(target: LOG_TARGET, "Validator {validator:?} has no providers: {error:?}");
+		}
+		for id in SessionKeys::key_ids() {
+			pezpallet_session::KeyOwner::<Runtime>::insert(
+				(*id, keys.get_raw(*id).to_vec()),
+				validator,
+			);
+		}
+		pezpallet_session::NextKeys::<Runtime>::insert(validator, keys);
+	}
+	pezpallet_session::Validators::<Runtime>::put(
+		validators.iter().map(|(validator, _)| validator.clone()).collect::<Vec<_>>(),
+	);
+	pezpallet_session::QueuedKeys::<Runtime>::put(validators);
+
+	let current = pezpallet_session::CurrentIndex::<Runtime>::get();
+	for index in [current, current + 1] {
+		<SessionManagerOf as SessionManager<AccountId>>::new_session_genesis(index);
+	}
+}
+
+#[cfg(not(feature = "babe"))]
+fn authoring_authorities() -> Vec<BlockAuthorityId> {
+	pezpallet_aura::Authorities::<Runtime>::get().into_inner()
+}
+
+#[cfg(feature = "babe")]
+fn authoring_authorities() -> Vec<BlockAuthorityId> {
+	crate::Babe::authorities().into_iter().map(|(id, _)| id).collect()
+}
+
+#[cfg(not(feature = "babe"))]
+fn session_keys(aura: BlockAuthorityId, grandpa: GrandpaId) -> SessionKeys {
+	SessionKeys { aura, grandpa }
+}
+
+#[cfg(feature = "babe")]
+fn session_keys(babe: BlockAuthorityId, grandpa: GrandpaId) -> SessionKeys {
+	SessionKeys { babe, grandpa }
+}
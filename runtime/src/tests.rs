@@ -0,0 +1,271 @@
+use crate::{
+	genesis_config_presets, migrations::SeedSessionFromAuthorities, Grandpa, Historical, Runtime,
+	RuntimeCall, RuntimeGenesisConfig, RuntimeOrigin, Session, System, UncheckedExtrinsic,
+};
+use codec::Decode;
+use pezframe_support::{
+	assert_ok,
+	genesis_builder_helper::build_state,
+	traits::{KeyOwnerProofSystem, OnRuntimeUpgrade},
+};
+use pezpallet_session::historical::HistoricalSessions;
+use pezsp_consensus_grandpa::{
+	AuthorityId as GrandpaId, Equivocation, EquivocationProof, RoundNumber, SetId, KEY_TYPE,
+};
+use pezsp_core::{
+	offchain::{testing::TestTransactionPoolExt, TransactionPoolExt},
+	H256,
+};
+use pezsp_keyring::Ed25519Keyring;
+
+// Build the state of the `local_testnet` preset, with Alice and Bob as validators.
+fn new_test_ext() -> pezsp_io::TestExternalities {
+	let mut ext = pezsp_io::TestExternalities::new_empty();
+	ext.execute_with(|| {
+		let preset = genesis_config_presets::get_preset(
+			&pezsp_genesis_builder::LOCAL_TESTNET_RUNTIME_PRESET.into(),
+		)
+		.expect("local testnet preset exists");
+		build_state::<RuntimeGenesisConfig>(preset).expect("preset builds");
+		System::set_block_number(1);
+	});
+	ext
+}
+
+// Two conflicting prevotes of `offender` in the given round.
+fn equivocation_proof(
+	set_id: SetId,
+	round: RoundNumber,
+	offender: Ed25519Keyring,
+) -> EquivocationProof<H256, u32> {
+	let signed_prevote = |target_hash| {
+		let prevote = finality_grandpa::Prevote { target_hash, target_number: 1 };
+		let message = finality_grandpa::Message::Prevote(prevote.clone());
+		let payload = pezsp_consensus_grandpa::localized_payload(round, set_id, &message);
+		(prevote, offender.sign(&payload).into())
+	};
+
+	EquivocationProof::new(
+		set_id,
+		Equivocation::Prevote(finality_grandpa::Equivocation {
+			round_number: round,
+			identity: offender.public().into(),
+			first: signed_prevote(H256::repeat_byte(1)),
+			second: signed_prevote(H256::repeat_byte(2)),
+		}),
+	)
+}
+
+#[test]
+fn grandpa_equivocation_disables_the_offender() {
+	new_test_ext().execute_with(|| {
+		let offender = Ed25519Keyring::Bob;
+		let proof = equivocation_proof(Grandpa::current_set_id(), 1, offender);
+		let key_owner_proof =
+			Historical::prove((KEY_TYPE, GrandpaId::from(offender.public()))).unwrap();
+
+		assert_ok!(Grandpa::report_equivocation_unsigned(
+			RuntimeOrigin::none(),
+			Box::new(proof.clone()),
+			key_owner_proof.clone(),
+		));
+		// Bob is the second validator.
+		assert_eq!(Session::disabled_validators(), vec![1]);
+
+		// The same equivocation can't be reported twice.
+		assert!(Grandpa::report_equivocation_unsigned(
+			RuntimeOrigin::none(),
+			Box::new(proof),
+			key_owner_proof,
+		)
+		.is_err());
+	});
+}
+
+#[test]
+fn grandpa_equivocation_report_is_submitted_to_the_pool() {
+	let (pool, pool_state) = TestTransactionPoolExt::new();
+	let mut ext = new_test_ext();
+	ext.register_extension(TransactionPoolExt::new(pool));
+
+	ext.execute_with(|| {
+		let offender = Ed25519Keyring::Alice;
+		let proof = equivocation_proof(Grandpa::current_set_id(), 1, offender);
+
+		// This is what the `GrandpaApi` runtime API does on behalf of the node's voter.
+		let key_owner_proof =
+			Historical::prove((KEY_TYPE, GrandpaId::from(offender.public()))).unwrap();
+		assert_eq!(
+			Grandpa::submit_unsigned_equivocation_report(proof.clone(), key_owner_proof),
+			Some(())
+		);
+
+		let transactions = &pool_state.read().transactions;
+		assert_eq!(transactions.len(), 1);
+		let extrinsic = UncheckedExtrinsic::decode(&mut &transactions[0][..]).unwrap();
+		assert!(matches!(
+			extrinsic.function,
+			RuntimeCall::Grandpa(pezpallet_grandpa::Call::report_equivocation_unsigned {
+				equivocation_proof,
+				..
+			}) if *equivocation_proof == proof
+		));
+	});
+}
+
+#[test]
+fn key_ownership_proofs_are_generated_for_session_keys() {
+	new_test_ext().execute_with(|| {
+		let authority = GrandpaId::from(Ed25519Keyring::Alice.public());
+		let proof = Historical::prove((KEY_TYPE, authority.clone())).unwrap();
+
+		assert!(Historical::check_proof((KEY_TYPE, authority), proof).is_some());
+		assert!(Historical::prove((KEY_TYPE, Ed25519Keyring::Charlie.public().into())).is_none());
+	});
+}
+
+#[test]
+fn genesis_sessions_have_a_historical_root() {
+	new_test_ext().execute_with(|| {
+		assert!(HistoricalSessions::<Runtime>::get(0).is_some());
+		assert!(HistoricalSessions::<Runtime>::get(1).is_some());
+	});
+}
+
+#[test]
+fn session_is_seeded_from_the_authorities() {
+	new_test_ext().execute_with(|| {
+		let validators = pezpallet_session::Validators::<Runtime>::get();
+		let queued_keys = pezpallet_session::QueuedKeys::<Runtime>::get();
+		let roots = (HistoricalSessions::<Runtime>::get(0), HistoricalSessions::<Runtime>::get(1));
+
+		// Forget everything but the authorities, like a chain that ran without the session pezpallet.
+		let _ = pezpallet_session::NextKeys::<Runtime>::clear(u32::MAX, None);
+		let _ = pezpallet_session::KeyOwner::<Runtime>::clear(u32::MAX, None);
+		let _ = HistoricalSessions::<Runtime>::clear(u32::MAX, None);
+		pezpallet_session::Validators::<Runtime>::kill();
+		pezpallet_session::QueuedKeys::<Runtime>::kill();
+
+		SeedSessionFromAuthorities::on_runtime_upgrade();
+
+		assert_eq!(pezpallet_session::Validators::<Runtime>::get(), validators);
+		assert_eq!(pezpallet_session::QueuedKeys::<Runtime>::get(), queued_keys);
+		assert_eq!(
+			(HistoricalSessions::<Runtime>::get(0), HistoricalSessions::<Runtime>::get(1)),
+			roots
+		);
+		let authority = GrandpaId::from(Ed25519Keyring::Bob.public());
+		let proof = Historical::prove((KEY_TYPE, authority.clone())).unwrap();
+		assert!(Historical::check_proof((KEY_TYPE, authority), proof).is_some());
+
+		// Once seeded, the session pezpallet is left alone.
+		pezpallet_session::NextKeys::<Runtime>::remove(&validators[0]);
+		SeedSessionFromAuthorities::on_runtime_upgrade();
+		assert!(pezpallet_session::NextKeys::<Runtime>::get(&validators[0]).is_none());
+	});
+}
+
+#[cfg(not(feature = "babe"))]
+mod aura {
+	use super::*;
+	use crate::{AuraEquivocation, Header, Runtime};
+	use pezframe_support::traits::DisabledValidators;
+	use pezsp_consensus_aura::{
+		digests::CompatibleDigestItem,
+		sr25519::{AuthorityId as AuraId, AuthoritySignature as AuraSignature},
+	};
+	use pezsp_keyring::Sr25519Keyring;
+	use pezsp_runtime::{traits::Header as _, Digest, DigestItem, RuntimeAppPublic};
+
+	// A header of block `number` authored by `author` in `slot`, sealed like Aura does.
+	fn sealed_header(author: Sr25519Keyring, slot: u64, number: u32) -> Header {
+		let pre_digest =
+			<DigestItem as CompatibleDigestItem<AuraSignature>>::aura_pre_digest(slot.into());
+		let mut header = Header::new(
+			number,
+			Default::default(),
+			Default::default(),
+			Default::default(),
+			Digest { logs: vec![pre_digest] },
+		);
+		let signature = AuraSignature::from(author.sign(header.hash().as_ref()));
+		header
+			.digest_mut()
+			.push(<DigestItem as CompatibleDigestItem<AuraSignature>>::aura_seal(signature));
+		header
+	}
+
+	#[test]
+	fn aura_equivocation_disables_the_offender() {
+		new_test_ext().execute_with(|| {
+			let offender = AuraId::from(Sr25519Keyring::Bob.public());
+			let proof = pezpallet_aura_equivocation::EquivocationProof {
+				offender: offender.clone(),
+				slot: 7.into(),
+				first_header: sealed_header(Sr25519Keyring::Bob, 7, 1),
+				second_header: sealed_header(Sr25519Keyring::Bob, 7, 2),
+			};
+			let key_owner_proof = Historical::prove((AuraId::ID, offender)).unwrap();
+
+			assert_ok!(AuraEquivocation::report_equivocation_unsigned(
+				RuntimeOrigin::none(),
+				Box::new(proof.clone()),
+				key_owner_proof.clone(),
+			));
+			// Bob is the second validator, and Aura no longer accepts his blocks.
+			assert_eq!(Session::disabled_validators(), vec![1]);
+			assert!(<Runtime as pezpallet_aura::Config>::DisabledValidators::is_disabled(1));
+			assert!(!<Runtime as pezpallet_aura::Config>::DisabledValidators::is_disabled(0));
+
+			// The same equivocation can't be reported twice.
+			assert!(AuraEquivocation::report_equivocation_unsigned(
+				RuntimeOrigin::none(),
+				Box::new(proof),
+				key_owner_proof,
+			)
+			.is_err());
+		});
+	}
+
+	#[test]
+	fn aura_equivocation_needs_two_different_blocks() {
+		new_test_ext().execute_with(|| {
+			let offender = AuraId::from(Sr25519Keyring::Bob.public());
+			let header = sealed_header(Sr25519Keyring::Bob, 7, 1);
+			let proof = pezpallet_aura_equivocation::EquivocationProof {
+				offender: offender.clone(),
+				slot: 7.into(),
+				first_header: header.clone(),
+				second_header: header,
+			};
+			let key_owner_proof = Historical::prove((AuraId::ID, offender)).unwrap();
+
+			assert!(AuraEquivocation::report_equivocation_unsigned(
+				RuntimeOrigin::none(),
+				Box::new(proof),
+				key_owner_proof,
+			)
+			.is_err());
+			assert!(Session::disabled_validators().is_empty());
+		});
+	}
+}
+
+#[cfg(feature = "babe")]
+mod babe {
+	use super::*;
+	use crate::{Babe, BABE_GENESIS_EPOCH_CONFIG};
+	use pezsp_keyring::Sr25519Keyring;
+
+	#[test]
+	fn babe_authorities_come_from_the_session_keys() {
+		new_test_ext().execute_with(|| {
+			let authorities: Vec<_> = Babe::authorities().into_iter().map(|(id, _)| id).collect();
+			assert_eq!(
+				authorities,
+				vec![Sr25519Keyring::Alice.public().into(), Sr25519Keyring::Bob.public().into()]
+			);
+			assert_eq!(Babe::epoch_config(), Some(BABE_GENESIS_EPOCH_CONFIG));
+		});
+	}
+}